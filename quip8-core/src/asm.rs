@@ -0,0 +1,775 @@
+//! Assembler for the mnemonics printed by [`Opcode::mnemonic`] and listed by
+//! `quip8-disasm`.
+//!
+//! One statement per line; `;` starts a comment. Operands are separated by
+//! commas:
+//!
+//! ```text
+//! sprite_x = 12              ; constant (also: sprite_x equ 12)
+//! main:                      ; label
+//!     LOAD V0, sprite_x
+//!     LOADI ball
+//!     DRAW V0, V1, 4
+//!     JUMP main
+//! ball:
+//!     db 0x60, 0xF0, 0xF0, 0x60
+//!     dw 0x1234, ball + 2    ; big-endian words
+//!     include "font.asm"     ; path relative to this file
+//!     org 0x300              ; continue assembling at 0x300
+//! ```
+//!
+//! Numbers are decimal, `0x`/`$` hex or `0b`/`%` binary, and expressions may
+//! add and subtract numbers, labels and constants. `db` also takes strings.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::decode::{Address, Opcode};
+use crate::memory::{FIRST_INSTRUCTION_ADDRESS, XO_CHIP_MEMORY_SIZE};
+
+/// How deeply `include`s may nest before assuming a cycle.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+const MNEMONICS: &[&str] = &[
+    "SYS", "CLR", "RTS", "SCD", "SCU", "SCR", "SCL", "EXIT", "LOW", "HIGH", "JUMP", "CALL", "SKE",
+    "SKNE", "SKRE", "STORER", "READR", "LOAD", "ADD", "MOVE", "OR", "AND", "XOR", "ADDR", "SUB",
+    "SHR", "RSUB", "SHL", "SKRNE", "LOADI", "LOADIL", "JUMPI", "RAND", "DRAW", "SKPR", "SKUP",
+    "MOVED", "KEYD", "LOADD", "LOADS", "PLANE", "AUDIO", "PITCH", "ADDI", "LDSPR", "LDBSPR", "BCD",
+    "STORE", "READ", "STOREF", "READF",
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmError {
+    pub file: String,
+    /// 1-based.
+    pub line: usize,
+    /// 1-based, in characters.
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.file, self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// Assembles `source` into a ROM image to be loaded at
+/// `FIRST_INSTRUCTION_ADDRESS`. Includes are resolved relative to the
+/// current directory. Returns every error found.
+pub fn assemble(source: &str) -> Result<Vec<u8>, Vec<AsmError>> {
+    let mut assembler = Assembler::new();
+    assembler.load_source(source, "<input>", Path::new(""), 0);
+    assembler.finish()
+}
+
+/// Assembles the file at `path`, resolving includes relative to it.
+pub fn assemble_file(path: &Path) -> Result<Vec<u8>, Vec<AsmError>> {
+    let mut assembler = Assembler::new();
+    if let Err(message) = assembler.load_file(path, 0) {
+        return Err(vec![AsmError {
+            file: path.display().to_string(),
+            line: 1,
+            column: 1,
+            message,
+        }]);
+    }
+    assembler.finish()
+}
+
+#[derive(Clone, Debug)]
+struct Location {
+    file: std::rc::Rc<str>,
+    line: usize,
+    column: usize,
+}
+
+impl Location {
+    fn error(&self, message: impl Into<String>) -> AsmError {
+        AsmError {
+            file: self.file.to_string(),
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+    }
+
+    fn at(&self, column: usize) -> Location {
+        Location {
+            column,
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TokenKind {
+    Ident,
+    Number(i64),
+    Str(String),
+    Punct(char),
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    column: usize,
+}
+
+fn parse_number(text: &str) -> Option<i64> {
+    let (digits, radix) = if let Some(hex) = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .or_else(|| text.strip_prefix('$'))
+    {
+        (hex, 16)
+    } else if let Some(binary) = text
+        .strip_prefix("0b")
+        .or_else(|| text.strip_prefix("0B"))
+        .or_else(|| text.strip_prefix('%'))
+    {
+        (binary, 2)
+    } else {
+        (text, 10)
+    };
+    i64::from_str_radix(&digits.replace('_', ""), radix).ok()
+}
+
+fn tokenize(line: &str, location: &Location) -> Result<Vec<Token>, AsmError> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == ';' {
+            break;
+        } else if c == '"' {
+            let mut value = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(location.at(column).error("unterminated string")),
+                    Some('"') => break,
+                    Some('\\') => {
+                        value.push(match chars.get(i + 1) {
+                            Some('n') => '\n',
+                            Some('0') => '\0',
+                            Some(&escaped) => escaped,
+                            None => return Err(location.at(column).error("unterminated string")),
+                        });
+                        i += 2;
+                    }
+                    Some(&c) => {
+                        value.push(c);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            tokens.push(Token {
+                kind: TokenKind::Str(value),
+                text: chars[column - 1..i].iter().collect(),
+                column,
+            });
+        } else if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$' | '%') {
+            let start = i;
+            i += 1;
+            while i < chars.len()
+                && (chars[i].is_ascii_alphanumeric() || matches!(chars[i], '_' | '.'))
+            {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let kind = if c.is_ascii_digit() || matches!(c, '$' | '%') {
+                match parse_number(&text) {
+                    Some(value) => TokenKind::Number(value),
+                    None => {
+                        return Err(location
+                            .at(column)
+                            .error(format!("invalid number '{text}'")))
+                    }
+                }
+            } else {
+                TokenKind::Ident
+            };
+            tokens.push(Token { kind, text, column });
+        } else if matches!(c, ',' | ':' | '=' | '+' | '-') {
+            tokens.push(Token {
+                kind: TokenKind::Punct(c),
+                text: c.to_string(),
+                column,
+            });
+            i += 1;
+        } else {
+            return Err(location
+                .at(column)
+                .error(format!("unexpected character '{c}'")));
+        }
+    }
+    Ok(tokens)
+}
+
+/// A sum of terms, each a number or a symbol.
+#[derive(Clone, Debug)]
+struct Expr {
+    terms: Vec<(i64, Term)>,
+    location: Location,
+}
+
+#[derive(Clone, Debug)]
+enum Term {
+    Number(i64),
+    Symbol(String, Location),
+}
+
+#[derive(Clone, Debug)]
+enum Operand {
+    Register(u8),
+    Expr(Expr),
+    Str(String),
+}
+
+fn register(text: &str) -> Option<u8> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('v' | 'V'), Some(digit), None) => digit.to_digit(16).map(|r| r as u8),
+        _ => None,
+    }
+}
+
+fn parse_operand(tokens: &[Token], location: &Location) -> Result<Operand, AsmError> {
+    let Some(first) = tokens.first() else {
+        return Err(location.error("missing operand"));
+    };
+    let at_first = location.at(first.column);
+    if tokens.len() == 1 {
+        match &first.kind {
+            TokenKind::Ident => {
+                if let Some(register) = register(&first.text) {
+                    return Ok(Operand::Register(register));
+                }
+            }
+            TokenKind::Str(value) => return Ok(Operand::Str(value.clone())),
+            _ => {}
+        }
+    }
+
+    let mut terms = Vec::new();
+    let mut sign = 1;
+    let mut expect_term = true;
+    for token in tokens {
+        let at = location.at(token.column);
+        match (&token.kind, expect_term) {
+            (TokenKind::Punct('-'), true) => sign = -sign,
+            (TokenKind::Punct('+'), true) => {}
+            (TokenKind::Number(value), true) => {
+                terms.push((sign, Term::Number(*value)));
+                expect_term = false;
+            }
+            (TokenKind::Ident, true) if register(&token.text).is_none() => {
+                terms.push((sign, Term::Symbol(token.text.clone(), at)));
+                expect_term = false;
+            }
+            (TokenKind::Punct('+'), false) => {
+                sign = 1;
+                expect_term = true;
+            }
+            (TokenKind::Punct('-'), false) => {
+                sign = -1;
+                expect_term = true;
+            }
+            _ => return Err(at.error(format!("unexpected '{}'", token.text))),
+        }
+    }
+    if expect_term {
+        let last = tokens.last().unwrap();
+        return Err(location
+            .at(last.column)
+            .error("expression ends with an operator"));
+    }
+    Ok(Operand::Expr(Expr {
+        terms,
+        location: at_first,
+    }))
+}
+
+#[derive(Clone, Debug)]
+enum StatementKind {
+    Instruction {
+        mnemonic: String,
+        operands: Vec<Operand>,
+    },
+    Data {
+        word: bool,
+        items: Vec<Operand>,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct Statement {
+    address: usize,
+    location: Location,
+    kind: StatementKind,
+}
+
+#[derive(Clone, Debug)]
+enum Symbol {
+    Label(usize),
+    Constant(Expr),
+}
+
+#[derive(Default)]
+struct Assembler {
+    statements: Vec<Statement>,
+    symbols: HashMap<String, (Symbol, Location)>,
+    errors: Vec<AsmError>,
+    address: usize,
+    end: usize,
+}
+
+impl Assembler {
+    fn new() -> Assembler {
+        Assembler {
+            address: FIRST_INSTRUCTION_ADDRESS as usize,
+            end: FIRST_INSTRUCTION_ADDRESS as usize,
+            ..Assembler::default()
+        }
+    }
+
+    fn load_file(&mut self, path: &Path, depth: usize) -> Result<(), String> {
+        let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        self.load_source(&source, &path.display().to_string(), dir, depth);
+        Ok(())
+    }
+
+    fn load_source(&mut self, source: &str, file: &str, dir: &Path, depth: usize) {
+        let file: std::rc::Rc<str> = file.into();
+        for (index, line) in source.lines().enumerate() {
+            let location = Location {
+                file: file.clone(),
+                line: index + 1,
+                column: 1,
+            };
+            if let Err(error) = self.load_line(line, &location, dir, depth) {
+                self.errors.push(error);
+            }
+        }
+    }
+
+    fn define(
+        &mut self,
+        name: &Token,
+        symbol: Symbol,
+        location: &Location,
+    ) -> Result<(), AsmError> {
+        let at = location.at(name.column);
+        if register(&name.text).is_some() {
+            return Err(at.error(format!("'{}' is a register name", name.text)));
+        }
+        if let Some((_, previous)) = self.symbols.get(&name.text) {
+            return Err(at.error(format!(
+                "'{}' is already defined at {}:{}",
+                name.text, previous.line, previous.column
+            )));
+        }
+        self.symbols.insert(name.text.clone(), (symbol, at));
+        Ok(())
+    }
+
+    fn load_line(
+        &mut self,
+        line: &str,
+        location: &Location,
+        dir: &Path,
+        depth: usize,
+    ) -> Result<(), AsmError> {
+        let mut tokens = &tokenize(line, location)?[..];
+
+        // Labels, any number of them before the statement.
+        while let [name, Token {
+            kind: TokenKind::Punct(':'),
+            ..
+        }, rest @ ..] = tokens
+        {
+            if name.kind != TokenKind::Ident {
+                return Err(location.at(name.column).error("expected a label name"));
+            }
+            self.define(name, Symbol::Label(self.address), location)?;
+            tokens = rest;
+        }
+
+        let Some((head, rest)) = tokens.split_first() else {
+            return Ok(());
+        };
+        if head.kind != TokenKind::Ident {
+            return Err(location
+                .at(head.column)
+                .error(format!("expected an instruction, found '{}'", head.text)));
+        }
+
+        // Constants: `name = expr` or `name equ expr`.
+        if let Some((separator, value)) = rest.split_first() {
+            if separator.kind == TokenKind::Punct('=') || separator.text.eq_ignore_ascii_case("equ")
+            {
+                let Operand::Expr(expr) = parse_operand(value, &location.at(separator.column))?
+                else {
+                    return Err(location
+                        .at(separator.column)
+                        .error("a constant must be a number or expression"));
+                };
+                return self.define(head, Symbol::Constant(expr), location);
+            }
+        }
+
+        let mut operands = Vec::new();
+        if !rest.is_empty() {
+            for group in rest.split(|t| t.kind == TokenKind::Punct(',')) {
+                operands.push(parse_operand(group, &location.at(head.column))?);
+            }
+        }
+
+        let statement_location = location.at(head.column);
+        let mnemonic = head.text.to_ascii_uppercase();
+        let size = match mnemonic.as_str() {
+            "DB" | "DW" => {
+                let word = mnemonic == "DW";
+                let mut size = 0;
+                for operand in &operands {
+                    size += match (operand, word) {
+                        (Operand::Str(value), false) => value.len(),
+                        (Operand::Expr(_), false) => 1,
+                        (Operand::Expr(_), true) => 2,
+                        _ => {
+                            return Err(statement_location
+                                .error(format!("{} takes numbers or expressions", head.text)))
+                        }
+                    };
+                }
+                self.statements.push(Statement {
+                    address: self.address,
+                    location: statement_location,
+                    kind: StatementKind::Data {
+                        word,
+                        items: operands,
+                    },
+                });
+                size
+            }
+            "ORG" => {
+                let [operand @ Operand::Expr(_)] = &operands[..] else {
+                    return Err(statement_location.error("ORG takes one address"));
+                };
+                let max = XO_CHIP_MEMORY_SIZE as i64;
+                let address = self.value(operand, max, "address", &statement_location)? as usize;
+                if address < self.address {
+                    return Err(statement_location
+                        .error(format!("ORG {address:#05X} would move backwards")));
+                }
+                self.address = address;
+                0
+            }
+            "INCLUDE" => {
+                let [Operand::Str(path)] = &operands[..] else {
+                    return Err(statement_location.error("INCLUDE takes a quoted path"));
+                };
+                if depth >= MAX_INCLUDE_DEPTH {
+                    return Err(statement_location.error("includes nested too deeply"));
+                }
+                let path: PathBuf = dir.join(path);
+                self.load_file(&path, depth + 1)
+                    .map_err(|e| statement_location.error(format!("{}: {e}", path.display())))?;
+                0
+            }
+            _ => {
+                let size = if mnemonic == "LOADIL" { 4 } else { 2 };
+                self.statements.push(Statement {
+                    address: self.address,
+                    location: statement_location,
+                    kind: StatementKind::Instruction { mnemonic, operands },
+                });
+                size
+            }
+        };
+        self.address += size;
+        self.end = self.end.max(self.address);
+        if self.address > XO_CHIP_MEMORY_SIZE {
+            return Err(location.error("program does not fit in memory"));
+        }
+        Ok(())
+    }
+
+    fn evaluate(&self, expr: &Expr, depth: usize) -> Result<i64, AsmError> {
+        let mut value: i64 = 0;
+        for (sign, term) in &expr.terms {
+            let term = match term {
+                Term::Number(number) => *number,
+                Term::Symbol(name, location) => match self.symbols.get(name) {
+                    Some((Symbol::Label(address), _)) => *address as i64,
+                    Some((Symbol::Constant(constant), _)) if depth < MAX_INCLUDE_DEPTH => {
+                        self.evaluate(constant, depth + 1)?
+                    }
+                    Some((Symbol::Constant(_), _)) => {
+                        return Err(
+                            location.error(format!("'{name}' is defined in terms of itself"))
+                        )
+                    }
+                    None => return Err(location.error(format!("undefined symbol '{name}'"))),
+                },
+            };
+            value = sign
+                .checked_mul(term)
+                .and_then(|term| value.checked_add(term))
+                .ok_or_else(|| expr.location.error("value out of range"))?;
+        }
+        Ok(value)
+    }
+
+    fn value(
+        &self,
+        operand: &Operand,
+        max: i64,
+        what: &str,
+        location: &Location,
+    ) -> Result<i64, AsmError> {
+        let Operand::Expr(expr) = operand else {
+            return Err(location.error(format!("expected {what}")));
+        };
+        let value = self.evaluate(expr, 0)?;
+        if !(0..=max).contains(&value) {
+            return Err(expr
+                .location
+                .error(format!("{what} {value} is out of range (0 to {max:#X})")));
+        }
+        Ok(value)
+    }
+
+    fn instruction(
+        &self,
+        mnemonic: &str,
+        operands: &[Operand],
+        location: &Location,
+    ) -> Result<Opcode, AsmError> {
+        let register = |operand: &Operand| match operand {
+            Operand::Register(register) => Ok(*register),
+            _ => Err(location.error(format!("{mnemonic} expects a register (V0-VF)"))),
+        };
+        let address = |operand: &Operand| {
+            self.value(operand, 0xFFF, "address", location)
+                .map(|a| a as Address)
+        };
+        let long_address = |operand: &Operand| {
+            self.value(operand, 0xFFFF, "address", location)
+                .map(|a| a as Address)
+        };
+        let byte = |operand: &Operand| self.value(operand, 0xFF, "byte", location).map(|b| b as u8);
+        let nibble = |operand: &Operand| {
+            self.value(operand, 0xF, "nibble", location)
+                .map(|n| n as u8)
+        };
+
+        let opcode = match (mnemonic, operands) {
+            ("CLR", []) => Opcode::CLR,
+            ("RTS", []) => Opcode::RTS,
+            ("SCR", []) => Opcode::SCR,
+            ("SCL", []) => Opcode::SCL,
+            ("EXIT", []) => Opcode::EXIT,
+            ("LOW", []) => Opcode::LOW,
+            ("HIGH", []) => Opcode::HIGH,
+            ("AUDIO", []) => Opcode::AUDIO,
+            ("SYS", [a]) => Opcode::SYS(address(a)?),
+            ("JUMP", [a]) => Opcode::JUMP(address(a)?),
+            ("CALL", [a]) => Opcode::CALL(address(a)?),
+            ("LOADI", [a]) => Opcode::LOADI(address(a)?),
+            ("JUMPI", [a]) => Opcode::JUMPI(address(a)?),
+            ("LOADIL", [a]) => Opcode::LOADIL(long_address(a)?),
+            ("SCD", [n]) => Opcode::SCD(nibble(n)?),
+            ("SCU", [n]) => Opcode::SCU(nibble(n)?),
+            ("PLANE", [n]) => Opcode::PLANE(nibble(n)?),
+            ("SKE", [x, nn]) => Opcode::SKE((register(x)?, byte(nn)?)),
+            ("SKNE", [x, nn]) => Opcode::SKNE((register(x)?, byte(nn)?)),
+            ("LOAD", [x, nn]) => Opcode::LOAD((register(x)?, byte(nn)?)),
+            ("ADD", [x, nn]) => Opcode::ADD((register(x)?, byte(nn)?)),
+            ("RAND", [x, nn]) => Opcode::RAND((register(x)?, byte(nn)?)),
+            ("SKRE", [x, y]) => Opcode::SKRE((register(x)?, register(y)?)),
+            ("STORER", [x, y]) => Opcode::STORER((register(x)?, register(y)?)),
+            ("READR", [x, y]) => Opcode::READR((register(x)?, register(y)?)),
+            ("MOVE", [x, y]) => Opcode::MOVE((register(x)?, register(y)?)),
+            ("OR", [x, y]) => Opcode::OR((register(x)?, register(y)?)),
+            ("AND", [x, y]) => Opcode::AND((register(x)?, register(y)?)),
+            ("XOR", [x, y]) => Opcode::XOR((register(x)?, register(y)?)),
+            ("ADDR", [x, y]) => Opcode::ADDR((register(x)?, register(y)?)),
+            ("SUB", [x, y]) => Opcode::SUB((register(x)?, register(y)?)),
+            ("SHR", [x, y]) => Opcode::SHR((register(x)?, register(y)?)),
+            ("RSUB", [x, y]) => Opcode::RSUB((register(x)?, register(y)?)),
+            ("SHL", [x, y]) => Opcode::SHL((register(x)?, register(y)?)),
+            ("SKRNE", [x, y]) => Opcode::SKRNE((register(x)?, register(y)?)),
+            ("DRAW", [x, y, n]) => Opcode::DRAW((register(x)?, register(y)?, nibble(n)?)),
+            ("SKPR", [x]) => Opcode::SKPR(register(x)?),
+            ("SKUP", [x]) => Opcode::SKUP(register(x)?),
+            ("MOVED", [x]) => Opcode::MOVED(register(x)?),
+            ("KEYD", [x]) => Opcode::KEYD(register(x)?),
+            ("LOADD", [x]) => Opcode::LOADD(register(x)?),
+            ("LOADS", [x]) => Opcode::LOADS(register(x)?),
+            ("PITCH", [x]) => Opcode::PITCH(register(x)?),
+            ("ADDI", [x]) => Opcode::ADDI(register(x)?),
+            ("LDSPR", [x]) => Opcode::LDSPR(register(x)?),
+            ("LDBSPR", [x]) => Opcode::LDBSPR(register(x)?),
+            ("BCD", [x]) => Opcode::BCD(register(x)?),
+            ("STORE", [x]) => Opcode::STORE(register(x)?),
+            ("READ", [x]) => Opcode::READ(register(x)?),
+            ("STOREF", [x]) => Opcode::STOREF(register(x)?),
+            ("READF", [x]) => Opcode::READF(register(x)?),
+            _ if MNEMONICS.contains(&mnemonic) => {
+                return Err(location.error(format!(
+                    "wrong number of operands for {mnemonic} (got {})",
+                    operands.len()
+                )))
+            }
+            _ => return Err(location.error(format!("unknown instruction '{mnemonic}'"))),
+        };
+        Ok(opcode)
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, Vec<AsmError>> {
+        let origin = FIRST_INSTRUCTION_ADDRESS as usize;
+        let mut rom = vec![0; self.end.saturating_sub(origin)];
+        for statement in std::mem::take(&mut self.statements) {
+            let offset = statement.address - origin;
+            let bytes = match &statement.kind {
+                StatementKind::Instruction { mnemonic, operands } => self
+                    .instruction(mnemonic, operands, &statement.location)
+                    .map(|opcode| opcode.encode()),
+                StatementKind::Data { word, items } => items
+                    .iter()
+                    .map(|item| match (item, word) {
+                        (Operand::Str(value), false) => Ok(value.as_bytes().to_vec()),
+                        (_, false) => self
+                            .signed_value(item, 0xFF, "byte", &statement.location)
+                            .map(|b| vec![b as u8]),
+                        (_, true) => self
+                            .signed_value(item, 0xFFFF, "word", &statement.location)
+                            .map(|w| (w as u16).to_be_bytes().to_vec()),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(|items| items.concat()),
+            };
+            match bytes {
+                Ok(bytes) => rom[offset..offset + bytes.len()].copy_from_slice(&bytes),
+                Err(error) => self.errors.push(error),
+            }
+        }
+        if self.errors.is_empty() {
+            Ok(rom)
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    /// Like `value` but also accepts negative numbers down to `-(max + 1) / 2`,
+    /// stored as two's complement.
+    fn signed_value(
+        &self,
+        operand: &Operand,
+        max: i64,
+        what: &str,
+        location: &Location,
+    ) -> Result<i64, AsmError> {
+        let Operand::Expr(expr) = operand else {
+            return Err(location.error(format!("expected {what}")));
+        };
+        let value = self.evaluate(expr, 0)?;
+        if !(-(max + 1) / 2..=max).contains(&value) {
+            return Err(expr
+                .location
+                .error(format!("{what} {value} is out of range")));
+        }
+        Ok(value & max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::{Disassembly, Syntax};
+
+    fn listing(disassembly: &Disassembly) -> String {
+        let mut out = Vec::new();
+        disassembly.write(&mut out, Syntax::Quip8).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn disassembly_assembles_back_to_the_rom() {
+        // Code, a subroutine, sprite data reached through LOADI, a long load
+        // and trailing bytes that aren't valid instructions.
+        let rom = [
+            0x00, 0xE0, 0x60, 0x05, 0x61, 0x0A, 0xA2, 0x12, 0x22, 0x0E, 0xD0, 0x14, 0x12, 0x0A,
+            0x81, 0x0E, 0xF0, 0x00, 0x12, 0x34, 0x00, 0xEE, 0x3C, 0x7E, 0xFF, 0xFF, 0x5A, 0x01,
+        ];
+        for disassembly in [Disassembly::new(&rom), Disassembly::linear(&rom)] {
+            assert_eq!(assemble(&listing(&disassembly)), Ok(rom.to_vec()));
+        }
+    }
+
+    #[test]
+    fn labels_constants_and_data() {
+        let source = "
+            speed equ 3
+            start: LOAD v1, speed + 1
+                   loadi sprite
+                   jump start
+            sprite: db 0b1111_0000, $90, \"AB\"
+                    dw -2, sprite
+        ";
+        assert_eq!(
+            assemble(source),
+            Ok(vec![
+                0x61, 0x04, 0xA2, 0x06, 0x12, 0x00, 0xF0, 0x90, b'A', b'B', 0xFF, 0xFE, 0x02, 0x06
+            ])
+        );
+    }
+
+    #[test]
+    fn errors_carry_their_position() {
+        let errors = assemble("CLR\n  LOAD V0, 0x100\n  JUMP nowhere\n  FROB\n").unwrap_err();
+        let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            errors,
+            [
+                "<input>:2:12: byte 256 is out of range (0 to 0xFF)",
+                "<input>:3:8: undefined symbol 'nowhere'",
+                "<input>:4:3: unknown instruction 'FROB'",
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_addresses_and_values_out_of_range() {
+        let errors = assemble(
+            "ORG -1\nORG 0x10001\ndb 0x7FFFFFFFFFFFFFFF + 1\ndb 0 - 0x7FFFFFFFFFFFFFFF - 2\n",
+        )
+        .unwrap_err();
+        let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            errors,
+            [
+                "<input>:1:5: address -1 is out of range (0 to 0x10000)",
+                "<input>:2:5: address 65537 is out of range (0 to 0x10000)",
+                "<input>:3:4: value out of range",
+                "<input>:4:4: value out of range",
+            ]
+        );
+    }
+}
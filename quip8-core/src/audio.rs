@@ -0,0 +1,339 @@
+use std::f32::consts::TAU;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::clock::TIMER_HZ;
+
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+
+/// Destination for the mono `f32` samples (in -1.0..=1.0) produced by the
+/// [`Beeper`].
+pub trait AudioSink {
+    fn sample_rate(&self) -> u32;
+    fn push_samples(&mut self, samples: &[f32]);
+}
+
+/// Discards everything. For headless runs and machines without audio devices.
+pub struct NullSink;
+
+impl AudioSink for NullSink {
+    fn sample_rate(&self) -> u32 {
+        DEFAULT_SAMPLE_RATE
+    }
+
+    fn push_samples(&mut self, _samples: &[f32]) {}
+}
+
+/// Records audio to a 16-bit PCM mono WAV. The header's length fields are
+/// patched in when the sink is finished or dropped.
+pub struct WavSink<W: Write + Seek> {
+    writer: W,
+    sample_rate: u32,
+    data_len: u32,
+    error: Option<io::Error>,
+}
+
+impl WavSink<BufWriter<File>> {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), DEFAULT_SAMPLE_RATE)
+    }
+}
+
+impl<W: Write + Seek> WavSink<W> {
+    pub fn new(mut writer: W, sample_rate: u32) -> io::Result<Self> {
+        Self::write_header(&mut writer, sample_rate, 0)?;
+        Ok(Self {
+            writer,
+            sample_rate,
+            data_len: 0,
+            error: None,
+        })
+    }
+
+    fn write_header(writer: &mut W, sample_rate: u32, data_len: u32) -> io::Result<()> {
+        const CHANNELS: u16 = 1;
+        const BITS_PER_SAMPLE: u16 = 16;
+        let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&(36 + data_len).to_le_bytes())?;
+        writer.write_all(b"WAVEfmt ")?;
+        writer.write_all(&16u32.to_le_bytes())?;
+        writer.write_all(&1u16.to_le_bytes())?; // PCM
+        writer.write_all(&CHANNELS.to_le_bytes())?;
+        writer.write_all(&sample_rate.to_le_bytes())?;
+        writer.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+        writer.write_all(&block_align.to_le_bytes())?;
+        writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+        writer.write_all(b"data")?;
+        writer.write_all(&data_len.to_le_bytes())
+    }
+
+    /// Patches the header and flushes, reporting the first write error if any
+    /// occurred while pushing samples.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.finalize()
+    }
+
+    fn finalize(&mut self) -> io::Result<()> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        let end = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(0))?;
+        Self::write_header(&mut self.writer, self.sample_rate, self.data_len)?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()
+    }
+}
+
+impl<W: Write + Seek> AudioSink for WavSink<W> {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn push_samples(&mut self, samples: &[f32]) {
+        if self.error.is_some() {
+            return;
+        }
+        for sample in samples {
+            let pcm = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            if let Err(error) = self.writer.write_all(&pcm.to_le_bytes()) {
+                self.error = Some(error);
+                return;
+            }
+            self.data_len += 2;
+        }
+    }
+}
+
+impl<W: Write + Seek> Drop for WavSink<W> {
+    fn drop(&mut self) {
+        let _ = self.finalize();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Waveform {
+    Square,
+    Triangle,
+    Sawtooth,
+    Sine,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AudioSettings {
+    /// 0.0 (silent) to 1.0 (full scale).
+    pub volume: f32,
+    pub muted: bool,
+    /// Tone frequency in Hz.
+    pub frequency: f32,
+    pub waveform: Waveform,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            volume: 0.25,
+            muted: false,
+            frequency: 440.0,
+            waveform: Waveform::Square,
+        }
+    }
+}
+
+/// XO-CHIP audio pattern to play in place of the configured tone: 128 1-bit
+/// samples, most significant bit first, looped at `rate` bits per second.
+pub struct Pattern<'a> {
+    pub bits: &'a [u8; 16],
+    pub rate: f32,
+}
+
+/// Tone generator for the sound timer. Each emulated frame it emits 1/60th
+/// of a second of samples into its sink: the configured tone (or XO-CHIP
+/// pattern) while the sound timer is running, silence otherwise.
+pub struct Beeper {
+    pub settings: AudioSettings,
+    sink: Box<dyn AudioSink>,
+    phase: f32,
+    // Position in the XO-CHIP pattern, in bits.
+    pattern_position: f32,
+    buffer: Vec<f32>,
+    // Samples owed to the next frame, in sixtieths of a sample.
+    sample_remainder: u32,
+}
+
+impl Beeper {
+    pub fn new(sink: Box<dyn AudioSink>, settings: AudioSettings) -> Self {
+        Self {
+            settings,
+            sink,
+            phase: 0.0,
+            pattern_position: 0.0,
+            buffer: Vec::new(),
+            sample_remainder: 0,
+        }
+    }
+
+    pub fn frame(&mut self, sound_active: bool, pattern: Option<Pattern>) {
+        let sample_rate = self.sink.sample_rate();
+        self.sample_remainder += sample_rate;
+        let samples = (self.sample_remainder / TIMER_HZ) as usize;
+        self.sample_remainder %= TIMER_HZ;
+
+        self.buffer.clear();
+        let amplitude = if self.settings.muted {
+            0.0
+        } else {
+            self.settings.volume.clamp(0.0, 1.0)
+        };
+        if !sound_active {
+            self.phase = 0.0;
+            self.buffer.resize(samples, 0.0);
+        } else if let Some(pattern) = pattern {
+            let step = pattern.rate / sample_rate as f32;
+            for _ in 0..samples {
+                let bit = self.pattern_position as usize;
+                let set = pattern.bits[bit / 8] & (0x80 >> (bit % 8)) != 0;
+                self.buffer.push(if set { amplitude } else { -amplitude });
+                self.pattern_position = (self.pattern_position + step) % 128.0;
+            }
+        } else {
+            let step = self.settings.frequency / sample_rate as f32;
+            for _ in 0..samples {
+                let sample = match self.settings.waveform {
+                    Waveform::Square => {
+                        if self.phase < 0.5 {
+                            1.0
+                        } else {
+                            -1.0
+                        }
+                    }
+                    Waveform::Triangle => 1.0 - 4.0 * (self.phase - 0.5).abs(),
+                    Waveform::Sawtooth => 2.0 * self.phase - 1.0,
+                    Waveform::Sine => (self.phase * TAU).sin(),
+                };
+                self.buffer.push(sample * amplitude);
+                self.phase = (self.phase + step).fract();
+            }
+        }
+        self.sink.push_samples(&self.buffer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// A file the test can still read after handing it to a [`WavSink`].
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Cursor<Vec<u8>>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for Shared {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.0.borrow_mut().seek(pos)
+        }
+    }
+
+    /// Collects samples at an arbitrary rate.
+    struct Collect(u32, Rc<RefCell<Vec<f32>>>);
+
+    impl AudioSink for Collect {
+        fn sample_rate(&self) -> u32 {
+            self.0
+        }
+
+        fn push_samples(&mut self, samples: &[f32]) {
+            self.1.borrow_mut().extend_from_slice(samples);
+        }
+    }
+
+    fn u32_at(wav: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(wav[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn u16_at(wav: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes(wav[offset..offset + 2].try_into().unwrap())
+    }
+
+    #[test]
+    fn records_tone_and_silence_to_wav() {
+        let file = Shared::default();
+        let sink = WavSink::new(file.clone(), DEFAULT_SAMPLE_RATE).unwrap();
+        let mut beeper = Beeper::new(Box::new(sink), AudioSettings::default());
+        for frame in 0..60 {
+            beeper.frame(frame < 30, None);
+        }
+        drop(beeper);
+
+        let wav = file.0.take().into_inner();
+        let data_len = 2 * DEFAULT_SAMPLE_RATE;
+        assert_eq!(wav.len(), 44 + data_len as usize);
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(u32_at(&wav, 4), 36 + data_len);
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(u32_at(&wav, 16), 16);
+        assert_eq!((u16_at(&wav, 20), u16_at(&wav, 22)), (1, 1));
+        assert_eq!(u32_at(&wav, 24), DEFAULT_SAMPLE_RATE);
+        assert_eq!(u32_at(&wav, 28), 2 * DEFAULT_SAMPLE_RATE);
+        assert_eq!((u16_at(&wav, 32), u16_at(&wav, 34)), (2, 16));
+        assert_eq!(&wav[36..40], b"data");
+        assert_eq!(u32_at(&wav, 40), data_len);
+
+        let samples: Vec<i16> = wav[44..]
+            .chunks(2)
+            .map(|pcm| i16::from_le_bytes([pcm[0], pcm[1]]))
+            .collect();
+        let (tone, silence) = samples.split_at(samples.len() / 2);
+        let full = (0.25 * i16::MAX as f32) as i16;
+        assert!(tone.iter().all(|&s| s == full || s == -full));
+        assert!(tone.contains(&-full));
+        assert!(silence.iter().all(|&s| s == 0));
+    }
+
+    #[test]
+    fn uneven_sample_rates_average_out() {
+        let samples = Rc::new(RefCell::new(Vec::new()));
+        let sink = Collect(22_050, samples.clone());
+        let mut beeper = Beeper::new(Box::new(sink), AudioSettings::default());
+        beeper.frame(true, None);
+        assert_eq!(samples.borrow().len(), 367);
+        for _ in 1..TIMER_HZ {
+            beeper.frame(false, None);
+        }
+        assert_eq!(samples.borrow().len(), 22_050);
+    }
+
+    #[test]
+    fn plays_patterns_bit_by_bit() {
+        let samples = Rc::new(RefCell::new(Vec::new()));
+        let sink = Collect(4000, samples.clone());
+        let settings = AudioSettings {
+            volume: 1.0,
+            ..AudioSettings::default()
+        };
+        let mut beeper = Beeper::new(Box::new(sink), settings);
+        let bits = [0b1010_0000, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let pattern = Pattern {
+            bits: &bits,
+            rate: 4000.0,
+        };
+        beeper.frame(true, Some(pattern));
+        assert_eq!(samples.borrow()[..4], [1.0, -1.0, 1.0, -1.0]);
+    }
+}
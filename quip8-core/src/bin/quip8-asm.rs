@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Default)]
+struct Args {
+    source: Option<PathBuf>,
+    output: Option<PathBuf>,
+}
+
+impl Args {
+    const USAGE: &'static str = "usage: quip8-asm [-o OUTPUT] SOURCE";
+
+    fn parse() -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-o" | "--output" => {
+                    let output = args.next().ok_or("-o needs an output path")?;
+                    parsed.output = Some(PathBuf::from(output));
+                }
+                "-h" | "--help" => {
+                    println!("{}", Self::USAGE);
+                    println!();
+                    println!("Assembles SOURCE into a ROM loaded at 0x200, written to OUTPUT or");
+                    println!("SOURCE with a .ch8 extension. Accepts the mnemonics quip8-disasm");
+                    println!("prints, so its listings assemble back to the original ROM.");
+                    std::process::exit(0);
+                }
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    return Err(format!("unknown option {arg}"))
+                }
+                _ => parsed.source = Some(PathBuf::from(arg)),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse() {
+        Ok(Args { source: None, .. }) => Err("no source file given".to_owned()),
+        result => result,
+    }
+    .unwrap_or_else(|e| {
+        eprintln!("{e}");
+        eprintln!("{}", Args::USAGE);
+        std::process::exit(2);
+    });
+    let source = args.source.unwrap();
+    let output = args.output.unwrap_or_else(|| source.with_extension("ch8"));
+
+    let rom = match quip8_core::assemble_file(&source) {
+        Ok(rom) => rom,
+        Err(errors) => {
+            for error in &errors {
+                eprintln!("{error}");
+            }
+            eprintln!("{} error(s); nothing written", errors.len());
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(e) = std::fs::write(&output, &rom) {
+        eprintln!("{}: {e}", output.display());
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
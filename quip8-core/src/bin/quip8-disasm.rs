@@ -0,0 +1,81 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use quip8_core::{Disassembly, Syntax};
+
+#[derive(Default)]
+struct Args {
+    rom: Option<PathBuf>,
+    syntax: Syntax,
+    linear: bool,
+}
+
+impl Args {
+    const USAGE: &'static str = "usage: quip8-disasm [--syntax quip8|octo] [--linear] ROM";
+
+    fn parse() -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--syntax" => {
+                    let syntax = args.next().ok_or("--syntax needs a syntax name")?;
+                    parsed.syntax = syntax.parse::<Syntax>().map_err(|e| e.to_string())?;
+                }
+                "--linear" => parsed.linear = true,
+                "-h" | "--help" => {
+                    println!("{}", Self::USAGE);
+                    println!();
+                    println!("Disassembles ROM to stdout. Code is found by following jumps, calls");
+                    println!("and skips from 0x200; everything else is listed as data. --linear");
+                    println!("decodes every word in order instead, listing words that aren't");
+                    println!("instructions as data.");
+                    std::process::exit(0);
+                }
+                _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+                _ => parsed.rom = Some(PathBuf::from(arg)),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse() {
+        Ok(Args { rom: None, .. }) => Err("no ROM given".to_owned()),
+        result => result,
+    }
+    .unwrap_or_else(|e| {
+        eprintln!("{e}");
+        eprintln!("{}", Args::USAGE);
+        std::process::exit(2);
+    });
+    let rom_path = args.rom.unwrap();
+
+    let rom = match std::fs::read(&rom_path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("{}: {e}", rom_path.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    let disassembly = if args.linear {
+        Disassembly::linear(&rom)
+    } else {
+        Disassembly::new(&rom)
+    };
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    match disassembly
+        .write(&mut out, args.syntax)
+        .and_then(|()| out.flush())
+    {
+        Ok(()) => ExitCode::SUCCESS,
+        // Piping into `head` and the like isn't an error.
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
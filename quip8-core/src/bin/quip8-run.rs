@@ -0,0 +1,478 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use quip8_core::clock::DEFAULT_INSTRUCTIONS_PER_SECOND;
+use quip8_core::image::{self, DEFAULT_PALETTE};
+use quip8_core::trace::parse_address_range;
+use quip8_core::{
+    octo, random_seed, Address, Chip8, Chip8Error, Desync, ErrorPolicy, Movie, MovieSession,
+    Opcode, QuirkProfile, RngAlgorithm, Scheduler, Speed, StepEvent, TraceFormat, Tracer,
+    FIRST_INSTRUCTION_ADDRESS, MEMORY_SIZE, XO_CHIP_MEMORY_SIZE,
+};
+
+/// Frames run when neither `--cycles` nor `--frames` is given: ten seconds.
+const DEFAULT_FRAMES: u64 = 600;
+
+/// Exit status when an `--until-*` condition was given but a limit ran out
+/// first.
+const NOT_REACHED: u8 = 3;
+/// Exit status when an instruction faulted under the halt policy.
+const FAULTED: u8 = 4;
+/// Exit status when a played movie's checksums stopped matching.
+const DESYNCED: u8 = 5;
+
+/// Keys held from `frame` on, one bit per key as in `Chip8::set_keys`.
+struct KeyEvent {
+    frame: u64,
+    keys: u16,
+}
+
+/// Parses events like `60:5 90: 120:4A`, separated by spaces, commas or new
+/// lines: from frame 60 key 5 is held, from 90 nothing, from 120 keys 4 and
+/// A. `#` starts a comment.
+fn parse_key_script(script: &str) -> Result<Vec<KeyEvent>, String> {
+    let mut events: Vec<KeyEvent> = Vec::new();
+    let words = script
+        .lines()
+        .map(|line| line.split('#').next().unwrap())
+        .flat_map(|line| line.split(|c: char| c == ',' || c.is_whitespace()))
+        .filter(|word| !word.is_empty());
+    for word in words {
+        let (frame, keys) = word
+            .split_once(':')
+            .ok_or_else(|| format!("key event '{word}' isn't FRAME:KEYS"))?;
+        let frame: u64 = frame
+            .parse()
+            .map_err(|_| format!("bad frame number in key event '{word}'"))?;
+        let keys = keys.chars().try_fold(0u16, |keys, key| {
+            key.to_digit(16)
+                .map(|key| keys | 1 << key)
+                .ok_or_else(|| format!("bad key '{key}' in key event '{word}'"))
+        })?;
+        if events.last().is_some_and(|last| last.frame > frame) {
+            return Err(format!("key event '{word}' is out of order"));
+        }
+        events.push(KeyEvent { frame, keys });
+    }
+    Ok(events)
+}
+
+fn parse_number<T: TryFrom<u64>>(option: &str, text: Option<String>) -> Result<T, String> {
+    let text = text.ok_or_else(|| format!("{option} needs a number"))?;
+    let value = match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    value
+        .ok()
+        .and_then(|value| T::try_from(value).ok())
+        .ok_or_else(|| format!("bad number '{text}' for {option}"))
+}
+
+#[derive(Default)]
+struct Args {
+    rom: Option<PathBuf>,
+    profile: QuirkProfile,
+    error_policy: ErrorPolicy,
+    rng: RngAlgorithm,
+    seed: Option<u64>,
+    ips: Option<u32>,
+    cycles: Option<u64>,
+    frames: Option<u64>,
+    until_pc: Option<Address>,
+    until_self_jump: bool,
+    keys: Vec<KeyEvent>,
+    record: Option<PathBuf>,
+    play: Option<PathBuf>,
+    ascii: bool,
+    registers: bool,
+    png: Option<PathBuf>,
+    pbm: Option<PathBuf>,
+    scale: Option<usize>,
+    trace: Option<PathBuf>,
+    trace_format: TraceFormat,
+    trace_range: Option<std::ops::RangeInclusive<Address>>,
+    trace_limit: Option<u64>,
+}
+
+impl Args {
+    const USAGE: &'static str = "usage: quip8-run [--quirks vip|chip48|schip|xochip] \
+                                 [--on-error halt|ignore|wrap] [--rng xorshift|vip] \
+                                 [--seed N] [--ips N] \
+                                 [--cycles N] [--frames N] [--until-pc ADDR] [--until-self-jump] \
+                                 [--keys SCRIPT] [--keys-file FILE] \
+                                 [--record MOVIE | --play MOVIE] [--ascii] [--registers] \
+                                 [--png FILE [--scale N]] [--pbm FILE] [--trace FILE \
+                                 [--trace-format text|json] [--trace-range START-END] \
+                                 [--trace-limit N]] ROM";
+
+    fn parse() -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--quirks" => {
+                    let profile = args.next().ok_or("--quirks needs a profile name")?;
+                    parsed.profile = profile.parse::<QuirkProfile>().map_err(|e| e.to_string())?;
+                }
+                "--on-error" => {
+                    let policy = args.next().ok_or("--on-error needs a policy name")?;
+                    parsed.error_policy =
+                        policy.parse::<ErrorPolicy>().map_err(|e| e.to_string())?;
+                }
+                "--rng" => {
+                    let algorithm = args.next().ok_or("--rng needs an algorithm name")?;
+                    parsed.rng = algorithm
+                        .parse::<RngAlgorithm>()
+                        .map_err(|e| e.to_string())?;
+                }
+                "--seed" => parsed.seed = Some(parse_number(&arg, args.next())?),
+                "--ips" => parsed.ips = Some(parse_number(&arg, args.next())?),
+                "--cycles" => parsed.cycles = Some(parse_number(&arg, args.next())?),
+                "--frames" => parsed.frames = Some(parse_number(&arg, args.next())?),
+                "--until-pc" => parsed.until_pc = Some(parse_number(&arg, args.next())?),
+                "--until-self-jump" => parsed.until_self_jump = true,
+                "--keys" => {
+                    let script = args.next().ok_or("--keys needs a key script")?;
+                    parsed.keys = parse_key_script(&script)?;
+                }
+                "--keys-file" => {
+                    let path = args.next().ok_or("--keys-file needs a file name")?;
+                    let script =
+                        std::fs::read_to_string(&path).map_err(|e| format!("{path}: {e}"))?;
+                    parsed.keys = parse_key_script(&script)?;
+                }
+                "--record" => {
+                    parsed.record = Some(args.next().ok_or("--record needs a file name")?.into())
+                }
+                "--play" => {
+                    parsed.play = Some(args.next().ok_or("--play needs a file name")?.into())
+                }
+                "--ascii" => parsed.ascii = true,
+                "--registers" => parsed.registers = true,
+                "--png" => parsed.png = Some(args.next().ok_or("--png needs a file name")?.into()),
+                "--pbm" => parsed.pbm = Some(args.next().ok_or("--pbm needs a file name")?.into()),
+                "--scale" => parsed.scale = Some(parse_number(&arg, args.next())?),
+                "--trace" => {
+                    parsed.trace = Some(args.next().ok_or("--trace needs a file name")?.into())
+                }
+                "--trace-format" => {
+                    let format = args.next().ok_or("--trace-format needs a format name")?;
+                    parsed.trace_format =
+                        format.parse::<TraceFormat>().map_err(|e| e.to_string())?;
+                }
+                "--trace-range" => {
+                    let range = args.next().ok_or("--trace-range needs an address range")?;
+                    parsed.trace_range = Some(
+                        parse_address_range(&range)
+                            .ok_or_else(|| format!("bad address range '{range}'"))?,
+                    );
+                }
+                "--trace-limit" => parsed.trace_limit = Some(parse_number(&arg, args.next())?),
+                "-h" | "--help" => {
+                    println!("{}", Self::USAGE);
+                    println!();
+                    println!("Runs ROM without a window until --cycles instructions or --frames");
+                    println!("60 Hz frames have run (600 frames if neither is given), stopping");
+                    println!("early at --until-pc, at a jump to itself with --until-self-jump, or");
+                    println!("at EXIT. Then prints or writes the requested outputs.");
+                    println!();
+                    println!("RAND is seeded randomly unless --seed is given, so runs with the");
+                    println!("same seed and keys are identical.");
+                    println!();
+                    println!("A key script lists FRAME:KEYS events: '60:5 90: 120:4A' holds key 5");
+                    println!("from frame 60, nothing from 90 and keys 4 and A from 120.");
+                    println!();
+                    println!("--record saves the run's settings, seed and keys as a movie that");
+                    println!("--play runs again exactly, checking it against the recording as it");
+                    println!("goes. A played movie replaces the quirk, error, RAND, speed and key");
+                    println!("options and runs for as many frames as were recorded.");
+                    println!();
+                    println!("Exits 0 when an --until-* condition or EXIT was reached, or when");
+                    println!("the limit ran out and no condition was given; 3 when a condition");
+                    println!("was given but not reached; 4 when an instruction faulted under the");
+                    println!("halt policy; 5 when a played movie desynced; 1 on I/O errors and 2");
+                    println!("on usage errors.");
+                    std::process::exit(0);
+                }
+                _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+                _ => parsed.rom = Some(PathBuf::from(arg)),
+            }
+        }
+        if parsed.ips == Some(0) {
+            return Err("--ips must be at least 1".to_owned());
+        }
+        if parsed.play.is_some() && parsed.record.is_some() {
+            return Err("--play and --record can't be used together".to_owned());
+        }
+        if parsed.play.is_some() && !parsed.keys.is_empty() {
+            return Err("--play takes its keys from the movie".to_owned());
+        }
+        Ok(parsed)
+    }
+}
+
+/// Why the run ended.
+#[derive(Debug, PartialEq, Eq)]
+enum Stop {
+    Cycles,
+    Frames,
+    Pc,
+    SelfJump,
+    Exit,
+    Fault(Chip8Error),
+    Desync(Desync),
+}
+
+impl Stop {
+    fn description(&self) -> String {
+        match self {
+            Stop::Cycles => "cycle limit reached".to_owned(),
+            Stop::Frames => "frame limit reached".to_owned(),
+            Stop::Pc => "reached --until-pc address".to_owned(),
+            Stop::SelfJump => "jumped to itself".to_owned(),
+            Stop::Exit => "EXIT".to_owned(),
+            Stop::Fault(error) => error.to_string(),
+            Stop::Desync(desync) => format!("movie {desync}"),
+        }
+    }
+}
+
+fn is_self_jump(chip8: &Chip8) -> bool {
+    matches!(
+        Opcode::decode_at(&chip8.memory, chip8.pc),
+        Ok(Opcode::JUMP(target)) if target == chip8.pc
+    )
+}
+
+/// Runs `chip8` frame by frame through `scheduler`, checking the stop
+/// conditions after every instruction. A frame cut short by one still
+/// finishes: its timers tick and a recorded movie keeps it. Returns the
+/// reason, instructions run and frames run.
+fn run(chip8: &mut Chip8, args: &Args, scheduler: &mut Scheduler) -> (Stop, u64, u64) {
+    let frame_limit = match (args.cycles, args.frames, &scheduler.movie) {
+        (None, None, Some(movie)) if movie.is_playing() => Some(movie.movie.frames.len() as u64),
+        (None, None, _) => Some(DEFAULT_FRAMES),
+        (_, frames, _) => frames,
+    };
+    let mut keys = args.keys.iter().peekable();
+    let (mut cycles, mut frames) = (0, 0);
+    loop {
+        if frame_limit.is_some_and(|limit| frames >= limit) {
+            return (Stop::Frames, cycles, frames);
+        }
+        if args.cycles.is_some_and(|limit| cycles >= limit) {
+            return (Stop::Cycles, cycles, frames);
+        }
+        while let Some(event) = keys.next_if(|event| event.frame <= frames) {
+            chip8.set_keys(event.keys);
+        }
+        let mut stop = None;
+        scheduler.run_frame_until(chip8, |chip8, step| {
+            cycles += 1;
+            stop = match step {
+                Err(error) => Some(Stop::Fault(error.clone())),
+                Ok(StepEvent::Exited) => Some(Stop::Exit),
+                _ if args.until_pc == Some(chip8.pc) => Some(Stop::Pc),
+                _ if args.until_self_jump && is_self_jump(chip8) => Some(Stop::SelfJump),
+                _ if args.cycles.is_some_and(|limit| cycles >= limit) => Some(Stop::Cycles),
+                _ => None,
+            };
+            stop.is_some()
+        });
+        frames += 1;
+        if let Some(desync) = scheduler.movie.as_ref().and_then(MovieSession::desync) {
+            return (Stop::Desync(desync.clone()), cycles, frames);
+        }
+        if let Some(stop) = stop {
+            return (stop, cycles, frames);
+        }
+    }
+}
+
+fn read_rom(path: &Path) -> Result<Vec<u8>, String> {
+    if path.extension().is_some_and(|e| e == "8o") {
+        Ok(octo::compile_file(path).map_err(|e| e.to_string())?.rom)
+    } else {
+        std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
+
+/// The movie to play, or an empty one holding the settings from `args`.
+fn movie(args: &Args, rom: &[u8]) -> Result<Movie, String> {
+    if let Some(path) = &args.play {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        return Movie::parse(&text).map_err(|e| format!("{}: {e}", path.display()));
+    }
+    let memory_size = if args.profile == QuirkProfile::XoChip {
+        XO_CHIP_MEMORY_SIZE
+    } else {
+        MEMORY_SIZE
+    };
+    Ok(Movie::new(
+        rom,
+        args.profile.quirks(),
+        memory_size,
+        args.ips.unwrap_or(DEFAULT_INSTRUCTIONS_PER_SECOND),
+        args.error_policy,
+        args.rng,
+        args.seed.unwrap_or_else(random_seed),
+    ))
+}
+
+fn load(path: &Path, rom: &[u8], movie: &Movie) -> Result<Chip8, String> {
+    let capacity = movie.memory_size - FIRST_INSTRUCTION_ADDRESS as usize;
+    if rom.len() > capacity {
+        return Err(format!(
+            "{}: ROM is {} bytes but only {capacity} fit in memory",
+            path.display(),
+            rom.len()
+        ));
+    }
+    let mut chip8 = movie
+        .machine(rom)
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    // Show exactly what's in the framebuffer, not what a screen would.
+    chip8.display.deflicker = false;
+    chip8.paused = false;
+    Ok(chip8)
+}
+
+fn write_registers(out: &mut impl Write, chip8: &Chip8) -> io::Result<()> {
+    writeln!(
+        out,
+        "pc={:04X} i={:04X} sp={:X} dt={:02X} st={:02X}",
+        chip8.pc, chip8.i, chip8.sp, chip8.delay_timer, chip8.sound_timer
+    )?;
+    let v: Vec<String> = chip8.v.iter().map(|v| format!("{v:02X}")).collect();
+    writeln!(out, "v={}", v.join(" "))?;
+    let stack: Vec<String> = chip8.stack[..chip8.sp as usize]
+        .iter()
+        .map(|address| format!("{address:04X}"))
+        .collect();
+    writeln!(out, "stack={}", stack.join(" "))
+}
+
+fn write_file(path: &Path, write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>) -> bool {
+    let result = File::create(path).map(BufWriter::new).and_then(|mut out| {
+        write(&mut out)?;
+        out.flush()
+    });
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("{}: {e}", path.display());
+            false
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse() {
+        Ok(Args { rom: None, .. }) => Err("no ROM given".to_owned()),
+        result => result,
+    }
+    .unwrap_or_else(|e| {
+        eprintln!("{e}");
+        eprintln!("{}", Args::USAGE);
+        std::process::exit(2);
+    });
+    let rom_path = args.rom.as_deref().unwrap();
+
+    let loaded = read_rom(rom_path).and_then(|rom| {
+        let movie = movie(&args, &rom)?;
+        Ok((load(rom_path, &rom, &movie)?, movie))
+    });
+    let (mut chip8, movie) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut scheduler = Scheduler::new(Speed::InstructionsPerSecond(movie.instructions_per_second));
+    match (&args.play, &args.record) {
+        (Some(_), _) => scheduler.start_movie(MovieSession::play(movie)),
+        (_, Some(_)) => scheduler.start_movie(MovieSession::record(movie)),
+        _ => {}
+    }
+    if let Some(path) = &args.trace {
+        match Tracer::create(path, args.trace_format) {
+            Ok(mut tracer) => {
+                tracer.range = args.trace_range.clone();
+                tracer.limit = args.trace_limit;
+                chip8.trace = Some(tracer);
+            }
+            Err(e) => {
+                eprintln!("{}: {e}", path.display());
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let (stop, cycles, frames) = run(&mut chip8, &args, &mut scheduler);
+    eprintln!(
+        "{}: {} after {cycles} instructions, {frames} frames, at pc {:#05X}",
+        rom_path.display(),
+        stop.description(),
+        chip8.pc
+    );
+
+    let mut ok = true;
+    if let Some(mut tracer) = chip8.trace.take() {
+        if let Some(e) = tracer.error() {
+            eprintln!("trace: {e}");
+            ok = false;
+        } else if let Err(e) = tracer.flush() {
+            eprintln!("trace: {e}");
+            ok = false;
+        }
+    }
+    if let (Some(path), Some(session)) = (&args.record, &scheduler.movie) {
+        let movie = session.movie.to_text();
+        ok &= write_file(path, |out| out.write_all(movie.as_bytes()));
+    }
+    if let Some(path) = &args.png {
+        let scale = args.scale.unwrap_or(1);
+        ok &= write_file(path, |out| {
+            image::write_png(out, &chip8.display, &DEFAULT_PALETTE, scale)
+        });
+    }
+    if let Some(path) = &args.pbm {
+        ok &= write_file(path, |out| image::write_pbm(out, &chip8.display));
+    }
+    let mut out = io::stdout().lock();
+    let printed = (|| {
+        if args.ascii {
+            out.write_all(image::ascii(&chip8.display).as_bytes())?;
+        }
+        if args.registers {
+            write_registers(&mut out, &chip8)?;
+        }
+        out.flush()
+    })();
+    match printed {
+        Ok(()) => {}
+        // Piping into `head` and the like isn't an error.
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+        Err(e) => {
+            eprintln!("{e}");
+            ok = false;
+        }
+    }
+
+    let condition_given = args.until_pc.is_some() || args.until_self_jump;
+    if !ok {
+        ExitCode::FAILURE
+    } else if matches!(stop, Stop::Desync(_)) {
+        ExitCode::from(DESYNCED)
+    } else if matches!(stop, Stop::Fault(_)) {
+        ExitCode::from(FAULTED)
+    } else if condition_given && matches!(stop, Stop::Cycles | Stop::Frames) {
+        ExitCode::from(NOT_REACHED)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
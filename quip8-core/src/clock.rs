@@ -0,0 +1,175 @@
+use std::time::{Duration, Instant};
+
+use crate::audio::{Beeper, Pattern};
+use crate::cpu::{Chip8, StepEvent};
+use crate::error::Chip8Error;
+use crate::movie::MovieSession;
+use crate::rewind::RewindBuffer;
+
+/// Rate of the delay and sound timers, and of emulated frames.
+pub const TIMER_HZ: u32 = 60;
+pub const DEFAULT_INSTRUCTIONS_PER_SECOND: u32 = 700;
+
+/// Upper bound on how many emulated frames a single call to
+/// [`Scheduler::advance`] or [`Scheduler::rewind`] will catch up on, so a long
+/// stall (window dragged, debugger breakpoint in the host) doesn't turn into a
+/// burst of fast-forward.
+const MAX_FRAMES_PER_ADVANCE: u32 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Speed {
+    InstructionsPerSecond(u32),
+    /// Run as many instructions as fit in one frame's worth of wall-clock
+    /// time.
+    Unlimited,
+}
+
+impl Default for Speed {
+    fn default() -> Self {
+        Speed::InstructionsPerSecond(DEFAULT_INSTRUCTIONS_PER_SECOND)
+    }
+}
+
+/// Drives a [`Chip8`] in emulated time: timers tick at exactly [`TIMER_HZ`]
+/// and `speed` instructions run per emulated second, independent of how often
+/// the frontend calls in.
+#[derive(Default)]
+pub struct Scheduler {
+    pub speed: Speed,
+    /// Fed one frame of audio per emulated frame, if present.
+    pub beeper: Option<Beeper>,
+    /// Fed every emulated frame after the timers tick, if present.
+    pub rewind: Option<RewindBuffer>,
+    /// Records or replays each emulated frame's input, if present. Start one
+    /// with [`start_movie`](Self::start_movie).
+    pub movie: Option<MovieSession>,
+    elapsed: Duration,
+    // Instructions owed to the next frame, in sixtieths of an instruction, so
+    // rates that aren't a multiple of 60 still average out exactly.
+    cycle_remainder: u32,
+}
+
+impl Scheduler {
+    pub fn new(speed: Speed) -> Self {
+        Self {
+            speed,
+            ..Self::default()
+        }
+    }
+
+    /// Starts recording or playing `session` from the next frame. The speed
+    /// is set to the movie's and no instructions are carried over, so every
+    /// run of the movie executes the same instructions in each frame.
+    pub fn start_movie(&mut self, session: MovieSession) {
+        self.speed = Speed::InstructionsPerSecond(session.movie.instructions_per_second);
+        self.cycle_remainder = 0;
+        self.elapsed = Duration::ZERO;
+        self.movie = Some(session);
+    }
+
+    fn frame_duration() -> Duration {
+        Duration::from_secs(1) / TIMER_HZ
+    }
+
+    /// Accumulates `wall_time` and runs as many whole emulated frames as it
+    /// covers. Returns the number of frames run.
+    pub fn advance(&mut self, chip8: &mut Chip8, wall_time: Duration) -> u32 {
+        if chip8.paused {
+            self.elapsed = Duration::ZERO;
+            return 0;
+        }
+
+        let frame_duration = Self::frame_duration();
+        self.elapsed = (self.elapsed + wall_time).min(frame_duration * MAX_FRAMES_PER_ADVANCE);
+        let mut frames = 0;
+        while self.elapsed >= frame_duration && !chip8.paused {
+            self.elapsed -= frame_duration;
+            self.run_frame(chip8);
+            frames += 1;
+        }
+        frames
+    }
+
+    /// Like [`advance`](Self::advance), but runs backwards through the rewind
+    /// history at the speed it was recorded. Returns the number of frames
+    /// rewound.
+    pub fn rewind(&mut self, chip8: &mut Chip8, wall_time: Duration) -> u32 {
+        let Some(rewind) = self.rewind.as_mut() else {
+            return 0;
+        };
+        let frame_duration = Self::frame_duration();
+        self.elapsed = (self.elapsed + wall_time).min(frame_duration * MAX_FRAMES_PER_ADVANCE);
+        let mut frames = 0;
+        while self.elapsed >= frame_duration {
+            self.elapsed -= frame_duration;
+            rewind.rewind_frame(chip8);
+            frames += 1;
+        }
+        frames
+    }
+
+    /// Runs one emulated frame: a frame's worth of instructions, a frame of
+    /// audio, a timer tick and possibly a rewind snapshot.
+    pub fn run_frame(&mut self, chip8: &mut Chip8) {
+        self.run_frame_until(chip8, |_, _| false);
+    }
+
+    /// Like [`run_frame`](Self::run_frame), but calls `stop` with the outcome
+    /// of every instruction and runs no more of the frame's instructions once
+    /// it returns true. The rest of the frame still happens. Returns whether
+    /// `stop` ended the frame early.
+    pub fn run_frame_until(
+        &mut self,
+        chip8: &mut Chip8,
+        mut stop: impl FnMut(&Chip8, &Result<StepEvent, Chip8Error>) -> bool,
+    ) -> bool {
+        if let Some(movie) = self.movie.as_mut() {
+            movie.before_frame(chip8);
+        }
+        let mut stopped = false;
+        // Runs one instruction and says whether the frame's are done.
+        let mut step = |chip8: &mut Chip8| {
+            let result = chip8.emulate_cycle();
+            stopped = stop(chip8, &result);
+            stopped || result.is_err() || chip8.vblank_wait || chip8.paused
+        };
+        match self.speed {
+            Speed::InstructionsPerSecond(ips) => {
+                self.cycle_remainder += ips;
+                let cycles = self.cycle_remainder / TIMER_HZ;
+                self.cycle_remainder %= TIMER_HZ;
+                for _ in 0..cycles {
+                    if step(chip8) {
+                        break;
+                    }
+                }
+            }
+            Speed::Unlimited => {
+                let deadline = Instant::now() + Self::frame_duration();
+                'frame: while Instant::now() < deadline {
+                    // Checking the clock is far slower than an instruction.
+                    for _ in 0..1000 {
+                        if step(chip8) {
+                            break 'frame;
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(beeper) = self.beeper.as_mut() {
+            let pattern = chip8.audio_pattern.as_ref().map(|bits| Pattern {
+                bits,
+                rate: chip8.pattern_rate(),
+            });
+            beeper.frame(chip8.sound_timer > 0, pattern);
+        }
+        chip8.tick_timers();
+        if let Some(rewind) = self.rewind.as_mut() {
+            rewind.frame(chip8);
+        }
+        if let Some(movie) = self.movie.as_mut() {
+            movie.after_frame(chip8);
+        }
+        stopped
+    }
+}
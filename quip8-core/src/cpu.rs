@@ -0,0 +1,656 @@
+use crate::debug::{Access, Break, Debugger};
+use crate::decode::{Address, Opcode, UnknownOpcode};
+use crate::display::Display;
+use crate::error::{Chip8Error, ErrorPolicy};
+use crate::memory::{
+    self, BIG_FONT_START_ADDRESS, FIRST_INSTRUCTION_ADDRESS, FONT_START_ADDRESS, MEMORY_SIZE,
+};
+use crate::quirks::Quirks;
+use crate::rng::Rng;
+use crate::trace::Tracer;
+
+/// XO-CHIP's initial `pitch`, which plays patterns at 4000 bits per second.
+pub const DEFAULT_PITCH: u8 = 64;
+
+/// What a successful [`Chip8::emulate_cycle`] did.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StepEvent {
+    /// The instruction ran.
+    Executed,
+    /// `KEYD` is waiting for a key press; `pc` stayed put.
+    WaitingForKey,
+    /// `EXIT` ran and paused the machine.
+    Exited,
+    /// A breakpoint or watchpoint paused the machine.
+    Break,
+    /// The instruction faulted and `error_policy` let execution carry on.
+    Recovered(Chip8Error),
+}
+
+/// The registers touched by `STORER`/`READR`, from X to Y in either direction.
+fn register_range(register_x: u8, register_y: u8) -> Box<dyn Iterator<Item = usize>> {
+    let (x, y) = (register_x as usize, register_y as usize);
+    if x <= y {
+        Box::new(x..=y)
+    } else {
+        Box::new((y..=x).rev())
+    }
+}
+
+pub struct Chip8 {
+    opcode: u16,
+    opcode_address: Address,
+    /// 4 KiB, or 64 KiB for XO-CHIP.
+    pub memory: Vec<u8>,
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: Address,
+    pub display: Display,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub stack: [Address; 16],
+    /// Number of stack entries in use. `CALL` pushes to `stack[sp]`.
+    pub sp: u16,
+    pub keys: u16,
+    pub key_pressed: Option<u8>,
+    /// SUPER-CHIP RPL user flags, written by `STOREF` and read by `READF`.
+    pub rpl: [u8; 16],
+    /// XO-CHIP 1-bit audio pattern loaded by `AUDIO`. Until one is loaded the
+    /// beeper plays its configured tone.
+    pub audio_pattern: Option<[u8; 16]>,
+    /// XO-CHIP playback rate of `audio_pattern`, set by `PITCH`.
+    pub pitch: u8,
+    pub rng: Rng,
+
+    pub quirks: Quirks,
+    /// How faulting instructions are handled.
+    pub error_policy: ErrorPolicy,
+    /// Set by `DRAW` under the display-wait quirk; the scheduler stops issuing
+    /// instructions until the next timer tick clears it.
+    pub vblank_wait: bool,
+
+    pub paused: bool,
+    pub debugger: Debugger,
+    /// When set, every executed instruction is logged to it.
+    pub trace: Option<Tracer>,
+}
+
+impl Chip8 {
+    /// Creates a paused machine with 4 KiB of memory and `rom` loaded at
+    /// `FIRST_INSTRUCTION_ADDRESS`.
+    pub fn new(rom: &[u8]) -> Self {
+        Self::with_memory_size(rom, MEMORY_SIZE)
+    }
+
+    /// Like [`new`](Self::new) with `memory_size` bytes of memory, e.g.
+    /// `XO_CHIP_MEMORY_SIZE`.
+    pub fn with_memory_size(rom: &[u8], memory_size: usize) -> Self {
+        Self {
+            opcode: 0,
+            opcode_address: FIRST_INSTRUCTION_ADDRESS,
+            memory: memory::with_rom(rom, memory_size),
+            v: [0; 16],
+            i: 0,
+            pc: FIRST_INSTRUCTION_ADDRESS,
+            display: Display::default(),
+            delay_timer: 0,
+            sound_timer: 0,
+            stack: [0; 16],
+            sp: 0,
+            keys: 0,
+            key_pressed: None,
+            rpl: [0; 16],
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            rng: Rng::default(),
+            quirks: Quirks::default(),
+            error_policy: ErrorPolicy::default(),
+            vblank_wait: false,
+            paused: true,
+            debugger: Debugger::default(),
+            trace: None,
+        }
+    }
+
+    /// Updates the keypad state, one bit per key with key 0 in the least
+    /// significant bit. `key_pressed` is set to the lowest key that went down
+    /// since the previous call.
+    pub fn set_keys(&mut self, keys: u16) {
+        let keys_pressed_since = (self.keys & keys) ^ keys;
+        self.keys = keys;
+        self.key_pressed = if keys_pressed_since != 0 {
+            Some(keys_pressed_since.trailing_zeros() as u8)
+        } else {
+            None
+        };
+    }
+
+    /// The address the next `RTS` returns to, if the stack isn't empty.
+    pub fn return_address(&self) -> Option<Address> {
+        let sp = self.sp as usize;
+        (sp > 0).then(|| self.stack[(sp - 1) % self.stack.len()])
+    }
+
+    /// Reads the big-endian instruction word at `address`.
+    pub fn read_opcode(&self, address: Address) -> u16 {
+        let len = self.memory.len();
+        (self.memory[address as usize % len] as u16) << 8
+            | self.memory[(address as usize + 1) % len] as u16
+    }
+
+    /// Skips the instruction at `pc`, which is four bytes long for XO-CHIP's
+    /// `F000 NNNN`. Like the fetch, this wraps at the end of the address
+    /// space; a `pc` left past the end of memory faults on the next fetch.
+    fn skip_next(&mut self) {
+        let size = if self.read_opcode(self.pc) == 0xF000 {
+            4
+        } else {
+            2
+        };
+        self.pc = self.pc.wrapping_add(size);
+    }
+
+    /// Executes the instruction at `pc`. When it faults under
+    /// [`ErrorPolicy::Halt`] the machine is left as it was before the
+    /// instruction, paused on it, and the error is also recorded as a
+    /// [`Break`] for the debugger.
+    pub fn emulate_cycle(&mut self) -> Result<StepEvent, Chip8Error> {
+        if let Some(mut trace) = self.trace.take() {
+            trace.record(self);
+            self.trace = Some(trace);
+        }
+
+        let fetch_address = self.pc;
+        let had_break = self.debugger.has_break();
+        let mut event = match self.step() {
+            Ok(event) => event,
+            Err(error) if self.error_policy == ErrorPolicy::Halt => {
+                self.pc = fetch_address;
+                self.paused = true;
+                self.debugger.record_break(Break::Error(error.clone()));
+                return Err(error);
+            }
+            // `step` has already moved past the faulting instruction.
+            Err(error) => StepEvent::Recovered(error),
+        };
+
+        // Instructions that wait by staying put (KEYD, EXIT) don't re-trigger
+        // the breakpoint they were resumed from.
+        if self.pc != fetch_address && self.debugger.breaks_at(self.pc) {
+            self.paused = true;
+            self.debugger.record_break(Break::Breakpoint(self.pc));
+        }
+        if event == StepEvent::Executed && !had_break && self.debugger.has_break() {
+            event = StepEvent::Break;
+        }
+        Ok(event)
+    }
+
+    /// Fetches and executes one instruction. Faults are detected before the
+    /// instruction changes anything but `pc`.
+    fn step(&mut self) -> Result<StepEvent, Chip8Error> {
+        if self.pc as usize >= self.memory.len() {
+            let error = Chip8Error::PcOutOfBounds { pc: self.pc };
+            // There's no instruction to skip, so ignoring wraps as well.
+            self.pc = (self.pc as usize % self.memory.len()) as Address;
+            if self.error_policy != ErrorPolicy::Wrap {
+                return Err(error);
+            }
+        }
+
+        // fetch opcode
+        let fetch_address = self.pc;
+        self.opcode_address = self.pc;
+        self.opcode = self.read_opcode(self.pc);
+        let decoded = Opcode::decode_at(&self.memory, self.pc);
+        let size = decoded.as_ref().map_or(2, Opcode::size);
+        self.watch_read(fetch_address as usize, size as usize);
+        self.pc = self.pc.wrapping_add(size);
+
+        // decode opcode
+        match decoded {
+            Ok(decoded_opcode) => match decoded_opcode {
+                Opcode::SYS(address) => {
+                    return Err(Chip8Error::MachineCodeCall {
+                        pc: fetch_address,
+                        address,
+                    });
+                } //Calls machine code routine (RCA 1802 for COSMAC VIP) at address NNN. Not necessary for most ROMs.
+                Opcode::CLR => {
+                    self.display.clear();
+                } // 	disp_clear() 	Clears the screen.
+                Opcode::RTS => {
+                    if self.sp == 0 && self.error_policy != ErrorPolicy::Wrap {
+                        return Err(Chip8Error::StackUnderflow { pc: fetch_address });
+                    }
+                    self.sp = ((self.sp as usize + self.stack.len() - 1) % self.stack.len()) as u16;
+                    self.pc = self.stack[self.sp as usize];
+                } //return; 	Returns from a subroutine.
+                Opcode::SCD(literal) => {
+                    self.display.scroll_down(literal as usize);
+                }
+                Opcode::SCU(literal) => {
+                    self.display.scroll_up(literal as usize);
+                }
+                Opcode::SCR => {
+                    self.display.scroll_right(4);
+                }
+                Opcode::SCL => {
+                    self.display.scroll_left(4);
+                }
+                Opcode::EXIT => {
+                    // Stay on the exit instruction so resuming exits again.
+                    self.pc = fetch_address;
+                    self.paused = true;
+                    return Ok(StepEvent::Exited);
+                }
+                Opcode::LOW => {
+                    self.display.set_hires(false);
+                }
+                Opcode::HIGH => {
+                    self.display.set_hires(true);
+                }
+                Opcode::JUMP(address) => {
+                    self.pc = address;
+                } //goto NNN; 	Jumps to address NNN.
+                Opcode::CALL(address) => {
+                    let sp = self.sp as usize;
+                    if sp >= self.stack.len() && self.error_policy != ErrorPolicy::Wrap {
+                        return Err(Chip8Error::StackOverflow { pc: fetch_address });
+                    }
+                    let entry = sp % self.stack.len();
+                    self.stack[entry] = self.pc;
+                    self.sp = entry as u16 + 1;
+                    self.pc = address;
+                } //*(0xNNN)() 	Calls subroutine at NNN.
+                Opcode::SKE((register, literal)) => {
+                    if self.v[register as usize] == literal {
+                        self.skip_next();
+                    }
+                } //if (Vx == NN) 	Skips the next instruction if VX equals NN (usually the next instruction is a jump to skip a code block).
+                Opcode::SKNE((register, literal)) => {
+                    if self.v[register as usize] != literal {
+                        self.skip_next();
+                    }
+                } //if (Vx != NN) 	Skips the next instruction if VX does not equal NN (usually the next instruction is a jump to skip a code block).
+                Opcode::SKRE((register_x, register_y)) => {
+                    if self.v[register_x as usize] == self.v[register_y as usize] {
+                        self.skip_next();
+                    }
+                } //if (Vx == Vy) 	Skips the next instruction if VX equals VY (usually the next instruction is a jump to skip a code block).
+                Opcode::STORER((register_x, register_y)) => {
+                    let len = register_x.abs_diff(register_y) as usize + 1;
+                    self.check_span(self.i as usize, len)?;
+                    for (offset, register) in register_range(register_x, register_y).enumerate() {
+                        let address = self.wrap(self.i as usize + offset);
+                        self.write(address, self.v[register]);
+                    }
+                }
+                Opcode::READR((register_x, register_y)) => {
+                    let len = register_x.abs_diff(register_y) as usize + 1;
+                    self.check_span(self.i as usize, len)?;
+                    for (offset, register) in register_range(register_x, register_y).enumerate() {
+                        let address = self.wrap(self.i as usize + offset);
+                        self.watch_read(address, 1);
+                        self.v[register] = self.memory[address];
+                    }
+                }
+                Opcode::LOAD((register, literal)) => {
+                    self.v[register as usize] = literal;
+                }
+                Opcode::ADD((register, literal)) => {
+                    self.v[register as usize] =
+                        (self.v[register as usize] as u16 + literal as u16) as u8;
+                } //Vx += NN 	Adds NN to VX (carry flag is not changed).
+                Opcode::MOVE((register_x, register_y)) => {
+                    self.v[register_x as usize] = self.v[register_y as usize];
+                } //Vx = Vy 	Sets VX to the value of VY.
+                Opcode::OR((register_x, register_y)) => {
+                    self.v[register_x as usize] |= self.v[register_y as usize];
+                    if self.quirks.logic_resets_vf {
+                        self.v[0xF] = 0;
+                    }
+                } // Vx |= Vy 	Sets VX to VX or VY. (bitwise OR operation)
+                Opcode::AND((register_x, register_y)) => {
+                    self.v[register_x as usize] &= self.v[register_y as usize];
+                    if self.quirks.logic_resets_vf {
+                        self.v[0xF] = 0;
+                    }
+                } //Vx &= Vy 	Sets VX to VX and VY. (bitwise AND operation)
+                Opcode::XOR((register_x, register_y)) => {
+                    self.v[register_x as usize] ^= self.v[register_y as usize];
+                    if self.quirks.logic_resets_vf {
+                        self.v[0xF] = 0;
+                    }
+                } // Vx ^= Vy 	Sets VX to VX xor VY.
+                Opcode::ADDR((register_x, register_y)) => {
+                    let (result, overflow) =
+                        self.v[register_x as usize].overflowing_add(self.v[register_y as usize]);
+                    self.v[register_x as usize] = result;
+                    self.v[0xF] = overflow as u8;
+                } // Vx += Vy 	Adds VY to VX. VF is set to 1 when there's a carry, and to 0 when there is not.
+                Opcode::SUB((register_x, register_y)) => {
+                    let (result, borrow) =
+                        self.v[register_x as usize].overflowing_sub(self.v[register_y as usize]);
+                    self.v[register_x as usize] = result;
+                    self.v[0xF] = !borrow as u8;
+                } // Vx -= Vy 	VY is subtracted from VX. VF is set to 0 when there's a borrow, and 1 when there is not.
+                Opcode::SHR((register_x, register_y)) => {
+                    let source = self.shift_source(register_x, register_y);
+                    // The flag is written last so it wins when X is F.
+                    self.v[register_x as usize] = source >> 1;
+                    self.v[0xF] = source & 1;
+                } // Vx >>= 1 	Stores the least significant bit of VX in VF and then shifts VX to the right by 1.[b]
+                Opcode::RSUB((register_x, register_y)) => {
+                    let (result, borrow) =
+                        self.v[register_y as usize].overflowing_sub(self.v[register_x as usize]);
+                    self.v[register_x as usize] = result;
+                    self.v[0xF] = !borrow as u8;
+                } // Vx = Vy - Vx 	Sets VX to VY minus VX. VF is set to 0 when there's a borrow, and 1 when there is not.
+                Opcode::SHL((register_x, register_y)) => {
+                    let source = self.shift_source(register_x, register_y);
+                    self.v[register_x as usize] = source << 1;
+                    self.v[0xF] = source >> 7;
+                } // Vx <<= 1 	Stores the most significant bit of VX in VF and then shifts VX to the left by 1.[b]
+                Opcode::SKRNE((register_x, register_y)) => {
+                    if self.v[register_x as usize] != self.v[register_y as usize] {
+                        self.skip_next();
+                    }
+                } //if (Vx != Vy) 	Skips the next instruction if VX does not equal VY. (Usually the next instruction is a jump to skip a code block);
+                Opcode::LOADI(address) => {
+                    self.i = address;
+                } //I = NNN 	Sets I to the address NNN.
+                Opcode::LOADIL(address) => {
+                    self.i = address;
+                }
+                Opcode::JUMPI(address) => {
+                    let register = if self.quirks.jump_uses_vx {
+                        (address >> 8) as usize
+                    } else {
+                        0
+                    };
+                    self.pc = address + self.v[register] as u16;
+                } // PC = V0 + NNN 	Jumps to the address NNN plus V0.
+                Opcode::RAND((register, literal)) => {
+                    self.v[register as usize] = self.rng.next_u8(&self.memory) & literal;
+                } //Vx = rand() & NN 	Sets VX to the result of a bitwise and operation on a random number (Typically: 0 to 255) and NN.
+                Opcode::DRAW((register_x, register_y, literal)) => {
+                    let x = self.v[register_x as usize] as usize;
+                    let y = self.v[register_y as usize] as usize;
+                    let wrap = self.quirks.wrap_sprites;
+                    let planes = self.display.selected_plane_count();
+                    let len = if literal == 0 { 32 } else { literal as usize } * planes;
+                    self.check_span(self.i as usize, len)?;
+                    self.watch_read(self.i as usize, len);
+                    let sprite: Vec<u8> = (0..len)
+                        .map(|offset| self.memory[self.wrap(self.i as usize + offset)])
+                        .collect();
+                    let collision = if literal == 0 {
+                        self.display.draw_wide(x, y, &sprite, wrap)
+                    } else {
+                        self.display.draw(x, y, &sprite, wrap)
+                    };
+                    self.v[0xF] = collision as u8;
+                    if self.quirks.display_wait {
+                        self.vblank_wait = true;
+                    }
+                } //draw(Vx, Vy, N) 	Draws a sprite at coordinate (VX, VY) that has a width of 8 pixels and a height of N pixels.
+                //  Each row of 8 pixels is read as bit-coded starting from memory location I; I value does not change after the execution of this instruction.
+                //  As described above, VF is set to 1 if any screen pixels are flipped from set to unset when the sprite is drawn, and to 0 if that does not happen.
+                // Only the low nibble of VX selects a key, as on the VIP.
+                Opcode::SKPR(register) => {
+                    if self.keys & (1 << (self.v[register as usize] & 0xF)) != 0 {
+                        self.skip_next();
+                    }
+                } // if (key() == Vx) 	Skips the next instruction if the key stored in VX is pressed (usually the next instruction is a jump to skip a code block).
+                Opcode::SKUP(register) => {
+                    if self.keys & (1 << (self.v[register as usize] & 0xF)) == 0 {
+                        self.skip_next();
+                    }
+                } //if (key() != Vx) 	Skips the next instruction if the key stored in VX is not pressed (usually the next instruction is a jump to skip a code block).
+                Opcode::MOVED(register) => {
+                    self.v[register as usize] = self.delay_timer;
+                } //Vx = get_delay() 	Sets VX to the value of the delay timer.
+                Opcode::KEYD(register) => {
+                    if let Some(key) = self.key_pressed.take() {
+                        self.v[register as usize] = key;
+                    } else {
+                        self.pc = fetch_address;
+                        return Ok(StepEvent::WaitingForKey);
+                    }
+                } //Vx = get_key() 	A key press is awaited, and then stored in VX (blocking operation, all instruction halted until next key event).
+                Opcode::LOADD(register) => {
+                    self.delay_timer = self.v[register as usize];
+                } //delay_timer(Vx) 	Sets the delay timer to VX.
+                Opcode::LOADS(register) => {
+                    self.sound_timer = self.v[register as usize];
+                } //sound_timer(Vx) 	Sets the sound timer to VX.
+                Opcode::PLANE(planes) => {
+                    self.display.select_planes(planes);
+                }
+                Opcode::AUDIO => {
+                    self.watch_read(self.i as usize, 16);
+                    let mut pattern = [0; 16];
+                    for (offset, byte) in pattern.iter_mut().enumerate() {
+                        *byte = self.memory[(self.i as usize + offset) % self.memory.len()];
+                    }
+                    self.audio_pattern = Some(pattern);
+                }
+                Opcode::PITCH(register) => {
+                    self.pitch = self.v[register as usize];
+                }
+                Opcode::ADDI(register) => {
+                    self.i = self.i.wrapping_add(self.v[register as usize] as u16);
+                } //I += Vx 	Adds VX to I. VF is not affected.[c]
+                Opcode::LDSPR(register) => {
+                    self.i = FONT_START_ADDRESS + (self.v[register as usize] & 0xF) as u16 * 5;
+                } //I = sprite_addr[Vx] 	Sets I to the location of the sprite for the character in VX. Characters 0-F (in hexadecimal) are represented by a 4x5 font.
+                Opcode::LDBSPR(register) => {
+                    self.i = BIG_FONT_START_ADDRESS + (self.v[register as usize] & 0xF) as u16 * 10;
+                }
+                Opcode::BCD(register) => {
+                    self.check_span(self.i as usize, 3)?;
+                    let value = self.v[register as usize];
+                    self.write(self.wrap(self.i as usize), value / 100);
+                    self.write(self.wrap(self.i as usize + 1), value / 10 % 10);
+                    self.write(self.wrap(self.i as usize + 2), value % 10);
+                } // set_BCD(Vx) *(I+0) = BCD(3); *(I+1) = BCD(2); *(I+2) = BCD(1);
+                // Stores the binary-coded decimal representation of VX, with the hundreds digit in memory at location in I, the tens digit at location I+1, and the ones digit at location I+2.
+                Opcode::STORE(register) => {
+                    self.check_span(self.i as usize, register as usize + 1)?;
+                    for offset in 0..=register as usize {
+                        self.write(self.wrap(self.i as usize + offset), self.v[offset]);
+                    }
+                    if self.quirks.load_store_increments_i {
+                        self.i = self.i.wrapping_add(register as u16 + 1);
+                    }
+                } //reg_dump(Vx, &I) 	Stores from V0 to VX (including VX) in memory, starting at address I. The offset from I is increased by 1 for each value written, but I itself is left unmodified.[d]
+                Opcode::READ(register) => {
+                    self.check_span(self.i as usize, register as usize + 1)?;
+                    self.watch_read(self.i as usize, register as usize + 1);
+                    for offset in 0..=register as usize {
+                        self.v[offset] = self.memory[self.wrap(self.i as usize + offset)];
+                    }
+                    if self.quirks.load_store_increments_i {
+                        self.i = self.i.wrapping_add(register as u16 + 1);
+                    }
+                } //reg_load(Vx, &I) 	Fills from V0 to VX (including VX) with values from memory, starting at address I. The offset from I is increased by 1 for each value read, but I itself is left unmodified.[d]
+                Opcode::STOREF(register) => {
+                    self.rpl[..=register as usize].copy_from_slice(&self.v[..=register as usize]);
+                }
+                Opcode::READF(register) => {
+                    self.v[..=register as usize].copy_from_slice(&self.rpl[..=register as usize]);
+                }
+            },
+            Err(UnknownOpcode) => {
+                return Err(Chip8Error::UnknownOpcode {
+                    pc: fetch_address,
+                    opcode: self.opcode,
+                });
+            }
+        }
+        Ok(StepEvent::Executed)
+    }
+
+    /// Checks that `len` bytes from `address` lie in memory. Under
+    /// [`ErrorPolicy::Wrap`] they always do, as accesses wrap around.
+    fn check_span(&self, address: usize, len: usize) -> Result<(), Chip8Error> {
+        if address + len <= self.memory.len() || self.error_policy == ErrorPolicy::Wrap {
+            Ok(())
+        } else {
+            Err(Chip8Error::MemoryOutOfBounds {
+                pc: self.opcode_address,
+                address,
+                len,
+            })
+        }
+    }
+
+    fn wrap(&self, address: usize) -> usize {
+        address % self.memory.len()
+    }
+
+    /// Reports a read of `len` bytes from `address` to the watchpoints.
+    fn watch_read(&mut self, address: usize, len: usize) {
+        if self.debugger.watchpoints.is_empty() {
+            return;
+        }
+        for offset in 0..len {
+            let address = (address + offset) % self.memory.len();
+            if self.debugger.watches(address as Address, Access::Read) {
+                let value = self.memory[address];
+                self.hit_watchpoint(Access::Read, address, value, value);
+                return;
+            }
+        }
+    }
+
+    /// Stores `value` at `address`, reporting the write to the watchpoints.
+    fn write(&mut self, address: usize, value: u8) {
+        if self.debugger.watches(address as Address, Access::Write) {
+            self.hit_watchpoint(Access::Write, address, self.memory[address], value);
+        }
+        self.memory[address] = value;
+    }
+
+    fn hit_watchpoint(&mut self, access: Access, address: usize, old: u8, new: u8) {
+        self.paused = true;
+        self.debugger.record_break(Break::Watchpoint {
+            pc: self.opcode_address,
+            opcode: self.opcode,
+            mnemonic: Opcode::decode_at(&self.memory, self.opcode_address)
+                .map_or("UNKNOWN", |opcode| opcode.mnemonic()),
+            access,
+            address: address as Address,
+            old,
+            new,
+        });
+    }
+
+    /// XO-CHIP pattern playback rate in bits per second for the current
+    /// `pitch`.
+    pub fn pattern_rate(&self) -> f32 {
+        4000.0 * 2f32.powf((self.pitch as f32 - 64.0) / 48.0)
+    }
+
+    fn shift_source(&self, register_x: u8, register_y: u8) -> u8 {
+        if self.quirks.shift_uses_vy {
+            self.v[register_y as usize]
+        } else {
+            self.v[register_x as usize]
+        }
+    }
+
+    /// Counts the delay and sound timers down by one. Called at 60 Hz of
+    /// emulated time by the [`Scheduler`](crate::clock::Scheduler), not per
+    /// instruction.
+    pub fn tick_timers(&mut self) {
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+        }
+        self.vblank_wait = false;
+        self.display.tick();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loads `program` and runs one instruction per word of it.
+    fn run(program: &[u8]) -> Chip8 {
+        let mut chip8 = Chip8::new(program);
+        chip8.paused = false;
+        for _ in 0..program.len() / 2 {
+            chip8.emulate_cycle().unwrap();
+        }
+        chip8
+    }
+
+    #[test]
+    fn font_digits_are_five_bytes_apart() {
+        // V0 := 0xB; I := hex V0
+        let chip8 = run(&[0x60, 0x0B, 0xF0, 0x29]);
+        assert_eq!(chip8.i, FONT_START_ADDRESS + 0xB * 5);
+    }
+
+    #[test]
+    fn sub_sets_vf_when_there_is_no_borrow() {
+        // V0 := 5; V1 := 3; V0 -= V1
+        let chip8 = run(&[0x60, 0x05, 0x61, 0x03, 0x80, 0x15]);
+        assert_eq!((chip8.v[0], chip8.v[0xF]), (2, 1));
+        // V0 := 3; V1 := 5; V0 -= V1
+        let chip8 = run(&[0x60, 0x03, 0x61, 0x05, 0x80, 0x15]);
+        assert_eq!((chip8.v[0], chip8.v[0xF]), (0xFE, 0));
+    }
+
+    #[test]
+    fn shr_into_vf_keeps_the_flag() {
+        // VF := 2; VF >>= VF
+        let chip8 = run(&[0x6F, 0x02, 0x8F, 0xF6]);
+        assert_eq!(chip8.v[0xF], 0);
+    }
+
+    #[test]
+    fn waiting_at_the_last_word_stays_put() {
+        let size = memory::XO_CHIP_MEMORY_SIZE;
+        for (word, event) in [
+            ([0x00, 0xFD], StepEvent::Exited),
+            ([0xF0, 0x0A], StepEvent::WaitingForKey),
+        ] {
+            let mut chip8 = Chip8::with_memory_size(&[], size);
+            chip8.paused = false;
+            chip8.memory[size - 2..].copy_from_slice(&word);
+            chip8.pc = (size - 2) as Address;
+            assert_eq!(chip8.emulate_cycle(), Ok(event));
+            assert_eq!(chip8.pc, 0xFFFE);
+        }
+    }
+
+    #[test]
+    fn skipping_past_the_last_word() {
+        // V0 == 0, so SE V0, 0 skips.
+        let size = memory::XO_CHIP_MEMORY_SIZE;
+        let mut chip8 = Chip8::with_memory_size(&[], size);
+        chip8.paused = false;
+        chip8.memory[size - 2..].copy_from_slice(&[0x30, 0x00]);
+        chip8.pc = 0xFFFE;
+        assert_eq!(chip8.emulate_cycle(), Ok(StepEvent::Executed));
+        assert_eq!(chip8.pc, 0x0002);
+
+        // With 4 KiB the skip lands past the end and the next fetch faults.
+        let size = memory::MEMORY_SIZE;
+        let mut chip8 = Chip8::with_memory_size(&[], size);
+        chip8.paused = false;
+        chip8.memory[size - 2..].copy_from_slice(&[0x30, 0x00]);
+        chip8.pc = (size - 2) as Address;
+        assert_eq!(chip8.emulate_cycle(), Ok(StepEvent::Executed));
+        assert_eq!(
+            chip8.emulate_cycle(),
+            Err(Chip8Error::PcOutOfBounds { pc: 0x1002 })
+        );
+    }
+}
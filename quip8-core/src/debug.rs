@@ -0,0 +1,345 @@
+use std::fmt;
+
+use crate::decode::Address;
+use crate::error::Chip8Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub address: Address,
+    pub enabled: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Which accesses a [`Watchpoint`] triggers on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl WatchKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            WatchKind::Read => "read",
+            WatchKind::Write => "write",
+            WatchKind::ReadWrite => "read/write",
+        }
+    }
+
+    fn matches(self, access: Access) -> bool {
+        matches!(
+            (self, access),
+            (WatchKind::ReadWrite, _)
+                | (WatchKind::Read, Access::Read)
+                | (WatchKind::Write, Access::Write)
+        )
+    }
+}
+
+/// Pauses execution when an instruction touches memory in `start..=end`.
+/// Covers instruction fetches, sprite reads and every instruction that loads
+/// or stores through I.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub start: Address,
+    pub end: Address,
+    pub kind: WatchKind,
+    pub enabled: bool,
+}
+
+impl Watchpoint {
+    pub fn triggers_on(&self, address: Address, access: Access) -> bool {
+        self.enabled && (self.start..=self.end).contains(&address) && self.kind.matches(access)
+    }
+}
+
+/// Why execution stopped on its own.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Break {
+    /// `pc` reached an enabled breakpoint.
+    Breakpoint(Address),
+    /// The instruction at `pc` accessed memory covered by a watchpoint. For
+    /// reads `old` and `new` are both the value read.
+    Watchpoint {
+        pc: Address,
+        opcode: u16,
+        mnemonic: &'static str,
+        access: Access,
+        address: Address,
+        old: u8,
+        new: u8,
+    },
+    /// An instruction faulted under [`ErrorPolicy::Halt`](crate::error::ErrorPolicy::Halt).
+    Error(Chip8Error),
+}
+
+impl fmt::Display for Break {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Break::Breakpoint(address) => write!(f, "Breakpoint hit at {address:#05X}"),
+            Break::Watchpoint {
+                pc,
+                opcode,
+                mnemonic,
+                access: Access::Read,
+                address,
+                old,
+                ..
+            } => write!(
+                f,
+                "Watchpoint hit: {mnemonic} ({opcode:04X}) at {pc:#05X} read {old:#04X} from {address:#05X}"
+            ),
+            Break::Watchpoint {
+                pc,
+                opcode,
+                mnemonic,
+                access: Access::Write,
+                address,
+                old,
+                new,
+            } => write!(
+                f,
+                "Watchpoint hit: {mnemonic} ({opcode:04X}) at {pc:#05X} wrote {address:#05X}: {old:#04X} -> {new:#04X}"
+            ),
+            Break::Error(error) => write!(f, "Halted: {error}"),
+        }
+    }
+}
+
+/// Debugging state carried by a [`Chip8`](crate::cpu::Chip8). It survives
+/// loading save states so breakpoints aren't lost when travelling through
+/// time.
+#[derive(Clone, Debug, Default)]
+pub struct Debugger {
+    /// Kept sorted by address.
+    breakpoints: Vec<Breakpoint>,
+    pub watchpoints: Vec<Watchpoint>,
+    /// Set when execution pauses itself; cleared by [`take_break`](Self::take_break).
+    last_break: Option<Break>,
+}
+
+impl Debugger {
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn breakpoint(&self, address: Address) -> Option<&Breakpoint> {
+        self.breakpoints.iter().find(|b| b.address == address)
+    }
+
+    /// Adds an enabled breakpoint at `address`, or removes the one already
+    /// there.
+    pub fn toggle_breakpoint(&mut self, address: Address) {
+        match self
+            .breakpoints
+            .binary_search_by_key(&address, |b| b.address)
+        {
+            Ok(index) => {
+                self.breakpoints.remove(index);
+            }
+            Err(index) => self.breakpoints.insert(
+                index,
+                Breakpoint {
+                    address,
+                    enabled: true,
+                },
+            ),
+        }
+    }
+
+    pub fn set_breakpoint_enabled(&mut self, address: Address, enabled: bool) {
+        if let Some(b) = self.breakpoints.iter_mut().find(|b| b.address == address) {
+            b.enabled = enabled;
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, address: Address) {
+        self.breakpoints.retain(|b| b.address != address);
+    }
+
+    /// Whether an enabled breakpoint sits at `address`.
+    pub fn breaks_at(&self, address: Address) -> bool {
+        self.breakpoint(address).is_some_and(|b| b.enabled)
+    }
+
+    /// Whether an enabled watchpoint covers `address` for `access`.
+    pub fn watches(&self, address: Address, access: Access) -> bool {
+        self.watchpoints
+            .iter()
+            .any(|w| w.triggers_on(address, access))
+    }
+
+    /// Records why execution paused. The first reason is kept until taken, so
+    /// an instruction tripping several watchpoints reports the first.
+    pub(crate) fn record_break(&mut self, reason: Break) {
+        if self.last_break.is_none() {
+            self.last_break = Some(reason);
+        }
+    }
+
+    /// Whether a reason for pausing is waiting to be taken.
+    pub(crate) fn has_break(&self) -> bool {
+        self.last_break.is_some()
+    }
+
+    /// The reason execution last paused itself, if not already taken.
+    pub fn take_break(&mut self) -> Option<Break> {
+        self.last_break.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::{Chip8, StepEvent};
+
+    /// Runs until the machine pauses itself, giving up after `limit` cycles.
+    fn run(chip8: &mut Chip8, limit: usize) {
+        chip8.paused = false;
+        for _ in 0..limit {
+            if chip8.paused {
+                return;
+            }
+            chip8.emulate_cycle().unwrap();
+        }
+        panic!("still running at {:#05X}", chip8.pc);
+    }
+
+    #[test]
+    fn runs_to_a_breakpoint() {
+        // V0 := 1; V1 := 2; V2 := 3; jump to itself
+        let mut chip8 = Chip8::new(&[0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0x12, 0x06]);
+        chip8.debugger.toggle_breakpoint(0x204);
+        run(&mut chip8, 10);
+        assert_eq!(chip8.pc, 0x204);
+        assert_eq!(chip8.v[..3], [1, 2, 0]);
+        assert_eq!(chip8.debugger.take_break(), Some(Break::Breakpoint(0x204)));
+    }
+
+    #[test]
+    fn stepping_off_a_breakpoint_runs_it() {
+        // loop: V0 += 1; jump loop
+        let mut chip8 = Chip8::new(&[0x70, 0x01, 0x12, 0x00]);
+        chip8.debugger.toggle_breakpoint(0x200);
+        chip8.paused = false;
+        assert_eq!(chip8.emulate_cycle(), Ok(StepEvent::Executed));
+        assert_eq!(chip8.emulate_cycle(), Ok(StepEvent::Break));
+        assert_eq!(chip8.debugger.take_break(), Some(Break::Breakpoint(0x200)));
+        // Resuming from the breakpoint runs the loop once more, then stops
+        // on the way back round.
+        run(&mut chip8, 10);
+        assert_eq!((chip8.pc, chip8.v[0]), (0x200, 2));
+        assert_eq!(chip8.debugger.take_break(), Some(Break::Breakpoint(0x200)));
+    }
+
+    #[test]
+    fn waiting_on_a_breakpoint_does_not_retrigger() {
+        // V0 := 1; V1 := key
+        let mut chip8 = Chip8::new(&[0x60, 0x01, 0xF1, 0x0A]);
+        chip8.debugger.toggle_breakpoint(0x202);
+        run(&mut chip8, 10);
+        assert_eq!(chip8.debugger.take_break(), Some(Break::Breakpoint(0x202)));
+        chip8.paused = false;
+        for _ in 0..3 {
+            assert_eq!(chip8.emulate_cycle(), Ok(StepEvent::WaitingForKey));
+        }
+        assert!(!chip8.paused);
+        assert_eq!(chip8.debugger.take_break(), None);
+    }
+
+    #[test]
+    fn disabled_breakpoints_are_skipped() {
+        let mut debugger = Debugger::default();
+        for address in [0x208, 0x200, 0x204] {
+            debugger.toggle_breakpoint(address);
+        }
+        let addresses: Vec<Address> = debugger.breakpoints().iter().map(|b| b.address).collect();
+        assert_eq!(addresses, [0x200, 0x204, 0x208]);
+        debugger.set_breakpoint_enabled(0x204, false);
+        assert!(!debugger.breaks_at(0x204));
+        debugger.toggle_breakpoint(0x200);
+        assert!(debugger.breakpoint(0x200).is_none());
+    }
+
+    /// Runs `program` with a watchpoint over `address` until it pauses.
+    fn watched(program: &[u8], address: Address, kind: WatchKind) -> Option<Break> {
+        let mut chip8 = Chip8::new(program);
+        chip8.memory[0x300..0x303].copy_from_slice(&[7, 8, 9]);
+        chip8.debugger.watchpoints.push(Watchpoint {
+            start: address,
+            end: address,
+            kind,
+            enabled: true,
+        });
+        chip8.paused = false;
+        for _ in 0..program.len() / 2 {
+            chip8.emulate_cycle().unwrap();
+        }
+        chip8.debugger.take_break()
+    }
+
+    fn hit(
+        pc: Address,
+        opcode: u16,
+        mnemonic: &'static str,
+        access: Access,
+        address: Address,
+        (old, new): (u8, u8),
+    ) -> Option<Break> {
+        Some(Break::Watchpoint {
+            pc,
+            opcode,
+            mnemonic,
+            access,
+            address,
+            old,
+            new,
+        })
+    }
+
+    #[test]
+    fn watchpoints_catch_stores() {
+        // I := 0x300; V1 := 5; save V1
+        let store = [0xA3, 0x00, 0x61, 0x05, 0xF1, 0x55];
+        assert_eq!(
+            watched(&store, 0x301, WatchKind::Write),
+            hit(0x204, 0xF155, "STORE", Access::Write, 0x301, (8, 5))
+        );
+        assert_eq!(watched(&store, 0x301, WatchKind::Read), None);
+        // I := 0x300; V0 := 123; bcd V0
+        let bcd = [0xA3, 0x00, 0x60, 0x7B, 0xF0, 0x33];
+        assert_eq!(
+            watched(&bcd, 0x302, WatchKind::ReadWrite),
+            hit(0x204, 0xF033, "BCD", Access::Write, 0x302, (9, 3))
+        );
+        // I := 0x300; V2 := 5; save V1 - V2
+        let store_range = [0xA3, 0x00, 0x62, 0x05, 0x51, 0x22];
+        assert_eq!(
+            watched(&store_range, 0x301, WatchKind::Write),
+            hit(0x204, 0x5122, "STORER", Access::Write, 0x301, (8, 5))
+        );
+    }
+
+    #[test]
+    fn watchpoints_catch_loads() {
+        // I := 0x300; load V2
+        let read = [0xA3, 0x00, 0xF2, 0x65];
+        assert_eq!(
+            watched(&read, 0x302, WatchKind::Read),
+            hit(0x202, 0xF265, "READ", Access::Read, 0x302, (9, 9))
+        );
+        assert_eq!(watched(&read, 0x302, WatchKind::Write), None);
+        // I := 0x300; load V3 - V1
+        let read_range = [0xA3, 0x00, 0x53, 0x13];
+        assert_eq!(
+            watched(&read_range, 0x301, WatchKind::ReadWrite),
+            hit(0x202, 0x5313, "READR", Access::Read, 0x301, (8, 8))
+        );
+    }
+}
@@ -0,0 +1,790 @@
+use crate::cpu::Chip8;
+
+pub type Address = u16;
+pub type RegisterAddress = u8;
+pub type Literal = u8;
+
+#[allow(clippy::upper_case_acronyms)]
+pub enum Opcode {
+    // 0x0NNN - Call
+    // Calls machine code routine (RCA 1802 for COSMAC VIP) at address NNN. Not necessary for most ROMs.
+    SYS(Address),
+
+    // 0x00E0 - Display
+    // C Pseudo: disp_clear()
+    // Clears the screen.
+    CLR,
+
+    // 0x00EE - Flow
+    // C Pseudo: return;
+    // Returns from a subroutine.
+    RTS,
+
+    // 0x00CN - Display (SUPER-CHIP)
+    // C Pseudo: scroll_down(N)
+    // Scrolls the display down by N pixels.
+    SCD(Literal),
+
+    // 0x00DN - Display (XO-CHIP)
+    // C Pseudo: scroll_up(N)
+    // Scrolls the display up by N pixels.
+    SCU(Literal),
+
+    // 0x00FB - Display (SUPER-CHIP)
+    // C Pseudo: scroll_right(4)
+    // Scrolls the display right by 4 pixels.
+    SCR,
+
+    // 0x00FC - Display (SUPER-CHIP)
+    // C Pseudo: scroll_left(4)
+    // Scrolls the display left by 4 pixels.
+    SCL,
+
+    // 0x00FD - Flow (SUPER-CHIP)
+    // C Pseudo: exit()
+    // Exits the interpreter.
+    EXIT,
+
+    // 0x00FE - Display (SUPER-CHIP)
+    // C Pseudo: lores()
+    // Switches to the 64x32 low resolution mode.
+    LOW,
+
+    // 0x00FF - Display (SUPER-CHIP)
+    // C Pseudo: hires()
+    // Switches to the 128x64 high resolution mode.
+    HIGH,
+
+    // 0x1NNN - Flow
+    // C Pseudo: goto NNN;
+    // Jumps to address NNN.
+    JUMP(Address),
+
+    // 0x2NNN - Flow
+    // C Pseudo: *(0xNNN)()
+    // Calls subroutine at NNN.
+    CALL(Address),
+
+    // 0x3XNN - Cond
+    // C Pseudo: if (Vx == NN)
+    // Skips the next instruction if VX equals NN (usually the next instruction
+    // is a jump to skip a code block).
+    SKE((RegisterAddress, Literal)),
+
+    // 0x4XNN - Cond
+    // C Pseudo: if (Vx != NN)
+    // Skips the next instruction if VX does not equal NN (usually the next
+    // instruction is a jump to skip a code block).
+    SKNE((RegisterAddress, Literal)),
+
+    // 0x5XY0 - Cond
+    // C Pseudo: if (Vx == Vy)
+    // Skips the next instruction if VX equals VY (usually the next instruction
+    // is a jump to skip a code block).
+    SKRE((RegisterAddress, RegisterAddress)),
+
+    // 0x5XY2 - MEM (XO-CHIP)
+    // C Pseudo: save(Vx..Vy, &I)
+    // Stores VX to VY (inclusive, in either order) in memory, starting at
+    // address I. I is not modified.
+    STORER((RegisterAddress, RegisterAddress)),
+
+    // 0x5XY3 - MEM (XO-CHIP)
+    // C Pseudo: load(Vx..Vy, &I)
+    // Fills VX to VY (inclusive, in either order) with values from memory,
+    // starting at address I. I is not modified.
+    READR((RegisterAddress, RegisterAddress)),
+
+    // 0x6XNN - Const
+    // C Pseudo: Vx = NN
+    // Sets VX to NN.
+    LOAD((RegisterAddress, Literal)),
+
+    // 0x7XNN - Const
+    // C Pseudo: Vx += NN
+    // Adds NN to VX (carry flag is not changed).
+    ADD((RegisterAddress, Literal)),
+
+    // 0x8XY0 - Assig
+    // C Pseudo: Vx = Vy
+    // Sets VX to the value of VY.
+    MOVE((RegisterAddress, RegisterAddress)),
+
+    // 0x8XY1 - BitOp
+    // C Pseudo: Vx |= Vy
+    // Sets VX to VX or VY. (bitwise OR operation)
+    OR((RegisterAddress, RegisterAddress)),
+
+    // 0x8XY2 - BitOp
+    // C Pseudo: Vx &= Vy
+    // Sets VX to VX and VY. (bitwise AND operation)
+    AND((RegisterAddress, RegisterAddress)),
+
+    // 0x8XY3[a] - BitOp
+    // C Pseudo: Vx ^= Vy
+    // Sets VX to VX xor VY.
+    XOR((RegisterAddress, RegisterAddress)),
+
+    // 0x8XY4 - Math
+    // C Pseudo: Vx += Vy
+    // Adds VY to VX. VF is set to 1 when there's a carry, and to 0 when there
+    // is not.
+    ADDR((RegisterAddress, RegisterAddress)),
+
+    // 0x8XY5 - Math
+    // C Pseudo: Vx -= Vy
+    // VY is subtracted from VX. VF is set to 0 when there's a borrow, and 1
+    // when there is not.
+    SUB((RegisterAddress, RegisterAddress)),
+
+    // 0x8XY6 - BitOp
+    // C Pseudo: Vx >>= 1
+    // Stores the least significant bit of VX in VF and then shifts VX to the
+    // right by 1.
+    // Better Name?
+    SHR((RegisterAddress, RegisterAddress)),
+
+    // 0x8XY7 - Math
+    // C Pseudo: Vx = Vy - Vx
+    // Sets VX to VY minus VX. VF is set to 0 when there's a borrow, and 1 when
+    //  there is not.
+    // Better Name?
+    RSUB((RegisterAddress, RegisterAddress)),
+
+    // 0x8XYE - BitOp
+    // C Pseudo: Vx <<= 1
+    // Stores the most significant bit of VX in VF and then shifts VX to the
+    // left by 1.
+    // Better Name?
+    SHL((RegisterAddress, RegisterAddress)),
+
+    // 0x9XY0 - Cond
+    // C Pseudo: if (Vx != Vy)
+    // Skips the next instruction if VX does not equal VY. (Usually the next
+    // instruction is a jump to skip a code block);
+    SKRNE((RegisterAddress, RegisterAddress)),
+
+    // 0xANNN - MEM
+    // C Pseudo: I = NNN
+    // Sets I to the address NNN.
+    LOADI(Address),
+
+    // 0xF000 0xNNNN - MEM (XO-CHIP)
+    // C Pseudo: I = NNNN
+    // Sets I to the 16-bit address in the following word. This is the only
+    // four byte instruction.
+    LOADIL(Address),
+
+    // 0xBNNN - Flow
+    // C Pseudo: PC = V0 + NNN
+    // Jumps to the address NNN plus V0.
+    JUMPI(Address),
+
+    // 0xCXNN - Rand
+    // C Pseudo: Vx = rand() & NN
+    // Sets VX to the result of a bitwise and operation on a random number
+    // (Typically: 0 to 255) and NN.
+    RAND((RegisterAddress, Literal)),
+
+    // 0xDXYN - Display
+    // C Pseudo: draw(Vx, Vy, N)
+    // Draws a sprite at coordinate (VX, VY) that has a width of 8 pixels and
+    // a height of N pixels. Each row of 8 pixels is read as bit-coded starting
+    // from memory location I; I value does not change after the execution of
+    // this instruction. As described above, VF is set to 1 if any screen pixels
+    // are flipped from set to unset when the sprite is drawn, and to 0 if that
+    // does not happen. (SUPER-CHIP) With N = 0, draws a 16x16 sprite stored as
+    // two bytes per row.
+    DRAW((RegisterAddress, RegisterAddress, Literal)),
+
+    // 0xEX9E - KeyOp
+    // C Pseudo: if (key() == Vx)
+    // Skips the next instruction if the key stored in VX is pressed (usually
+    // the next instruction is a jump to skip a code block).
+    SKPR(RegisterAddress),
+
+    // 0xEXA1 - KeyOp
+    // C Pseudo: if (key() != Vx)
+    // Skips the next instruction if the key stored in VX is not pressed
+    // (usually the next instruction is a jump to skip a code block).
+    SKUP(RegisterAddress),
+
+    // 0xFX07 - Timer
+    // C Pseudo: Vx = get_delay()
+    // Sets VX to the value of the delay timer.
+    MOVED(RegisterAddress),
+
+    // 0xFX0A - KeyOp
+    // C Pseudo: Vx = get_key()
+    // A key press is awaited, and then stored in VX (blocking operation, all
+    // instruction halted until next key event).
+    KEYD(RegisterAddress),
+
+    // 0xFX15 - Timer
+    // C Pseudo: delay_timer(Vx)
+    // Sets the delay timer to VX.
+    LOADD(RegisterAddress),
+
+    // 0xFX18 - Sound
+    // C Pseudo: sound_timer(Vx)
+    // Sets the sound timer to VX.
+    LOADS(RegisterAddress),
+
+    // 0xFN01 - Display (XO-CHIP)
+    // C Pseudo: plane(N)
+    // Selects the bitplanes (bitmask N) that drawing, clearing and scrolling
+    // apply to.
+    PLANE(Literal),
+
+    // 0xF002 - Sound (XO-CHIP)
+    // C Pseudo: audio(&I)
+    // Loads the 16 byte audio pattern buffer from memory starting at I.
+    AUDIO,
+
+    // 0xFX3A - Sound (XO-CHIP)
+    // C Pseudo: pitch(Vx)
+    // Sets the audio pattern playback rate to 4000*2^((VX-64)/48) bits per
+    // second.
+    PITCH(RegisterAddress),
+
+    // 0xFX1E - MEM
+    // C Pseudo: I += Vx
+    // Adds VX to I. VF is not affected.[c]
+    ADDI(RegisterAddress),
+
+    // 0xFX29 - MEM
+    // C Pseudo: I = sprite_addr[Vx]
+    // Sets I to the location of the sprite for the character in VX. Characters
+    //  0-F (in hexadecimal) are represented by a 4x5 font.
+    LDSPR(RegisterAddress),
+
+    // 0xFX30 - MEM (SUPER-CHIP)
+    // C Pseudo: I = big_sprite_addr[Vx]
+    // Sets I to the location of the 8x10 sprite for the digit in VX.
+    LDBSPR(RegisterAddress),
+
+    // 0xFX33 - BCD
+    // C Pseudo: set_BCD(Vx) *(I+0) = BCD(3); *(I+1) = BCD(2); *(I+2) = BCD(1);
+    // Stores the binary-coded decimal representation of VX, with the hundreds
+    // digit in memory at location in I, the tens digit at location I+1, and
+    // the ones digit at location I+2.
+    BCD(RegisterAddress),
+
+    // 0xFX55 - MEM
+    // C Pseudo: reg_dump(Vx, &I)
+    // Stores from V0 to VX (including VX) in memory, starting at address I.
+    // The offset from I is increased by 1 for each value written, but I itself
+    // is left unmodified.[d]
+    STORE(RegisterAddress),
+
+    // 0xFX65 - MEM
+    // C Pseudo: reg_load(Vx, &I)
+    // Fills from V0 to VX (including VX) with values from memory, starting at
+    // address I. The offset from I is increased by 1 for each value read, but
+    // I itself is left unmodified.[d]
+    READ(RegisterAddress),
+
+    // 0xFX75 - MEM (SUPER-CHIP)
+    // C Pseudo: flags_dump(Vx)
+    // Stores V0 to VX (including VX) in the RPL user flags.
+    STOREF(RegisterAddress),
+
+    // 0xFX85 - MEM (SUPER-CHIP)
+    // C Pseudo: flags_load(Vx)
+    // Fills V0 to VX (including VX) from the RPL user flags.
+    READF(RegisterAddress),
+}
+
+#[derive(Debug)]
+pub struct UnknownOpcode;
+
+impl Opcode {
+    pub fn decode(raw_opcode: u16) -> Result<Opcode, UnknownOpcode> {
+        match raw_opcode & 0xF000 {
+            0x0000 => match raw_opcode {
+                0x00E0 => Ok(Opcode::CLR),
+                0x00EE => Ok(Opcode::RTS),
+                0x00C0..=0x00CF => Ok(Opcode::SCD((raw_opcode & 0x000F) as Literal)),
+                0x00D0..=0x00DF => Ok(Opcode::SCU((raw_opcode & 0x000F) as Literal)),
+                0x00FB => Ok(Opcode::SCR),
+                0x00FC => Ok(Opcode::SCL),
+                0x00FD => Ok(Opcode::EXIT),
+                0x00FE => Ok(Opcode::LOW),
+                0x00FF => Ok(Opcode::HIGH),
+                _ => Ok(Opcode::SYS((raw_opcode & 0x0FFF) as Address)),
+            },
+            0x1000 => Ok(Opcode::JUMP((raw_opcode & 0x0FFF) as Address)),
+            0x2000 => Ok(Opcode::CALL((raw_opcode & 0x0FFF) as Address)),
+            0x3000 => Ok(Opcode::SKE((
+                ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                (raw_opcode & 0x00FF) as Literal,
+            ))),
+            0x4000 => Ok(Opcode::SKNE((
+                ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                (raw_opcode & 0x00FF) as Literal,
+            ))),
+            0x5000 => match raw_opcode & 0x000F {
+                0x0000 => Ok(Opcode::SKRE((
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
+                ))),
+                0x0002 => Ok(Opcode::STORER((
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
+                ))),
+                0x0003 => Ok(Opcode::READR((
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
+                ))),
+                _ => Err(UnknownOpcode),
+            },
+            0x6000 => Ok(Opcode::LOAD((
+                ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                (raw_opcode & 0x00FF) as Literal,
+            ))),
+            0x7000 => Ok(Opcode::ADD((
+                ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                (raw_opcode & 0x00FF) as Literal,
+            ))),
+            0x8000 => match raw_opcode & 0x000F {
+                0x0000 => Ok(Opcode::MOVE((
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
+                ))),
+                0x0001 => Ok(Opcode::OR((
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
+                ))),
+                0x0002 => Ok(Opcode::AND((
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
+                ))),
+                0x0003 => Ok(Opcode::XOR((
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
+                ))),
+                0x0004 => Ok(Opcode::ADDR((
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
+                ))),
+                0x0005 => Ok(Opcode::SUB((
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
+                ))),
+                0x0006 => Ok(Opcode::SHR((
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
+                ))),
+                0x0007 => Ok(Opcode::RSUB((
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
+                ))),
+                0x000E => Ok(Opcode::SHL((
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
+                ))),
+                _ => Err(UnknownOpcode),
+            },
+            0x9000 => match raw_opcode & 0x000F {
+                0x0000 => Ok(Opcode::SKRNE((
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
+                ))),
+                _ => Err(UnknownOpcode),
+            },
+            0xA000 => Ok(Opcode::LOADI((raw_opcode & 0x0FFF) as Address)),
+            0xB000 => Ok(Opcode::JUMPI((raw_opcode & 0x0FFF) as Address)),
+            0xC000 => Ok(Opcode::RAND((
+                ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                (raw_opcode & 0x00FF) as Literal,
+            ))),
+            0xD000 => Ok(Opcode::DRAW((
+                ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
+                (raw_opcode & 0x000F) as Literal,
+            ))),
+            0xE000 => match raw_opcode & 0x00FF {
+                0x009E => Ok(Opcode::SKPR(
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                )),
+                0x00A1 => Ok(Opcode::SKUP(
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                )),
+                _ => Err(UnknownOpcode),
+            },
+            0xF000 => match raw_opcode & 0x00FF {
+                0x0001 => Ok(Opcode::PLANE(((raw_opcode & 0x0F00) >> 8) as Literal)),
+                0x0002 if raw_opcode == 0xF002 => Ok(Opcode::AUDIO),
+                0x0007 => Ok(Opcode::MOVED(
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                )),
+                0x000A => Ok(Opcode::KEYD(
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                )),
+                0x0015 => Ok(Opcode::LOADD(
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                )),
+                0x0018 => Ok(Opcode::LOADS(
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                )),
+                0x001E => Ok(Opcode::ADDI(
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                )),
+                0x0029 => Ok(Opcode::LDSPR(
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                )),
+                0x0030 => Ok(Opcode::LDBSPR(
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                )),
+                0x003A => Ok(Opcode::PITCH(
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                )),
+                0x0033 => Ok(Opcode::BCD(((raw_opcode & 0x0F00) >> 8) as RegisterAddress)),
+                0x0055 => Ok(Opcode::STORE(
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                )),
+                0x0065 => Ok(Opcode::READ(
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                )),
+                0x0075 => Ok(Opcode::STOREF(
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                )),
+                0x0085 => Ok(Opcode::READF(
+                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
+                )),
+                _ => Err(UnknownOpcode),
+            },
+
+            _ => Err(UnknownOpcode),
+        }
+    }
+
+    /// Decodes the instruction at `address`, reading the operand word of
+    /// XO-CHIP's four byte `F000 NNNN` as well. Reads past the end of `memory`
+    /// wrap around.
+    pub fn decode_at(memory: &[u8], address: Address) -> Result<Opcode, UnknownOpcode> {
+        let word = |address: usize| {
+            (memory[address % memory.len()] as u16) << 8
+                | memory[(address + 1) % memory.len()] as u16
+        };
+        match word(address as usize) {
+            0xF000 => Ok(Opcode::LOADIL(word(address as usize + 2))),
+            raw_opcode => Opcode::decode(raw_opcode),
+        }
+    }
+
+    /// Encodes the instruction back into its big-endian bytes, `size()` of
+    /// them. The inverse of [`decode_at`](Self::decode_at); operands wider
+    /// than their field are truncated.
+    pub fn encode(&self) -> Vec<u8> {
+        let xy = |x: RegisterAddress, y: RegisterAddress| {
+            ((x as u16 & 0xF) << 8) | ((y as u16 & 0xF) << 4)
+        };
+        let xnn = |x: RegisterAddress, nn: Literal| ((x as u16 & 0xF) << 8) | nn as u16;
+        let x = |x: RegisterAddress| (x as u16 & 0xF) << 8;
+        let word = match self {
+            Opcode::SYS(address) => address & 0x0FFF,
+            Opcode::CLR => 0x00E0,
+            Opcode::RTS => 0x00EE,
+            Opcode::SCD(literal) => 0x00C0 | (*literal as u16 & 0xF),
+            Opcode::SCU(literal) => 0x00D0 | (*literal as u16 & 0xF),
+            Opcode::SCR => 0x00FB,
+            Opcode::SCL => 0x00FC,
+            Opcode::EXIT => 0x00FD,
+            Opcode::LOW => 0x00FE,
+            Opcode::HIGH => 0x00FF,
+            Opcode::JUMP(address) => 0x1000 | (address & 0x0FFF),
+            Opcode::CALL(address) => 0x2000 | (address & 0x0FFF),
+            Opcode::SKE((register, literal)) => 0x3000 | xnn(*register, *literal),
+            Opcode::SKNE((register, literal)) => 0x4000 | xnn(*register, *literal),
+            Opcode::SKRE((register_x, register_y)) => 0x5000 | xy(*register_x, *register_y),
+            Opcode::STORER((register_x, register_y)) => 0x5002 | xy(*register_x, *register_y),
+            Opcode::READR((register_x, register_y)) => 0x5003 | xy(*register_x, *register_y),
+            Opcode::LOAD((register, literal)) => 0x6000 | xnn(*register, *literal),
+            Opcode::ADD((register, literal)) => 0x7000 | xnn(*register, *literal),
+            Opcode::MOVE((register_x, register_y)) => 0x8000 | xy(*register_x, *register_y),
+            Opcode::OR((register_x, register_y)) => 0x8001 | xy(*register_x, *register_y),
+            Opcode::AND((register_x, register_y)) => 0x8002 | xy(*register_x, *register_y),
+            Opcode::XOR((register_x, register_y)) => 0x8003 | xy(*register_x, *register_y),
+            Opcode::ADDR((register_x, register_y)) => 0x8004 | xy(*register_x, *register_y),
+            Opcode::SUB((register_x, register_y)) => 0x8005 | xy(*register_x, *register_y),
+            Opcode::SHR((register_x, register_y)) => 0x8006 | xy(*register_x, *register_y),
+            Opcode::RSUB((register_x, register_y)) => 0x8007 | xy(*register_x, *register_y),
+            Opcode::SHL((register_x, register_y)) => 0x800E | xy(*register_x, *register_y),
+            Opcode::SKRNE((register_x, register_y)) => 0x9000 | xy(*register_x, *register_y),
+            Opcode::LOADI(address) => 0xA000 | (address & 0x0FFF),
+            Opcode::LOADIL(address) => {
+                return [0xF0, 0x00, (address >> 8) as u8, *address as u8].to_vec()
+            }
+            Opcode::JUMPI(address) => 0xB000 | (address & 0x0FFF),
+            Opcode::RAND((register, literal)) => 0xC000 | xnn(*register, *literal),
+            Opcode::DRAW((register_x, register_y, literal)) => {
+                0xD000 | xy(*register_x, *register_y) | (*literal as u16 & 0xF)
+            }
+            Opcode::SKPR(register) => 0xE09E | x(*register),
+            Opcode::SKUP(register) => 0xE0A1 | x(*register),
+            Opcode::MOVED(register) => 0xF007 | x(*register),
+            Opcode::KEYD(register) => 0xF00A | x(*register),
+            Opcode::LOADD(register) => 0xF015 | x(*register),
+            Opcode::LOADS(register) => 0xF018 | x(*register),
+            Opcode::PLANE(literal) => 0xF001 | x(*literal),
+            Opcode::AUDIO => 0xF002,
+            Opcode::PITCH(register) => 0xF03A | x(*register),
+            Opcode::ADDI(register) => 0xF01E | x(*register),
+            Opcode::LDSPR(register) => 0xF029 | x(*register),
+            Opcode::LDBSPR(register) => 0xF030 | x(*register),
+            Opcode::BCD(register) => 0xF033 | x(*register),
+            Opcode::STORE(register) => 0xF055 | x(*register),
+            Opcode::READ(register) => 0xF065 | x(*register),
+            Opcode::STOREF(register) => 0xF075 | x(*register),
+            Opcode::READF(register) => 0xF085 | x(*register),
+        };
+        word.to_be_bytes().to_vec()
+    }
+
+    /// Length of the instruction in bytes.
+    pub fn size(&self) -> u16 {
+        match self {
+            Opcode::LOADIL(_address) => 4,
+            _ => 2,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Opcode::SYS(_address) => "SYS",
+            Opcode::CLR => "CLR",
+            Opcode::RTS => "RTS",
+            Opcode::SCD(_literal) => "SCD",
+            Opcode::SCU(_literal) => "SCU",
+            Opcode::SCR => "SCR",
+            Opcode::SCL => "SCL",
+            Opcode::EXIT => "EXIT",
+            Opcode::LOW => "LOW",
+            Opcode::HIGH => "HIGH",
+            Opcode::JUMP(_address) => "JUMP",
+            Opcode::CALL(_address) => "CALL",
+            Opcode::SKE((_register, _literal)) => "SKE",
+            Opcode::SKNE((_register, _literal)) => "SKNE",
+            Opcode::SKRE((_register_x, _register_y)) => "SKRE",
+            Opcode::STORER((_register_x, _register_y)) => "STORER",
+            Opcode::READR((_register_x, _register_y)) => "READR",
+            Opcode::LOAD((_register, _literal)) => "LOAD",
+            Opcode::ADD((_register, _literal)) => "ADD",
+            Opcode::MOVE((_register_x, _register_y)) => "MOVE",
+            Opcode::OR((_register_x, _register_y)) => "OR",
+            Opcode::AND((_register_x, _register_y)) => "AND",
+            Opcode::XOR((_register_x, _register_y)) => "XOR",
+            Opcode::ADDR((_register_x, _register_y)) => "ADDR",
+            Opcode::SUB((_register_x, _register_y)) => "SUB",
+            Opcode::SHR((_register_x, _register_y)) => "SHR",
+            Opcode::RSUB((_register_x, _register_y)) => "RSUB",
+            Opcode::SHL((_register_x, _register_y)) => "SHL",
+            Opcode::SKRNE((_register_x, _register_y)) => "SKRNE",
+            Opcode::LOADI(_address) => "LOADI",
+            Opcode::LOADIL(_address) => "LOADIL",
+            Opcode::JUMPI(_address) => "JUMPI",
+            Opcode::RAND((_register, _literal)) => "RAND",
+            Opcode::DRAW((_register_x, _register_y, _literal)) => "DRAW",
+            Opcode::SKPR(_register) => "SKPR",
+            Opcode::SKUP(_register) => "SKUP",
+            Opcode::MOVED(_register) => "MOVED",
+            Opcode::KEYD(_register) => "KEYD",
+            Opcode::LOADD(_register) => "LOADD",
+            Opcode::LOADS(_register) => "LOADS",
+            Opcode::PLANE(_literal) => "PLANE",
+            Opcode::AUDIO => "AUDIO",
+            Opcode::PITCH(_register) => "PITCH",
+            Opcode::ADDI(_register) => "ADDI",
+            Opcode::LDSPR(_register) => "LDSPR",
+            Opcode::LDBSPR(_register) => "LDBSPR",
+            Opcode::BCD(_register) => "BCD",
+            Opcode::STORE(_register) => "STORE",
+            Opcode::READ(_register) => "READ",
+            Opcode::STOREF(_register) => "STOREF",
+            Opcode::READF(_register) => "READF",
+        }
+    }
+
+    pub fn operand(&self, i: u8) -> Option<u16> {
+        let operands = match self {
+            Opcode::SYS(address) => (Some(*address), None, None),
+            Opcode::CLR => (None, None, None),
+            Opcode::RTS => (None, None, None),
+            Opcode::SCD(literal) => (Some(*literal as u16), None, None),
+            Opcode::SCU(literal) => (Some(*literal as u16), None, None),
+            Opcode::SCR => (None, None, None),
+            Opcode::SCL => (None, None, None),
+            Opcode::EXIT => (None, None, None),
+            Opcode::LOW => (None, None, None),
+            Opcode::HIGH => (None, None, None),
+            Opcode::JUMP(address) => (Some(*address), None, None),
+            Opcode::CALL(address) => (Some(*address), None, None),
+            Opcode::SKE((register, literal)) => (Some(*register as u16), Some(*literal), None),
+            Opcode::SKNE((register, literal)) => (Some(*register as u16), Some(*literal), None),
+            Opcode::SKRE((register_x, register_y)) => {
+                (Some(*register_x as u16), Some(*register_y), None)
+            }
+            Opcode::STORER((register_x, register_y)) => {
+                (Some(*register_x as u16), Some(*register_y), None)
+            }
+            Opcode::READR((register_x, register_y)) => {
+                (Some(*register_x as u16), Some(*register_y), None)
+            }
+            Opcode::LOAD((register, literal)) => (Some(*register as u16), Some(*literal), None),
+            Opcode::ADD((register, literal)) => (Some(*register as u16), Some(*literal), None),
+            Opcode::MOVE((register_x, register_y)) => {
+                (Some(*register_x as u16), Some(*register_y), None)
+            }
+            Opcode::OR((register_x, register_y)) => {
+                (Some(*register_x as u16), Some(*register_y), None)
+            }
+            Opcode::AND((register_x, register_y)) => {
+                (Some(*register_x as u16), Some(*register_y), None)
+            }
+            Opcode::XOR((register_x, register_y)) => {
+                (Some(*register_x as u16), Some(*register_y), None)
+            }
+            Opcode::ADDR((register_x, register_y)) => {
+                (Some(*register_x as u16), Some(*register_y), None)
+            }
+            Opcode::SUB((register_x, register_y)) => {
+                (Some(*register_x as u16), Some(*register_y), None)
+            }
+            Opcode::SHR((register_x, register_y)) => {
+                (Some(*register_x as u16), Some(*register_y), None)
+            }
+            Opcode::RSUB((register_x, register_y)) => {
+                (Some(*register_x as u16), Some(*register_y), None)
+            }
+            Opcode::SHL((register_x, register_y)) => {
+                (Some(*register_x as u16), Some(*register_y), None)
+            }
+            Opcode::SKRNE((register_x, register_y)) => {
+                (Some(*register_x as u16), Some(*register_y), None)
+            }
+            Opcode::LOADI(address) => (Some(*address), None, None),
+            Opcode::LOADIL(address) => (Some(*address), None, None),
+            Opcode::JUMPI(address) => (Some(*address), None, None),
+            Opcode::RAND((register, literal)) => (Some(*register as u16), Some(*literal), None),
+            Opcode::DRAW((register_x, register_y, literal)) => {
+                (Some(*register_x as u16), Some(*register_y), Some(*literal))
+            }
+            Opcode::SKPR(register) => (Some(*register as u16), None, None),
+            Opcode::SKUP(register) => (Some(*register as u16), None, None),
+            Opcode::MOVED(register) => (Some(*register as u16), None, None),
+            Opcode::KEYD(register) => (Some(*register as u16), None, None),
+            Opcode::LOADD(register) => (Some(*register as u16), None, None),
+            Opcode::LOADS(register) => (Some(*register as u16), None, None),
+            Opcode::PLANE(literal) => (Some(*literal as u16), None, None),
+            Opcode::AUDIO => (None, None, None),
+            Opcode::PITCH(register) => (Some(*register as u16), None, None),
+            Opcode::ADDI(register) => (Some(*register as u16), None, None),
+            Opcode::LDSPR(register) => (Some(*register as u16), None, None),
+            Opcode::LDBSPR(register) => (Some(*register as u16), None, None),
+            Opcode::BCD(register) => (Some(*register as u16), None, None),
+            Opcode::STORE(register) => (Some(*register as u16), None, None),
+            Opcode::READ(register) => (Some(*register as u16), None, None),
+            Opcode::STOREF(register) => (Some(*register as u16), None, None),
+            Opcode::READF(register) => (Some(*register as u16), None, None),
+        };
+
+        match i {
+            0 => operands.0,
+            1 => operands.1.map(|o| o as u16),
+            2 => operands.2.map(|o| o as u16),
+            _ => None,
+        }
+    }
+
+    pub fn describe(&self, chip8: &Chip8) -> String {
+        match self {
+            Opcode::SYS(address) => format!(
+                "Call machine code routine (RCA 1802 for COSMAC VIP) at address {address:#05X}."),
+            Opcode::CLR => "Clear the screen".to_owned(),
+            Opcode::RTS => "Return from subroutine (pop the stack)".to_owned(),
+            Opcode::SCD(literal) => format!("Scroll the display down by {literal} pixels"),
+            Opcode::SCU(literal) => format!("Scroll the display up by {literal} pixels"),
+            Opcode::SCR => "Scroll the display right by 4 pixels".to_owned(),
+            Opcode::SCL => "Scroll the display left by 4 pixels".to_owned(),
+            Opcode::EXIT => "Exit the interpreter".to_owned(),
+            Opcode::LOW => "Switch to 64x32 low resolution mode".to_owned(),
+            Opcode::HIGH => "Switch to 128x64 high resolution mode".to_owned(),
+            Opcode::JUMP(address) => format!("Jump to address {address:#05X}"),
+            Opcode::CALL(address) => format!("Calls subroutine at {address:#05X} (push on the stack)"),
+            Opcode::SKE((register, literal)) => format!(
+                "Skips the next instruction if V{register:X} equals {literal:#04X}"),
+            Opcode::SKNE((register, literal)) => format!(
+                "Skips the next instruction if V{register:X} does not equal {literal:#04X}"),
+            Opcode::SKRE((register_x, register_y)) => format!("Skips the next instruction if V{register_x:X} equals V{register_y:X}"),
+            Opcode::STORER((register_x, register_y)) => format!("Stores V{register_x:X} to V{register_y:X} in memory, starting at address I"),
+            Opcode::READR((register_x, register_y)) => format!("Fills V{register_x:X} to V{register_y:X} with values from memory, starting at address I"),
+            Opcode::LOAD((register, literal)) => format!("Sets V{register:X} to {literal:#04X}"),
+            Opcode::ADD((register, literal)) => format!("Adds {literal:#04X} to V{register:X} (carry flag is not changed)"),
+            Opcode::MOVE((register_x, register_y)) => format!("Sets V{register_x:X} to the value of V{register_y}"),
+            Opcode::OR((register_x, register_y)) => format!("Sets V{register_x:X} to V{register_x:X} or V{register_y:X}. (bitwise OR operation"),
+            Opcode::AND((register_x, register_y)) => format!("Sets V{register_x:X} to V{register_x:X} and V{register_y:X}. (bitwise AND operation"),
+            Opcode::XOR((register_x, register_y)) => format!("Sets V{register_x:X} to V{register_x:X} xor V{register_y:X}."),
+            Opcode::ADDR((register_x, register_y)) => format!("Adds V{register_y:X} to V{register_x:X}. VF is set to 1 when there's a carry, and to 0 when there is not."),
+            Opcode::SUB((register_x, register_y)) => format!("V{register_y:X} ({}) is subtracted from V{register_x:X} ({}). VF is set to 0 when there's a borrow, and 1 when there is not.", chip8.v[*register_y as usize], chip8.v[*register_x as usize]),
+            Opcode::SHR((register_x, register_y)) => {
+                let source = if chip8.quirks.shift_uses_vy { register_y } else { register_x };
+                format!("Stores the least significant bit of V{source:X} in VF and then stores V{source:X} shifted to the right by 1 in V{register_x:X}.")
+            }
+            Opcode::RSUB((register_x, register_y)) => format!("Sets V{register_x:X} to V{register_y:X} minus V{register_x:X}. VF is set to 0 when there's a borrow, and 1 when there is not."),
+            Opcode::SHL((register_x, register_y)) => {
+                let source = if chip8.quirks.shift_uses_vy { register_y } else { register_x };
+                format!("Stores the most significant bit of V{source:X} in VF and then stores V{source:X} shifted to the left by 1 in V{register_x:X}")
+            }
+            Opcode::SKRNE((register_x, register_y)) => format!("Skips the next instruction if V{register_x:X} does not equal V{register_y:X}"),
+            Opcode::LOADI(address) => format!("Sets I to the address {address:#05X}"),
+            Opcode::LOADIL(address) => format!("Sets I to the address {address:#06X}"),
+            Opcode::JUMPI(address) => format!("Jumps to the address {address:#05X} plus V{:X}", if chip8.quirks.jump_uses_vx { address >> 8 } else { 0 }),
+            Opcode::RAND((register, literal)) => format!("Sets V{register:X} to the result of a bitwise and operation on a random number (Typically: 0 to 255) and {literal:#04X}"),
+            Opcode::DRAW((register_x, register_y, 0)) => format!("Draws a 16x16 sprite at coordinate (V{register_x:X} ({}), V{register_y:X} ({})).", chip8.v[*register_x as usize], chip8.v[*register_y as usize]),
+            Opcode::DRAW((register_x, register_y, literal)) => format!("Draws an 8x{literal} sprite at coordinate (V{register_x:X} ({}), V{register_y:X} ({})).", chip8.v[*register_x as usize], chip8.v[*register_y as usize]),
+            Opcode::SKPR(register) => format!("Skips the next instruction if the key stored in V{register:X} is pressed (usually the next instruction is a jump to skip a code block)"),
+            Opcode::SKUP(register) => format!("Skips the next instruction if the key stored in V{register:X} is not pressed"),
+            Opcode::MOVED(register) => format!("Sets V{register:X} to the value of the delay timer"),
+            Opcode::KEYD(register) => format!("A key press is awaited, and then stored in V{register:X} (blocking operation, all instruction halted until next key event)"),
+            Opcode::LOADD(register) => format!("Sets the delay timer to V{register:X}"),
+            Opcode::LOADS(register) => format!("Sets the sound timer to V{register:X}"),
+            Opcode::PLANE(literal) => format!("Selects bitplanes {literal:#04b} for drawing, clearing and scrolling"),
+            Opcode::AUDIO => "Loads the 16 byte audio pattern starting at I".to_owned(),
+            Opcode::PITCH(register) => format!("Sets the audio pattern playback pitch to V{register:X} ({})", chip8.v[*register as usize]),
+            Opcode::ADDI(register) => format!("Adds V{register:X} to I. VF is not affected."),
+            Opcode::LDSPR(register) => format!("Sets I to the location of the sprite for the character in V{register:X}. Characters 0-F (in hexadecimal) are represented by a 4x5 font."),
+            Opcode::LDBSPR(register) => format!("Sets I to the location of the 8x10 sprite for the digit in V{register:X}."),
+            Opcode::BCD(register) => format!("Stores the binary-coded decimal representation of V{register:X}, with the hundreds digit in memory at location in I, the tens digit at location I+1, and the ones digit at location I+2."),
+            Opcode::STORE(register) => format!("Stores from V0 to V{register:X} (including V{register:X}) in memory, starting at address I"),
+            Opcode::READ(register) => format!("Fills from V0 to V{register:X} (including V{register:X}) with values from memory, starting at address I"),
+            Opcode::STOREF(register) => format!("Stores V0 to V{register:X} (including V{register:X}) in the RPL user flags"),
+            Opcode::READF(register) => format!("Fills V0 to V{register:X} (including V{register:X}) from the RPL user flags"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_decodable_word_encodes_back_to_itself() {
+        for word in 0..=u16::MAX {
+            if let Ok(opcode) = Opcode::decode(word) {
+                assert_eq!(opcode.encode(), word.to_be_bytes(), "{word:#06X}");
+                assert_eq!(opcode.size(), 2);
+            }
+        }
+    }
+
+    #[test]
+    fn long_load_round_trips() {
+        for address in [0x0000, 0x0200, 0x1234, 0xFFFF] {
+            let bytes = Opcode::LOADIL(address).encode();
+            assert_eq!(bytes.len(), 4);
+            let decoded = Opcode::decode_at(&bytes, 0).unwrap();
+            assert!(matches!(decoded, Opcode::LOADIL(a) if a == address));
+        }
+    }
+}
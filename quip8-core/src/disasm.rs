@@ -0,0 +1,451 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::decode::{Address, Opcode};
+use crate::memory::{FIRST_INSTRUCTION_ADDRESS, XO_CHIP_MEMORY_SIZE};
+
+/// Most data bytes shown on one listing line.
+const DATA_BYTES_PER_LINE: usize = 4;
+
+/// Assembly dialect for listings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Syntax {
+    /// The mnemonics used throughout quip-8 (`LOAD V0, 0x05`).
+    #[default]
+    Quip8,
+    /// Octo's assembly language (`v0 := 0x05`).
+    Octo,
+}
+
+#[derive(Debug)]
+pub struct UnknownSyntax(pub String);
+
+impl fmt::Display for UnknownSyntax {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown syntax '{}' (expected quip8 or octo)", self.0)
+    }
+}
+
+impl std::error::Error for UnknownSyntax {}
+
+impl FromStr for Syntax {
+    type Err = UnknownSyntax;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace(['-', '_'], "").as_str() {
+            "quip8" | "native" => Ok(Syntax::Quip8),
+            "octo" => Ok(Syntax::Octo),
+            _ => Err(UnknownSyntax(s.to_owned())),
+        }
+    }
+}
+
+/// Name given to a jump, call or `LOADI` target in listings.
+pub fn label_name(address: Address) -> String {
+    if address == FIRST_INSTRUCTION_ADDRESS {
+        "main".to_owned()
+    } else {
+        format!("label_{address:03X}")
+    }
+}
+
+/// Formats `opcode` in `syntax`. Addresses are written with `address`, which
+/// can substitute a label name.
+pub fn format_opcode(
+    opcode: &Opcode,
+    syntax: Syntax,
+    address: &dyn Fn(Address) -> String,
+) -> String {
+    match syntax {
+        Syntax::Quip8 => format_quip8(opcode, address),
+        Syntax::Octo => format_octo(opcode, address),
+    }
+}
+
+fn format_quip8(opcode: &Opcode, address: &dyn Fn(Address) -> String) -> String {
+    let mnemonic = opcode.mnemonic();
+    match opcode {
+        Opcode::SYS(a)
+        | Opcode::JUMP(a)
+        | Opcode::CALL(a)
+        | Opcode::LOADI(a)
+        | Opcode::LOADIL(a)
+        | Opcode::JUMPI(a) => format!("{mnemonic} {}", address(*a)),
+        Opcode::SCD(n) | Opcode::SCU(n) | Opcode::PLANE(n) => format!("{mnemonic} {n}"),
+        Opcode::SKE((x, nn))
+        | Opcode::SKNE((x, nn))
+        | Opcode::LOAD((x, nn))
+        | Opcode::ADD((x, nn))
+        | Opcode::RAND((x, nn)) => format!("{mnemonic} V{x:X}, {nn:#04X}"),
+        Opcode::SKRE((x, y))
+        | Opcode::STORER((x, y))
+        | Opcode::READR((x, y))
+        | Opcode::MOVE((x, y))
+        | Opcode::OR((x, y))
+        | Opcode::AND((x, y))
+        | Opcode::XOR((x, y))
+        | Opcode::ADDR((x, y))
+        | Opcode::SUB((x, y))
+        | Opcode::SHR((x, y))
+        | Opcode::RSUB((x, y))
+        | Opcode::SHL((x, y))
+        | Opcode::SKRNE((x, y)) => format!("{mnemonic} V{x:X}, V{y:X}"),
+        Opcode::DRAW((x, y, n)) => format!("{mnemonic} V{x:X}, V{y:X}, {n}"),
+        Opcode::SKPR(x)
+        | Opcode::SKUP(x)
+        | Opcode::MOVED(x)
+        | Opcode::KEYD(x)
+        | Opcode::LOADD(x)
+        | Opcode::LOADS(x)
+        | Opcode::PITCH(x)
+        | Opcode::ADDI(x)
+        | Opcode::LDSPR(x)
+        | Opcode::LDBSPR(x)
+        | Opcode::BCD(x)
+        | Opcode::STORE(x)
+        | Opcode::READ(x)
+        | Opcode::STOREF(x)
+        | Opcode::READF(x) => format!("{mnemonic} V{x:X}"),
+        Opcode::CLR
+        | Opcode::RTS
+        | Opcode::SCR
+        | Opcode::SCL
+        | Opcode::EXIT
+        | Opcode::LOW
+        | Opcode::HIGH
+        | Opcode::AUDIO => mnemonic.to_owned(),
+    }
+}
+
+fn format_octo(opcode: &Opcode, address: &dyn Fn(Address) -> String) -> String {
+    match opcode {
+        // Octo has no machine code calls; emit the raw word.
+        Opcode::SYS(a) => format!("{:#04X} {:#04X}", a >> 8, a & 0xFF),
+        Opcode::CLR => "clear".to_owned(),
+        Opcode::RTS => "return".to_owned(),
+        Opcode::SCD(n) => format!("scroll-down {n}"),
+        Opcode::SCU(n) => format!("scroll-up {n}"),
+        Opcode::SCR => "scroll-right".to_owned(),
+        Opcode::SCL => "scroll-left".to_owned(),
+        Opcode::EXIT => "exit".to_owned(),
+        Opcode::LOW => "lores".to_owned(),
+        Opcode::HIGH => "hires".to_owned(),
+        Opcode::JUMP(a) => format!("jump {}", address(*a)),
+        Opcode::CALL(a) => format!(":call {}", address(*a)),
+        // Octo conditionals name the case in which the next instruction runs,
+        // the opposite of the skip condition.
+        Opcode::SKE((x, nn)) => format!("if v{x:x} != {nn:#04X} then"),
+        Opcode::SKNE((x, nn)) => format!("if v{x:x} == {nn:#04X} then"),
+        Opcode::SKRE((x, y)) => format!("if v{x:x} != v{y:x} then"),
+        Opcode::SKRNE((x, y)) => format!("if v{x:x} == v{y:x} then"),
+        Opcode::SKPR(x) => format!("if v{x:x} -key then"),
+        Opcode::SKUP(x) => format!("if v{x:x} key then"),
+        Opcode::STORER((x, y)) => format!("save v{x:x} - v{y:x}"),
+        Opcode::READR((x, y)) => format!("load v{x:x} - v{y:x}"),
+        Opcode::LOAD((x, nn)) => format!("v{x:x} := {nn:#04X}"),
+        Opcode::ADD((x, nn)) => format!("v{x:x} += {nn:#04X}"),
+        Opcode::MOVE((x, y)) => format!("v{x:x} := v{y:x}"),
+        Opcode::OR((x, y)) => format!("v{x:x} |= v{y:x}"),
+        Opcode::AND((x, y)) => format!("v{x:x} &= v{y:x}"),
+        Opcode::XOR((x, y)) => format!("v{x:x} ^= v{y:x}"),
+        Opcode::ADDR((x, y)) => format!("v{x:x} += v{y:x}"),
+        Opcode::SUB((x, y)) => format!("v{x:x} -= v{y:x}"),
+        Opcode::SHR((x, y)) => format!("v{x:x} >>= v{y:x}"),
+        Opcode::RSUB((x, y)) => format!("v{x:x} =- v{y:x}"),
+        Opcode::SHL((x, y)) => format!("v{x:x} <<= v{y:x}"),
+        Opcode::LOADI(a) => format!("i := {}", address(*a)),
+        Opcode::LOADIL(a) => format!("i := long {}", address(*a)),
+        Opcode::JUMPI(a) => format!("jump0 {}", address(*a)),
+        Opcode::RAND((x, nn)) => format!("v{x:x} := random {nn:#04X}"),
+        Opcode::DRAW((x, y, n)) => format!("sprite v{x:x} v{y:x} {n}"),
+        Opcode::MOVED(x) => format!("v{x:x} := delay"),
+        Opcode::KEYD(x) => format!("v{x:x} := key"),
+        Opcode::LOADD(x) => format!("delay := v{x:x}"),
+        Opcode::LOADS(x) => format!("buzzer := v{x:x}"),
+        Opcode::PLANE(n) => format!("plane {n}"),
+        Opcode::AUDIO => "audio".to_owned(),
+        Opcode::PITCH(x) => format!("pitch := v{x:x}"),
+        Opcode::ADDI(x) => format!("i += v{x:x}"),
+        Opcode::LDSPR(x) => format!("i := hex v{x:x}"),
+        Opcode::LDBSPR(x) => format!("i := bighex v{x:x}"),
+        Opcode::BCD(x) => format!("bcd v{x:x}"),
+        Opcode::STORE(x) => format!("save v{x:x}"),
+        Opcode::READ(x) => format!("load v{x:x}"),
+        Opcode::STOREF(x) => format!("saveflags v{x:x}"),
+        Opcode::READF(x) => format!("loadflags v{x:x}"),
+    }
+}
+
+/// Formats data bytes as a directive in `syntax`.
+fn format_data(bytes: &[u8], syntax: Syntax) -> String {
+    let bytes = bytes.iter().map(|b| format!("{b:#04X}"));
+    match syntax {
+        Syntax::Quip8 => format!("db {}", bytes.collect::<Vec<_>>().join(", ")),
+        Syntax::Octo => bytes.collect::<Vec<_>>().join(" "),
+    }
+}
+
+/// One line of a listing.
+pub enum Item {
+    Instruction(Opcode),
+    /// Bytes not reached as code, or that don't decode.
+    Data(Vec<u8>),
+}
+
+pub struct Line {
+    pub address: Address,
+    pub bytes: Vec<u8>,
+    pub item: Item,
+}
+
+/// A ROM split into code and data. Code is found by following control flow
+/// from `FIRST_INSTRUCTION_ADDRESS`: both outcomes of every skip, jump and call
+/// targets, and the instruction after a call. Computed jumps (`JUMPI`) can't
+/// be followed, so code only reachable through them shows up as data.
+pub struct Disassembly {
+    rom: Vec<u8>,
+    /// Whether an instruction starts at each ROM offset.
+    instruction_starts: Vec<bool>,
+    labels: BTreeSet<Address>,
+}
+
+impl Disassembly {
+    /// Disassembles `rom` by following control flow.
+    pub fn new(rom: &[u8]) -> Self {
+        let mut disassembly = Self::empty(rom);
+        disassembly.trace(FIRST_INSTRUCTION_ADDRESS);
+        disassembly
+    }
+
+    /// Disassembles `rom` treating every word that decodes as an instruction,
+    /// without control flow analysis. Words that don't decode are listed as
+    /// data and decoding carries on after them.
+    pub fn linear(rom: &[u8]) -> Self {
+        let mut disassembly = Self::empty(rom);
+        let mut offset = 0;
+        while offset < disassembly.addressable_len() {
+            let address = FIRST_INSTRUCTION_ADDRESS + offset as Address;
+            match disassembly.decode(address) {
+                Some(opcode) => {
+                    disassembly.mark_instruction(address, &opcode);
+                    offset += opcode.size() as usize;
+                }
+                None => offset += 2,
+            }
+        }
+        disassembly
+    }
+
+    fn empty(rom: &[u8]) -> Self {
+        Self {
+            rom: rom.to_vec(),
+            instruction_starts: vec![false; rom.len()],
+            labels: BTreeSet::new(),
+        }
+    }
+
+    /// How much of the ROM has an address. A ROM that runs past the end of
+    /// the 64 KiB address space is cut off there.
+    fn addressable_len(&self) -> usize {
+        self.rom
+            .len()
+            .min(XO_CHIP_MEMORY_SIZE - FIRST_INSTRUCTION_ADDRESS as usize)
+    }
+
+    fn offset(&self, address: Address) -> Option<usize> {
+        let offset = address.checked_sub(FIRST_INSTRUCTION_ADDRESS)? as usize;
+        (offset < self.rom.len()).then_some(offset)
+    }
+
+    fn end(&self) -> usize {
+        FIRST_INSTRUCTION_ADDRESS as usize + self.rom.len()
+    }
+
+    /// Decodes the instruction at `address` if it lies entirely inside the
+    /// ROM.
+    fn decode(&self, address: Address) -> Option<Opcode> {
+        let offset = self.offset(address)?;
+        let word = |offset: usize| -> Option<u16> {
+            Some((*self.rom.get(offset)? as u16) << 8 | *self.rom.get(offset + 1)? as u16)
+        };
+        match word(offset)? {
+            0xF000 => Some(Opcode::LOADIL(word(offset + 2)?)),
+            raw_opcode => Opcode::decode(raw_opcode).ok(),
+        }
+    }
+
+    fn mark_instruction(&mut self, address: Address, opcode: &Opcode) {
+        self.instruction_starts[(address - FIRST_INSTRUCTION_ADDRESS) as usize] = true;
+        match opcode {
+            Opcode::JUMP(target)
+            | Opcode::CALL(target)
+            | Opcode::LOADI(target)
+            | Opcode::LOADIL(target)
+            | Opcode::JUMPI(target)
+                if (*target as usize) < self.end() && *target >= FIRST_INSTRUCTION_ADDRESS =>
+            {
+                self.labels.insert(*target);
+            }
+            _ => {}
+        }
+    }
+
+    fn trace(&mut self, entry: Address) {
+        self.labels.insert(entry);
+        let mut pending = vec![entry];
+        while let Some(address) = pending.pop() {
+            let Some(offset) = self.offset(address) else {
+                continue;
+            };
+            if self.instruction_starts[offset] {
+                continue;
+            }
+            let Some(opcode) = self.decode(address) else {
+                continue;
+            };
+            self.mark_instruction(address, &opcode);
+            let next = address.wrapping_add(opcode.size());
+            match opcode {
+                Opcode::JUMP(target) => pending.push(target),
+                Opcode::CALL(target) => pending.extend([target, next]),
+                Opcode::RTS | Opcode::EXIT | Opcode::JUMPI(_) => {}
+                Opcode::SKE(_)
+                | Opcode::SKNE(_)
+                | Opcode::SKRE(_)
+                | Opcode::SKRNE(_)
+                | Opcode::SKPR(_)
+                | Opcode::SKUP(_) => {
+                    let skipped = self.decode(next).map_or(2, |opcode| opcode.size());
+                    pending.extend([next, next.wrapping_add(skipped)]);
+                }
+                _ => pending.push(next),
+            }
+        }
+    }
+
+    /// Addresses that are the target of a jump, call or `LOADI`, plus the
+    /// entry point.
+    pub fn labels(&self) -> &BTreeSet<Address> {
+        &self.labels
+    }
+
+    pub fn is_code(&self, address: Address) -> bool {
+        self.offset(address)
+            .is_some_and(|offset| self.instruction_starts[offset])
+    }
+
+    pub fn lines(&self) -> Vec<Line> {
+        let mut lines = Vec::new();
+        let mut offset = 0;
+        while offset < self.addressable_len() {
+            let address = FIRST_INSTRUCTION_ADDRESS + offset as Address;
+            if self.instruction_starts[offset] {
+                let opcode = self.decode(address).unwrap();
+                let size = opcode.size() as usize;
+                lines.push(Line {
+                    address,
+                    bytes: self.rom[offset..offset + size].to_vec(),
+                    item: Item::Instruction(opcode),
+                });
+                offset += size;
+            } else {
+                // Data runs stop at the next instruction or label.
+                let len = (1..DATA_BYTES_PER_LINE)
+                    .take_while(|&n| {
+                        offset + n < self.addressable_len()
+                            && !self.instruction_starts[offset + n]
+                            && !self.labels.contains(&(address + n as Address))
+                    })
+                    .count()
+                    + 1;
+                let bytes = self.rom[offset..offset + len].to_vec();
+                lines.push(Line {
+                    address,
+                    bytes: bytes.clone(),
+                    item: Item::Data(bytes),
+                });
+                offset += len;
+            }
+        }
+        lines
+    }
+
+    /// Writes the listing: a label line before each labelled address, then
+    /// one line per instruction or data run with its address and raw bytes.
+    pub fn write(&self, out: &mut impl Write, syntax: Syntax) -> io::Result<()> {
+        let lines = self.lines();
+        // A target inside another instruction gets no label line, so it's
+        // referred to by number to keep the listing assemblable.
+        let line_starts: BTreeSet<Address> = lines.iter().map(|line| line.address).collect();
+        let address_name = |address: Address| {
+            if self.labels.contains(&address) && line_starts.contains(&address) {
+                label_name(address)
+            } else {
+                format!("{address:#05X}")
+            }
+        };
+        let comment = match syntax {
+            Syntax::Quip8 => ';',
+            Syntax::Octo => '#',
+        };
+        for line in lines {
+            if self.labels.contains(&line.address) {
+                match syntax {
+                    Syntax::Quip8 => writeln!(out, "{}:", label_name(line.address))?,
+                    Syntax::Octo => writeln!(out, ": {}", label_name(line.address))?,
+                }
+            }
+            let text = match &line.item {
+                Item::Instruction(opcode) => format_opcode(opcode, syntax, &address_name),
+                Item::Data(bytes) => format_data(bytes, syntax),
+            };
+            let bytes = line
+                .bytes
+                .iter()
+                .map(|b| format!("{b:02X}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(
+                out,
+                "    {text:<32} {comment} {:04X}: {bytes}",
+                line.address
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_lists_data_between_code_and_carries_on() {
+        // CLR, a word that isn't an instruction, then JUMP 0x200.
+        let disassembly = Disassembly::linear(&[0x00, 0xE0, 0x5A, 0x01, 0x12, 0x00]);
+        let lines = disassembly.lines();
+        assert_eq!(
+            lines.iter().map(|line| line.address).collect::<Vec<_>>(),
+            [0x200, 0x202, 0x204]
+        );
+        assert!(matches!(lines[0].item, Item::Instruction(Opcode::CLR)));
+        assert!(matches!(&lines[1].item, Item::Data(bytes) if bytes == &[0x5A, 0x01]));
+        assert!(matches!(
+            lines[2].item,
+            Item::Instruction(Opcode::JUMP(0x200))
+        ));
+    }
+
+    #[test]
+    fn stops_at_the_end_of_the_address_space() {
+        // Longer than the 0xFE00 bytes from 0x200 to the end of 64 KiB.
+        let rom = [0x00, 0xE0].repeat(0x8000);
+        for disassembly in [Disassembly::new(&rom), Disassembly::linear(&rom)] {
+            let lines = disassembly.lines();
+            assert_eq!(lines.len(), 0x7F00);
+            assert_eq!(lines.last().unwrap().address, 0xFFFE);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+pub const DISPLAY_HEIGHT: usize = 32;
+pub const DISPLAY_WIDTH: usize = 64;
+pub const HIRES_DISPLAY_HEIGHT: usize = 64;
+pub const HIRES_DISPLAY_WIDTH: usize = 128;
+/// XO-CHIP bitplanes. Plain CHIP-8 and SUPER-CHIP only ever draw to the first.
+pub const PLANES: usize = 2;
+const PIXEL_ERASE_FRAME_DELAY: u8 = 2;
+
+type Plane = [u128; HIRES_DISPLAY_HEIGHT];
+
+/// Framebuffer of two bitplanes, 64x32 in lo-res mode or 128x64 in SUPER-CHIP
+/// hi-res mode. Each row is a `u128` with the leftmost pixel in the most
+/// significant bit; in lo-res mode only the top 64 bits of the first 32 rows
+/// are used. A pixel's color is a palette index 0-3 made of its plane bits.
+pub struct Display {
+    pub(crate) gfx: [Plane; PLANES],
+    pub(crate) hires: bool,
+    /// Bitmask of the planes drawing, clearing and scrolling operate on.
+    pub(crate) selected_planes: u8,
+
+    pub(crate) last_gfx: [Plane; PLANES],
+    pub(crate) last_gfx_ttl: u8,
+    pub deflicker: bool,
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Self {
+            gfx: [[0; HIRES_DISPLAY_HEIGHT]; PLANES],
+            hires: false,
+            selected_planes: 1,
+            last_gfx: [[0; HIRES_DISPLAY_HEIGHT]; PLANES],
+            last_gfx_ttl: 0,
+            deflicker: true,
+        }
+    }
+}
+
+impl Display {
+    pub fn width(&self) -> usize {
+        if self.hires {
+            HIRES_DISPLAY_WIDTH
+        } else {
+            DISPLAY_WIDTH
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        if self.hires {
+            HIRES_DISPLAY_HEIGHT
+        } else {
+            DISPLAY_HEIGHT
+        }
+    }
+
+    pub fn hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Switches between 64x32 and 128x64. All planes are cleared on a change
+    /// of resolution.
+    pub fn set_hires(&mut self, hires: bool) {
+        if self.hires != hires {
+            self.hires = hires;
+            self.gfx = [[0; HIRES_DISPLAY_HEIGHT]; PLANES];
+            self.last_gfx_ttl = 0;
+        }
+    }
+
+    pub fn selected_planes(&self) -> u8 {
+        self.selected_planes
+    }
+
+    /// Selects the planes (bit 0 for the first, bit 1 for the second) that
+    /// subsequent drawing, clearing and scrolling apply to.
+    pub fn select_planes(&mut self, planes: u8) {
+        self.selected_planes = planes & 0b11;
+    }
+
+    pub fn selected_plane_count(&self) -> usize {
+        self.selected_planes.count_ones() as usize
+    }
+
+    fn selected(&self) -> impl Iterator<Item = usize> {
+        let selected_planes = self.selected_planes;
+        (0..PLANES).filter(move |plane| selected_planes & (1 << plane) != 0)
+    }
+
+    /// Raw rows of `plane` for the current resolution, without deflickering
+    /// applied.
+    pub fn rows(&self, plane: usize) -> &[u128] {
+        &self.gfx[plane][..self.height()]
+    }
+
+    /// Palette index (0-3) of the pixel at (`x`, `y`). With deflicker enabled,
+    /// pixels erased by a recent `DRAW` keep showing for a couple of frames.
+    pub fn color(&self, x: usize, y: usize) -> u8 {
+        let mut color = 0;
+        for plane in 0..PLANES {
+            let row = self.gfx[plane][y]
+                | if self.last_gfx_ttl > 0 {
+                    self.last_gfx[plane][y]
+                } else {
+                    0
+                };
+            if row & (1 << (127 - x)) != 0 {
+                color |= 1 << plane;
+            }
+        }
+        color
+    }
+
+    /// Whether the pixel at (`x`, `y`) is lit on any plane.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.color(x, y) != 0
+    }
+
+    /// Clears the selected planes.
+    pub fn clear(&mut self) {
+        for plane in self.selected() {
+            self.gfx[plane] = [0; HIRES_DISPLAY_HEIGHT];
+        }
+        self.last_gfx_ttl = 0;
+    }
+
+    /// Mask of the bits in a row that are on screen at the current resolution.
+    fn row_mask(&self) -> u128 {
+        !0 << (128 - self.width())
+    }
+
+    /// XORs an 8 pixel wide sprite onto the screen at (`x`, `y`). The starting
+    /// coordinate always wraps; parts of the sprite crossing the right or
+    /// bottom edge wrap around if `wrap` is set and are clipped otherwise.
+    /// `sprite` holds one equally sized chunk per selected plane, in plane
+    /// order. Returns whether any lit pixel was erased.
+    pub fn draw(&mut self, x: usize, y: usize, sprite: &[u8], wrap: bool) -> bool {
+        self.last_gfx = self.gfx;
+        let chunk_len = sprite.len() / self.selected_plane_count().max(1);
+        let mut collision = false;
+        for (plane, chunk) in self.selected().zip(sprite.chunks(chunk_len.max(1))) {
+            let rows = chunk.iter().map(|row| (*row as u128) << 120);
+            collision |= self.blit(plane, x, y, rows, wrap);
+        }
+        self.update_deflicker(collision);
+        collision
+    }
+
+    /// Like [`draw`](Self::draw) for SUPER-CHIP's 16x16 sprites, stored as two
+    /// bytes per row.
+    pub fn draw_wide(&mut self, x: usize, y: usize, sprite: &[u8], wrap: bool) -> bool {
+        self.last_gfx = self.gfx;
+        let mut collision = false;
+        for (plane, chunk) in self.selected().zip(sprite.chunks(32)) {
+            let rows = chunk.chunks(2).map(|row| {
+                ((row[0] as u128) << 120) | ((*row.get(1).unwrap_or(&0) as u128) << 112)
+            });
+            collision |= self.blit(plane, x, y, rows, wrap);
+        }
+        self.update_deflicker(collision);
+        collision
+    }
+
+    fn update_deflicker(&mut self, collision: bool) {
+        if collision && self.deflicker {
+            self.last_gfx_ttl = PIXEL_ERASE_FRAME_DELAY;
+        } else {
+            self.last_gfx_ttl = 0;
+        }
+    }
+
+    fn blit(
+        &mut self,
+        plane: usize,
+        x: usize,
+        y: usize,
+        rows: impl ExactSizeIterator<Item = u128>,
+        wrap: bool,
+    ) -> bool {
+        let (width, height) = (self.width(), self.height());
+        let x = x % width;
+        let y = y % height;
+        let sprite_height = if wrap {
+            rows.len()
+        } else {
+            rows.len().min(height - y)
+        };
+        let mask = self.row_mask();
+
+        let gfx = &mut self.gfx[plane];
+        let mut bit_unset = 0;
+        for (i, aligned_row) in rows.take(sprite_height).enumerate() {
+            let row = (y + i) % height;
+            let sprite_bits = if wrap && width == 128 {
+                aligned_row.rotate_right(x as u32)
+            } else {
+                let shifted = aligned_row >> x;
+                if wrap {
+                    (shifted & mask) | ((shifted & !mask) << width)
+                } else {
+                    shifted & mask
+                }
+            };
+            let current_row = gfx[row];
+            gfx[row] ^= sprite_bits;
+            bit_unset |= current_row & sprite_bits;
+        }
+        bit_unset != 0
+    }
+
+    /// Scrolls the selected planes down by `n` rows, filling with blank rows.
+    pub fn scroll_down(&mut self, n: usize) {
+        let height = self.height();
+        let n = n.min(height);
+        for plane in self.selected() {
+            self.gfx[plane].copy_within(0..height - n, n);
+            self.gfx[plane][..n].fill(0);
+        }
+    }
+
+    /// Scrolls the selected planes up by `n` rows, filling with blank rows.
+    pub fn scroll_up(&mut self, n: usize) {
+        let height = self.height();
+        let n = n.min(height);
+        for plane in self.selected() {
+            self.gfx[plane].copy_within(n..height, 0);
+            self.gfx[plane][height - n..height].fill(0);
+        }
+    }
+
+    /// Scrolls the selected planes right by `n` pixels, filling with blank
+    /// columns.
+    pub fn scroll_right(&mut self, n: usize) {
+        let (mask, height) = (self.row_mask(), self.height());
+        for plane in self.selected() {
+            for row in &mut self.gfx[plane][..height] {
+                *row = (*row >> n) & mask;
+            }
+        }
+    }
+
+    /// Scrolls the selected planes left by `n` pixels, filling with blank
+    /// columns.
+    pub fn scroll_left(&mut self, n: usize) {
+        let height = self.height();
+        for plane in self.selected() {
+            for row in &mut self.gfx[plane][..height] {
+                *row <<= n;
+            }
+        }
+    }
+
+    pub(crate) fn tick(&mut self) {
+        if self.last_gfx_ttl > 0 {
+            self.last_gfx_ttl -= 1;
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::decode::Address;
+
+/// Something a ROM did that no real interpreter could carry out. Each variant
+/// records `pc`, the address of the faulting instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Chip8Error {
+    /// The word at `pc` isn't an instruction.
+    UnknownOpcode { pc: Address, opcode: u16 },
+    /// `SYS` (0NNN) calls native machine code, which isn't emulated.
+    MachineCodeCall { pc: Address, address: Address },
+    /// `CALL` with every stack entry in use.
+    StackOverflow { pc: Address },
+    /// `RTS` with nothing on the stack.
+    StackUnderflow { pc: Address },
+    /// The instruction would access `len` bytes from `address`, past the end
+    /// of memory.
+    MemoryOutOfBounds {
+        pc: Address,
+        address: usize,
+        len: usize,
+    },
+    /// Execution ran off the end of memory.
+    PcOutOfBounds { pc: Address },
+}
+
+impl Chip8Error {
+    /// Address of the instruction that faulted.
+    pub fn pc(&self) -> Address {
+        match *self {
+            Chip8Error::UnknownOpcode { pc, .. }
+            | Chip8Error::MachineCodeCall { pc, .. }
+            | Chip8Error::StackOverflow { pc }
+            | Chip8Error::StackUnderflow { pc }
+            | Chip8Error::MemoryOutOfBounds { pc, .. }
+            | Chip8Error::PcOutOfBounds { pc } => pc,
+        }
+    }
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Error::UnknownOpcode { pc, opcode } => {
+                write!(f, "Unknown opcode {opcode:04X} at {pc:#05X}")
+            }
+            Chip8Error::MachineCodeCall { pc, address } => write!(
+                f,
+                "SYS {address:#05X} at {pc:#05X} calls machine code, which isn't supported"
+            ),
+            Chip8Error::StackOverflow { pc } => write!(f, "Stack overflow: CALL at {pc:#05X}"),
+            Chip8Error::StackUnderflow { pc } => {
+                write!(f, "Stack underflow: RTS at {pc:#05X} with an empty stack")
+            }
+            Chip8Error::MemoryOutOfBounds { pc, address, len } => write!(
+                f,
+                "Instruction at {pc:#05X} accesses {len} byte(s) at {address:#06X}, past the end of memory"
+            ),
+            Chip8Error::PcOutOfBounds { pc } => {
+                write!(f, "Execution ran off the end of memory at {pc:#05X}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {}
+
+/// What `Chip8::emulate_cycle` does when an instruction faults.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Pause on the faulting instruction, leaving the machine as it was before
+    /// it, and report the error.
+    #[default]
+    Halt,
+    /// Skip the faulting instruction and carry on. A `pc` past the end of
+    /// memory has no instruction to skip, so it wraps around.
+    Ignore,
+    /// Wrap memory addresses, `pc` and the stack pointer around like hardware
+    /// with too few address lines would. Faults with nothing to wrap are
+    /// skipped as under `Ignore`.
+    Wrap,
+}
+
+impl ErrorPolicy {
+    pub const ALL: [ErrorPolicy; 3] = [ErrorPolicy::Halt, ErrorPolicy::Ignore, ErrorPolicy::Wrap];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorPolicy::Halt => "Halt",
+            ErrorPolicy::Ignore => "Ignore",
+            ErrorPolicy::Wrap => "Wrap",
+        }
+    }
+}
+
+impl fmt::Display for ErrorPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug)]
+pub struct UnknownErrorPolicy(pub String);
+
+impl fmt::Display for UnknownErrorPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown error policy '{}' (expected halt, ignore or wrap)",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownErrorPolicy {}
+
+impl FromStr for ErrorPolicy {
+    type Err = UnknownErrorPolicy;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "halt" => Ok(ErrorPolicy::Halt),
+            "ignore" => Ok(ErrorPolicy::Ignore),
+            "wrap" => Ok(ErrorPolicy::Wrap),
+            _ => Err(UnknownErrorPolicy(s.to_owned())),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Framebuffer export as text, PBM and PNG, without an image library.
+
+use std::io::{self, Write};
+
+use crate::display::Display;
+
+pub type Rgb = [u8; 3];
+
+/// Colors by pixel color: unlit, first plane, second plane, both planes.
+pub const DEFAULT_PALETTE: [Rgb; 4] = [[0, 0, 0], [255, 255, 255], [170, 170, 170], [85, 85, 85]];
+
+/// One character per pixel and one line per row: `.` unlit, `#` first plane,
+/// `+` second plane, `*` both.
+pub fn ascii(display: &Display) -> String {
+    let mut text = String::with_capacity((display.width() + 1) * display.height());
+    for y in 0..display.height() {
+        for x in 0..display.width() {
+            text.push(['.', '#', '+', '*'][display.color(x, y) as usize]);
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Block characters with two rows per line, so pixels come out roughly
+/// square in a monospaced font. Lit pixels, on any plane, are filled.
+pub fn blocks(display: &Display) -> String {
+    let lines = display.height().div_ceil(2);
+    let mut text = String::with_capacity((display.width() * 3 + 1) * lines);
+    for y in (0..display.height()).step_by(2) {
+        for x in 0..display.width() {
+            let top = display.pixel(x, y);
+            let bottom = y + 1 < display.height() && display.pixel(x, y + 1);
+            text.push(match (top, bottom) {
+                (false, false) => ' ',
+                (true, false) => '\u{2580}',
+                (false, true) => '\u{2584}',
+                (true, true) => '\u{2588}',
+            });
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Writes a plain (P1) PBM with lit pixels, on any plane, as 1.
+pub fn write_pbm(out: &mut impl Write, display: &Display) -> io::Result<()> {
+    writeln!(out, "P1\n{} {}", display.width(), display.height())?;
+    for y in 0..display.height() {
+        let row: Vec<&str> = (0..display.width())
+            .map(|x| if display.pixel(x, y) { "1" } else { "0" })
+            .collect();
+        writeln!(out, "{}", row.join(" "))?;
+    }
+    Ok(())
+}
+
+/// Writes an RGB PNG of the display in `palette`, each pixel a `scale` by
+/// `scale` square.
+pub fn write_png(
+    out: &mut impl Write,
+    display: &Display,
+    palette: &[Rgb; 4],
+    scale: usize,
+) -> io::Result<()> {
+    let scale = scale.max(1);
+    let (width, height) = (display.width() * scale, display.height() * scale);
+    // Each scanline starts with its filter type, 0 for none.
+    let mut pixels = Vec::with_capacity((width * 3 + 1) * height);
+    for y in 0..height {
+        pixels.push(0);
+        for x in 0..width {
+            let color = display.color(x / scale, y / scale);
+            pixels.extend_from_slice(&palette[color as usize]);
+        }
+    }
+
+    out.write_all(b"\x89PNG\r\n\x1a\n")?;
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits per channel, truecolor, default compression, filtering and no
+    // interlacing.
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    write_chunk(out, b"IHDR", &header)?;
+    write_chunk(out, b"IDAT", &zlib_stored(&pixels))?;
+    write_chunk(out, b"IEND", &[])
+}
+
+fn write_chunk(out: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data));
+    out.write_all(&crc.to_be_bytes())
+}
+
+/// Wraps `data` in a zlib stream of uncompressed deflate blocks. Framebuffers
+/// are small enough that compressing isn't worth the code.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK: usize = 0xFFFF;
+    let mut stream = Vec::with_capacity(data.len() + data.len() / MAX_BLOCK * 5 + 11);
+    // Deflate with a 32 KiB window, no preset dictionary.
+    stream.extend_from_slice(&[0x78, 0x01]);
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        stream.push(last as u8);
+        let len = block.len() as u16;
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&(!len).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+fn crc32<'a>(bytes: impl IntoIterator<Item = &'a u8>) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                crc >> 1 ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_match_known_values() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn exports_lit_pixels() {
+        let mut display = Display::default();
+        display.draw(1, 0, &[0b1000_0000], false);
+        assert!(ascii(&display).starts_with(".#.."));
+        display.draw(2, 1, &[0b1000_0000], false);
+        assert!(blocks(&display).starts_with(" \u{2580}\u{2584} "));
+        assert_eq!(blocks(&display).lines().count(), 16);
+
+        let mut pbm = Vec::new();
+        write_pbm(&mut pbm, &display).unwrap();
+        assert!(String::from_utf8(pbm)
+            .unwrap()
+            .starts_with("P1\n64 32\n0 1 0 0"));
+
+        let mut png = Vec::new();
+        write_png(&mut png, &display, &DEFAULT_PALETTE, 2).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[16..24], &[0, 0, 0, 128, 0, 0, 0, 64]);
+        assert_eq!(&png[png.len() - 8..], b"IEND\xAE\x42\x60\x82");
+    }
+}
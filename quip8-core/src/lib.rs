@@ -0,0 +1,47 @@
+//! Headless CHIP-8 interpreter core.
+//!
+//! Everything needed to load and run a ROM lives here with no GUI
+//! dependencies; frontends drive a [`Chip8`] by setting keys, stepping it and
+//! reading back its [`Display`].
+
+pub mod asm;
+pub mod audio;
+pub mod clock;
+pub mod cpu;
+pub mod debug;
+pub mod decode;
+pub mod disasm;
+pub mod display;
+pub mod error;
+pub mod image;
+pub mod memory;
+pub mod movie;
+pub mod octo;
+pub mod quirks;
+pub mod rewind;
+pub mod rng;
+pub mod state;
+pub mod trace;
+
+pub use asm::{assemble, assemble_file, AsmError};
+pub use audio::{AudioSettings, AudioSink, Beeper, NullSink, Pattern, WavSink, Waveform};
+pub use clock::{Scheduler, Speed, TIMER_HZ};
+pub use cpu::{Chip8, StepEvent};
+pub use debug::{Access, Break, Breakpoint, Debugger, WatchKind, Watchpoint};
+pub use decode::{Address, Literal, Opcode, RegisterAddress, UnknownOpcode};
+pub use disasm::{Disassembly, Syntax};
+pub use display::{
+    Display, DISPLAY_HEIGHT, DISPLAY_WIDTH, HIRES_DISPLAY_HEIGHT, HIRES_DISPLAY_WIDTH, PLANES,
+};
+pub use error::{Chip8Error, ErrorPolicy};
+pub use memory::{
+    BIG_FONT_SET, BIG_FONT_START_ADDRESS, FIRST_INSTRUCTION_ADDRESS, FONT_SET, FONT_START_ADDRESS,
+    MEMORY_SIZE, XO_CHIP_MEMORY_SIZE,
+};
+pub use movie::{rom_hash, Desync, FrameInput, Movie, MovieError, MovieSession};
+pub use octo::OctoProgram;
+pub use quirks::{QuirkProfile, Quirks};
+pub use rewind::RewindBuffer;
+pub use rng::{random_seed, Rng, RngAlgorithm};
+pub use state::{StateError, STATE_VERSION};
+pub use trace::{TraceFormat, Tracer};
@@ -0,0 +1,60 @@
+pub const MEMORY_SIZE: usize = 4096;
+pub const XO_CHIP_MEMORY_SIZE: usize = 0x10000;
+
+pub const FONT_START_ADDRESS: u16 = 0x0;
+pub static FONT_SET: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+// SUPER-CHIP 8x10 digits, addressed by FX30. SUPER-CHIP 1.1 only defined 0-9;
+// A-F follow Octo's extension.
+pub const BIG_FONT_START_ADDRESS: u16 = 0x50;
+pub static BIG_FONT_SET: [u8; 160] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+    0x3C, 0x7E, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFE, 0xC3, 0xC3, 0xFE, 0xFE, 0xC3, 0xC3, 0xFE, 0xFC, // B
+    0x3C, 0x7E, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0x7E, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFC, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFC, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+pub const FIRST_INSTRUCTION_ADDRESS: u16 = 0x200;
+
+/// Builds a fresh memory image with the fonts at `FONT_START_ADDRESS` and
+/// `BIG_FONT_START_ADDRESS` and `rom` at `FIRST_INSTRUCTION_ADDRESS`. ROMs
+/// larger than the available space are truncated.
+pub fn with_rom(rom: &[u8], memory_size: usize) -> Vec<u8> {
+    let mut memory = vec![0; memory_size];
+    let font_start = FONT_START_ADDRESS as usize;
+    memory[font_start..font_start + FONT_SET.len()].copy_from_slice(FONT_SET.as_slice());
+    let big_font_start = BIG_FONT_START_ADDRESS as usize;
+    memory[big_font_start..big_font_start + BIG_FONT_SET.len()]
+        .copy_from_slice(BIG_FONT_SET.as_slice());
+    let rom_start = FIRST_INSTRUCTION_ADDRESS as usize;
+    let rom_len = rom.len().min(memory_size - rom_start);
+    memory[rom_start..rom_start + rom_len].copy_from_slice(&rom[..rom_len]);
+    memory
+}
@@ -0,0 +1,474 @@
+use std::fmt;
+
+use crate::cpu::Chip8;
+use crate::error::ErrorPolicy;
+use crate::memory::{MEMORY_SIZE, XO_CHIP_MEMORY_SIZE};
+use crate::quirks::Quirks;
+use crate::rng::{Rng, RngAlgorithm};
+
+const HEADER: &str = "quip8-movie";
+/// Bumped whenever the format or the emulation it reproduces changes in a
+/// way old movies can't follow.
+pub const MOVIE_VERSION: u32 = 1;
+/// Frames between the state checksums a recording stores.
+pub const CHECKSUM_INTERVAL: u64 = 60;
+/// The longest movie, a day at 60 frames per second. Recording stops
+/// here, and longer movies are rejected rather than filling memory.
+pub const MAX_FRAMES: usize = 24 * 60 * 60 * 60;
+
+/// 64-bit FNV-1a, used for the ROM hash and state checksums.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(0xCBF2_9CE4_8422_2325)
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0100_0000_01B3);
+        }
+        self
+    }
+}
+
+/// Identifies a ROM image so a movie isn't played against the wrong one.
+pub fn rom_hash(rom: &[u8]) -> u64 {
+    Fnv::new().bytes(rom).0
+}
+
+impl Chip8 {
+    /// Hash of everything that decides how the machine runs from here:
+    /// memory, registers, stack, timers, framebuffer and RNG. The deflicker
+    /// ghost only changes what's shown, so it's left out.
+    pub fn checksum(&self) -> u64 {
+        let mut hash = Fnv::new();
+        hash.bytes(&self.memory)
+            .bytes(&self.v)
+            .bytes(&self.i.to_le_bytes())
+            .bytes(&self.pc.to_le_bytes());
+        for address in self.stack {
+            hash.bytes(&address.to_le_bytes());
+        }
+        hash.bytes(&self.sp.to_le_bytes())
+            .bytes(&[self.delay_timer, self.sound_timer])
+            .bytes(&self.rpl)
+            .bytes(&[self.pitch, self.vblank_wait as u8]);
+        match &self.audio_pattern {
+            Some(pattern) => hash.bytes(&[1]).bytes(pattern),
+            None => hash.bytes(&[0]),
+        };
+        let display = &self.display;
+        hash.bytes(&[display.hires as u8, display.selected_planes]);
+        for row in display.gfx.iter().flatten() {
+            hash.bytes(&row.to_le_bytes());
+        }
+        hash.bytes(&self.rng.state().to_le_bytes()).0
+    }
+}
+
+/// Input for one frame: the keypad as the frame started and the key
+/// `KEYD` would see as newly pressed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameInput {
+    pub keys: u16,
+    pub key_pressed: Option<u8>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MovieError {
+    /// The text isn't a movie at all.
+    NotAMovie,
+    /// The movie was written by an incompatible version of the format.
+    UnsupportedVersion(u32),
+    /// A line couldn't be read.
+    Parse { line: usize, message: String },
+    /// The movie was recorded with a different ROM.
+    RomMismatch { expected: u64, actual: u64 },
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MovieError::NotAMovie => f.write_str("not a movie"),
+            MovieError::UnsupportedVersion(version) => write!(
+                f,
+                "movie version {version} is not supported (expected {MOVIE_VERSION})"
+            ),
+            MovieError::Parse { line, message } => write!(f, "movie line {line}: {message}"),
+            MovieError::RomMismatch { expected, actual } => write!(
+                f,
+                "movie was recorded with ROM {expected:016x} but this ROM is {actual:016x}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MovieError {}
+
+/// A recorded run: the machine it started from and the input for every
+/// frame. Playing the input back on the same ROM reproduces the run exactly;
+/// the checksums taken while recording show where it doesn't.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Movie {
+    pub rom_hash: u64,
+    pub quirks: Quirks,
+    pub memory_size: usize,
+    pub instructions_per_second: u32,
+    pub error_policy: ErrorPolicy,
+    pub rng: RngAlgorithm,
+    pub seed: u64,
+    pub frames: Vec<FrameInput>,
+    /// The machine's checksum after every `CHECKSUM_INTERVAL`th frame.
+    pub checksums: Vec<u64>,
+}
+
+/// The quirk names used in movie files, which are the field names.
+fn quirk_flags(quirks: &mut Quirks) -> [(&'static str, &mut bool); 6] {
+    [
+        ("shift_uses_vy", &mut quirks.shift_uses_vy),
+        (
+            "load_store_increments_i",
+            &mut quirks.load_store_increments_i,
+        ),
+        ("jump_uses_vx", &mut quirks.jump_uses_vx),
+        ("logic_resets_vf", &mut quirks.logic_resets_vf),
+        ("wrap_sprites", &mut quirks.wrap_sprites),
+        ("display_wait", &mut quirks.display_wait),
+    ]
+}
+
+impl Movie {
+    /// An empty movie of `rom` run with these settings.
+    pub fn new(
+        rom: &[u8],
+        quirks: Quirks,
+        memory_size: usize,
+        instructions_per_second: u32,
+        error_policy: ErrorPolicy,
+        rng: RngAlgorithm,
+        seed: u64,
+    ) -> Self {
+        Self {
+            rom_hash: rom_hash(rom),
+            quirks,
+            memory_size,
+            instructions_per_second,
+            error_policy,
+            rng,
+            seed,
+            frames: Vec::new(),
+            checksums: Vec::new(),
+        }
+    }
+
+    /// A paused machine with `rom` loaded, as the movie starts.
+    pub fn machine(&self, rom: &[u8]) -> Result<Chip8, MovieError> {
+        let actual = rom_hash(rom);
+        if actual != self.rom_hash {
+            return Err(MovieError::RomMismatch {
+                expected: self.rom_hash,
+                actual,
+            });
+        }
+        let mut chip8 = Chip8::with_memory_size(rom, self.memory_size);
+        chip8.quirks = self.quirks;
+        chip8.error_policy = self.error_policy;
+        chip8.rng = Rng::new(self.rng, self.seed);
+        Ok(chip8)
+    }
+
+    /// Writes the movie as text: a header of settings, then the input as
+    /// runs of identical frames and the checksums.
+    ///
+    /// ```text
+    /// quip8-movie 1
+    /// rom 5d1f0c3a9b7e2468
+    /// ...
+    /// input 120 0000
+    /// input 1 0020 5
+    /// checksum 60 0f3c5a7e9b1d2c48
+    /// ```
+    pub fn to_text(&self) -> String {
+        let mut quirks = self.quirks;
+        let quirk_names: Vec<&str> = quirk_flags(&mut quirks)
+            .into_iter()
+            .filter(|(_, enabled)| **enabled)
+            .map(|(name, _)| name)
+            .collect();
+        let rng = match self.rng {
+            RngAlgorithm::Xorshift => "xorshift",
+            RngAlgorithm::CosmacVip => "vip",
+        };
+        let mut text = format!(
+            "{HEADER} {MOVIE_VERSION}\n\
+             rom {:016x}\n\
+             memory {}\n\
+             quirks {}\n\
+             ips {}\n\
+             on-error {}\n\
+             rng {rng}\n\
+             seed {}\n",
+            self.rom_hash,
+            self.memory_size,
+            quirk_names.join(" "),
+            self.instructions_per_second,
+            self.error_policy.name().to_ascii_lowercase(),
+            self.seed,
+        );
+        let mut frames = self.frames.iter().peekable();
+        while let Some(input) = frames.next() {
+            let mut count = 1;
+            while frames.next_if_eq(&input).is_some() {
+                count += 1;
+            }
+            text.push_str(&format!("input {count} {:04x}", input.keys));
+            if let Some(key) = input.key_pressed {
+                text.push_str(&format!(" {key:x}"));
+            }
+            text.push('\n');
+        }
+        for (index, checksum) in self.checksums.iter().enumerate() {
+            let frame = (index as u64 + 1) * CHECKSUM_INTERVAL;
+            text.push_str(&format!("checksum {frame} {checksum:016x}\n"));
+        }
+        text
+    }
+
+    /// Reads a movie written by [`to_text`](Self::to_text). Blank lines and
+    /// lines starting with `#` are skipped.
+    pub fn parse(text: &str) -> Result<Movie, MovieError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+        let version = match lines.next().and_then(|(_, line)| line.split_once(' ')) {
+            Some((HEADER, version)) => version.parse().map_err(|_| MovieError::NotAMovie)?,
+            _ => return Err(MovieError::NotAMovie),
+        };
+        if version != MOVIE_VERSION {
+            return Err(MovieError::UnsupportedVersion(version));
+        }
+
+        let mut movie = Movie {
+            rom_hash: 0,
+            quirks: Quirks::default(),
+            memory_size: MEMORY_SIZE,
+            instructions_per_second: 0,
+            error_policy: ErrorPolicy::default(),
+            rng: RngAlgorithm::default(),
+            seed: 0,
+            frames: Vec::new(),
+            checksums: Vec::new(),
+        };
+        let mut seen_rom = false;
+        let mut seen_ips = false;
+        for (line, text) in lines {
+            let error = |message: String| MovieError::Parse { line, message };
+            let hex = |word: &str| u64::from_str_radix(word, 16).ok();
+            let (key, value) = text.split_once(' ').unwrap_or((text, ""));
+            let words: Vec<&str> = value.split_whitespace().collect();
+            match key {
+                "rom" => {
+                    movie.rom_hash = hex(value).ok_or_else(|| error("bad ROM hash".into()))?;
+                    seen_rom = true;
+                }
+                "memory" => {
+                    movie.memory_size = value
+                        .parse()
+                        .ok()
+                        .filter(|&size| size == MEMORY_SIZE || size == XO_CHIP_MEMORY_SIZE)
+                        .ok_or_else(|| error(format!("bad memory size '{value}'")))?;
+                }
+                "quirks" => {
+                    let mut quirks = Quirks {
+                        shift_uses_vy: false,
+                        load_store_increments_i: false,
+                        jump_uses_vx: false,
+                        logic_resets_vf: false,
+                        wrap_sprites: false,
+                        display_wait: false,
+                    };
+                    for word in &words {
+                        let (_, enabled) = quirk_flags(&mut quirks)
+                            .into_iter()
+                            .find(|(name, _)| name == word)
+                            .ok_or_else(|| error(format!("unknown quirk '{word}'")))?;
+                        *enabled = true;
+                    }
+                    movie.quirks = quirks;
+                }
+                "ips" => {
+                    movie.instructions_per_second = value
+                        .parse()
+                        .map_err(|_| error(format!("bad instructions per second '{value}'")))?;
+                    seen_ips = true;
+                }
+                "on-error" => {
+                    movie.error_policy = value.parse().map_err(|e| error(format!("{e}")))?;
+                }
+                "rng" => movie.rng = value.parse().map_err(|e| error(format!("{e}")))?,
+                "seed" => {
+                    movie.seed = value
+                        .parse()
+                        .map_err(|_| error(format!("bad seed '{value}'")))?;
+                }
+                "input" => {
+                    let (count, keys, key_pressed) = match words[..] {
+                        [count, keys] => (count, keys, None),
+                        [count, keys, key] => (count, keys, Some(key)),
+                        _ => return Err(error("expected 'input COUNT KEYS [KEY]'".into())),
+                    };
+                    let count: usize = count
+                        .parse()
+                        .map_err(|_| error(format!("bad frame count '{count}'")))?;
+                    let keys = u16::from_str_radix(keys, 16)
+                        .map_err(|_| error(format!("bad keys '{keys}'")))?;
+                    let key_pressed = key_pressed
+                        .map(|key| {
+                            u8::from_str_radix(key, 16)
+                                .ok()
+                                .filter(|&key| key < 16)
+                                .ok_or_else(|| error(format!("bad key '{key}'")))
+                        })
+                        .transpose()?;
+                    if count > MAX_FRAMES - movie.frames.len() {
+                        return Err(error(format!("more than {MAX_FRAMES} frames")));
+                    }
+                    let input = FrameInput { keys, key_pressed };
+                    movie.frames.extend(std::iter::repeat_n(input, count));
+                }
+                "checksum" => {
+                    let [frame, checksum] = words[..] else {
+                        return Err(error("expected 'checksum FRAME HASH'".into()));
+                    };
+                    let expected_frame = (movie.checksums.len() as u64 + 1) * CHECKSUM_INTERVAL;
+                    if frame.parse() != Ok(expected_frame) {
+                        return Err(error(format!(
+                            "expected the checksum for frame {expected_frame}"
+                        )));
+                    }
+                    let checksum = hex(checksum).ok_or_else(|| error("bad checksum".into()))?;
+                    movie.checksums.push(checksum);
+                }
+                _ => return Err(error(format!("unknown setting '{key}'"))),
+            }
+        }
+        if !seen_rom || !seen_ips {
+            return Err(MovieError::Parse {
+                line: 1,
+                message: "the header needs 'rom' and 'ips'".into(),
+            });
+        }
+        Ok(movie)
+    }
+}
+
+/// Where a played-back run first differed from the recording.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Desync {
+    pub frame: u64,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl fmt::Display for Desync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "desynced by frame {}: checksum {:016x}, recorded {:016x}",
+            self.frame, self.actual, self.expected
+        )
+    }
+}
+
+/// A movie being recorded or played back. The frontend calls
+/// [`before_frame`](Self::before_frame) and
+/// [`after_frame`](Self::after_frame) around each emulated frame.
+pub struct MovieSession {
+    pub movie: Movie,
+    playing: bool,
+    /// Frames run since the movie started.
+    frame: u64,
+    desync: Option<Desync>,
+}
+
+impl MovieSession {
+    pub fn record(movie: Movie) -> Self {
+        Self {
+            movie,
+            playing: false,
+            frame: 0,
+            desync: None,
+        }
+    }
+
+    pub fn play(movie: Movie) -> Self {
+        Self {
+            playing: true,
+            ..Self::record(movie)
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Whether playback has used up the recorded input. Later frames keep
+    /// the last input unless the frontend sets its own.
+    pub fn finished(&self) -> bool {
+        self.playing && self.frame >= self.movie.frames.len() as u64
+    }
+
+    /// The first checksum that didn't match during playback.
+    pub fn desync(&self) -> Option<&Desync> {
+        self.desync.as_ref()
+    }
+
+    /// Records the frame's input, or replaces it with the recorded one.
+    pub fn before_frame(&mut self, chip8: &mut Chip8) {
+        let input = FrameInput {
+            keys: chip8.keys,
+            key_pressed: chip8.key_pressed,
+        };
+        if !self.playing {
+            if self.movie.frames.len() < MAX_FRAMES {
+                self.movie.frames.push(input);
+            }
+        } else if let Some(recorded) = self.movie.frames.get(self.frame as usize) {
+            chip8.keys = recorded.keys;
+            chip8.key_pressed = recorded.key_pressed;
+        }
+    }
+
+    /// Takes or checks a checksum once the frame's timers have ticked.
+    pub fn after_frame(&mut self, chip8: &Chip8) {
+        self.frame += 1;
+        if !self.frame.is_multiple_of(CHECKSUM_INTERVAL) {
+            return;
+        }
+        let checksum = chip8.checksum();
+        if !self.playing {
+            if self.frame <= MAX_FRAMES as u64 {
+                self.movie.checksums.push(checksum);
+            }
+            return;
+        }
+        let index = (self.frame / CHECKSUM_INTERVAL - 1) as usize;
+        match self.movie.checksums.get(index) {
+            Some(&expected) if expected != checksum && self.desync.is_none() => {
+                self.desync = Some(Desync {
+                    frame: self.frame,
+                    expected,
+                    actual: checksum,
+                });
+            }
+            _ => {}
+        }
+    }
+}
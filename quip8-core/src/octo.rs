@@ -0,0 +1,1240 @@
+//! Compiler for [Octo](https://github.com/JohnEarnest/Octo) source (`.8o`).
+//!
+//! Supports the language as documented by Octo: labels (`: name`,
+//! `:next`), `:call`, `:alias`, `:const`, `:calc` and inline `{ ... }`
+//! expressions, `:macro`, `:org`, `:byte`, `:pointer`, `:unpack`, `:assert`,
+//! every instruction form, `if ... then`, `if ... begin ... else ... end`,
+//! `loop ... while ... again`, and bare numbers as sprite data. A bare name
+//! calls that subroutine, even if it's defined further down.
+//!
+//! Execution starts at `main`. Unless `main` is the first thing in the
+//! program, 0x200 holds a jump to it. `:stringmode` is not supported;
+//! `:breakpoint`, `:monitor` and `:proto` are accepted and ignored.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::asm::AsmError;
+use crate::decode::Address;
+use crate::memory::{FIRST_INSTRUCTION_ADDRESS, XO_CHIP_MEMORY_SIZE};
+
+/// How many macro expansions a program may perform before assuming a macro
+/// invokes itself forever.
+const MAX_MACRO_EXPANSIONS: usize = 100_000;
+
+/// A compiled program.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OctoProgram {
+    /// Image to load at `FIRST_INSTRUCTION_ADDRESS`.
+    pub rom: Vec<u8>,
+    /// Label names by address. Where several labels share an address the
+    /// first one defined is kept.
+    pub labels: BTreeMap<Address, String>,
+}
+
+/// Compiles Octo `source`.
+pub fn compile(source: &str) -> Result<OctoProgram, AsmError> {
+    Compiler::new(source, "<input>").compile()
+}
+
+/// Compiles the Octo source file at `path`.
+pub fn compile_file(path: &Path) -> Result<OctoProgram, AsmError> {
+    let file = path.display().to_string();
+    let source = std::fs::read_to_string(path).map_err(|e| AsmError {
+        file: file.clone(),
+        line: 1,
+        column: 1,
+        message: e.to_string(),
+    })?;
+    Compiler::new(&source, &file).compile()
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+/// Splits on whitespace, dropping `#` comments. A token starting with `"`
+/// runs to the closing quote.
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            if chars[i].is_whitespace() {
+                i += 1;
+                continue;
+            }
+            if chars[i] == '#' {
+                break;
+            }
+            let start = i;
+            if chars[i] == '"' {
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    i += 1;
+                }
+                i = (i + 1).min(chars.len());
+            } else {
+                while i < chars.len() && !chars[i].is_whitespace() {
+                    i += 1;
+                }
+            }
+            tokens.push(Token {
+                text: chars[start..i].iter().collect(),
+                line: index + 1,
+                column: start + 1,
+            });
+        }
+    }
+    tokens
+}
+
+fn parse_number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()?
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn register_number(text: &str) -> Option<u8> {
+    let digit = text.strip_prefix('v').or_else(|| text.strip_prefix('V'))?;
+    match digit.len() {
+        1 => digit.chars().next()?.to_digit(16).map(|r| r as u8),
+        _ => None,
+    }
+}
+
+/// Names that can't be used for labels, constants or macros.
+fn is_reserved(name: &str) -> bool {
+    register_number(name).is_some()
+        || parse_number(name).is_some()
+        || name.starts_with(':')
+        || matches!(
+            name,
+            "{" | "}"
+                | ";"
+                | ":="
+                | "+="
+                | "-="
+                | "=-"
+                | "|="
+                | "&="
+                | "^="
+                | ">>="
+                | "<<="
+                | "=="
+                | "!="
+                | "<"
+                | ">"
+                | "<="
+                | ">="
+                | "-"
+                | "i"
+                | "if"
+                | "then"
+                | "begin"
+                | "else"
+                | "end"
+                | "loop"
+                | "again"
+                | "while"
+                | "key"
+                | "-key"
+                | "hex"
+                | "bighex"
+                | "long"
+                | "random"
+                | "delay"
+                | "buzzer"
+                | "pitch"
+                | "return"
+                | "clear"
+        )
+}
+
+/// How a forward reference is patched once its label is known.
+#[derive(Clone, Copy, Debug)]
+enum Patch {
+    /// Low 12 bits of the word at the address.
+    Address,
+    /// The 16-bit word at the address.
+    Long,
+    /// The byte at the address.
+    Byte,
+    /// The byte at the address gets `nibble << 4 | target >> 8`.
+    UnpackHigh(u8),
+    /// The byte at the address gets the low byte of a 12-bit target.
+    UnpackLow,
+}
+
+#[derive(Debug)]
+struct Fixup {
+    at: usize,
+    patch: Patch,
+    name: String,
+    token: Token,
+}
+
+/// An operand that may be a label not yet defined.
+#[derive(Clone)]
+enum Value {
+    Known(i64),
+    Forward(String, Token),
+}
+
+/// Right-hand side of a comparison.
+enum Operand {
+    Register(u8),
+    Byte(u8),
+}
+
+/// `vx op operand` as written after `if` or `while`.
+struct Condition {
+    x: u8,
+    op: String,
+    operand: Option<Operand>,
+}
+
+enum Flow {
+    /// `if ... begin`; the jump at `jump_at` goes to the `else` branch.
+    If { jump_at: usize, token: Token },
+    /// `else`; the jump at `jump_at` goes past `end`.
+    Else { jump_at: usize, token: Token },
+    Loop {
+        start: usize,
+        whiles: Vec<usize>,
+        token: Token,
+    },
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<Token>,
+}
+
+struct Compiler {
+    file: String,
+    tokens: Vec<Token>,
+    pos: usize,
+    memory: Vec<u8>,
+    here: usize,
+    end: usize,
+    /// Whether 0x200 still needs deciding between `main` and a jump to it.
+    entry_pending: bool,
+    labels: HashMap<String, usize>,
+    label_table: BTreeMap<Address, String>,
+    constants: HashMap<String, f64>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+    expansions: usize,
+    fixups: Vec<Fixup>,
+    flow: Vec<Flow>,
+}
+
+impl Compiler {
+    fn new(source: &str, file: &str) -> Compiler {
+        Compiler {
+            file: file.to_owned(),
+            tokens: tokenize(source),
+            pos: 0,
+            memory: vec![0; XO_CHIP_MEMORY_SIZE],
+            here: FIRST_INSTRUCTION_ADDRESS as usize,
+            end: FIRST_INSTRUCTION_ADDRESS as usize,
+            entry_pending: true,
+            labels: HashMap::new(),
+            label_table: BTreeMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            expansions: 0,
+            fixups: Vec::new(),
+            flow: Vec::new(),
+        }
+    }
+
+    fn error_at(&self, token: &Token, message: impl Into<String>) -> AsmError {
+        AsmError {
+            file: self.file.clone(),
+            line: token.line,
+            column: token.column,
+            message: message.into(),
+        }
+    }
+
+    /// An error at the end of the source.
+    fn error_at_end(&self, message: impl Into<String>) -> AsmError {
+        let (line, column) = self.tokens.last().map_or((1, 1), |token| {
+            (token.line, token.column + token.text.chars().count())
+        });
+        AsmError {
+            file: self.file.clone(),
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    fn next(&mut self) -> Result<Token, AsmError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| self.error_at_end("unexpected end of file"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|token| token.text.as_str())
+    }
+
+    fn expect(&mut self, text: &str) -> Result<Token, AsmError> {
+        let token = self.next()?;
+        if token.text != text {
+            return Err(self.error_at(&token, format!("expected '{text}', found '{}'", token.text)));
+        }
+        Ok(token)
+    }
+
+    fn compile(mut self) -> Result<OctoProgram, AsmError> {
+        while self.pos < self.tokens.len() {
+            self.statement()?;
+        }
+        if let Some(flow) = self.flow.last() {
+            let (token, what) = match flow {
+                Flow::If { token, .. } => (token, "'begin' without a matching 'end'"),
+                Flow::Else { token, .. } => (token, "'else' without a matching 'end'"),
+                Flow::Loop { token, .. } => (token, "'loop' without a matching 'again'"),
+            };
+            return Err(self.error_at(token, what));
+        }
+        let Some(&main) = self.labels.get("main") else {
+            return Err(self.error_at_end("this program is missing a 'main' label"));
+        };
+        if self.entry_pending {
+            self.resolve_entry(false)?;
+        }
+        let entry = FIRST_INSTRUCTION_ADDRESS as usize;
+        if main != entry {
+            self.memory[entry..entry + 2].copy_from_slice(&(0x1000 | main as u16).to_be_bytes());
+        }
+        for fixup in std::mem::take(&mut self.fixups) {
+            let Some(&target) = self.labels.get(&fixup.name) else {
+                return Err(self.error_at(&fixup.token, format!("undefined name '{}'", fixup.name)));
+            };
+            self.patch(fixup.at, fixup.patch, target as i64, &fixup.token)?;
+        }
+        Ok(OctoProgram {
+            rom: self.memory[entry..self.end].to_vec(),
+            labels: self.label_table,
+        })
+    }
+
+    /// Reserves 0x200 for a jump to `main` unless `main` is about to be
+    /// defined there.
+    fn resolve_entry(&mut self, defining_main: bool) -> Result<(), AsmError> {
+        self.entry_pending = false;
+        if !defining_main && self.here == FIRST_INSTRUCTION_ADDRESS as usize {
+            self.emit(&[0x00, 0x00])?;
+        }
+        Ok(())
+    }
+
+    fn emit(&mut self, bytes: &[u8]) -> Result<(), AsmError> {
+        if self.entry_pending {
+            self.resolve_entry(false)?;
+        }
+        if self.here + bytes.len() > self.memory.len() {
+            return Err(self.error_at_end("program does not fit in 64 KiB"));
+        }
+        self.memory[self.here..self.here + bytes.len()].copy_from_slice(bytes);
+        self.here += bytes.len();
+        self.end = self.end.max(self.here);
+        Ok(())
+    }
+
+    fn emit_word(&mut self, word: u16) -> Result<(), AsmError> {
+        self.emit(&word.to_be_bytes())
+    }
+
+    fn patch(
+        &mut self,
+        at: usize,
+        patch: Patch,
+        target: i64,
+        token: &Token,
+    ) -> Result<(), AsmError> {
+        match patch {
+            Patch::Address => {
+                let target = self.check_range(target, 0xFFF, "address", token)?;
+                self.memory[at] = self.memory[at] & 0xF0 | (target >> 8) as u8;
+                self.memory[at + 1] = target as u8;
+            }
+            Patch::Long => {
+                let target = self.check_range(target, 0xFFFF, "address", token)?;
+                self.memory[at..at + 2].copy_from_slice(&(target as u16).to_be_bytes());
+            }
+            Patch::Byte => {
+                let target = self.check_range(target, 0xFF, "byte", token)?;
+                self.memory[at] = target as u8;
+            }
+            Patch::UnpackHigh(nibble) => {
+                let target = self.check_range(target, 0xFFF, "address", token)?;
+                self.memory[at] = nibble << 4 | (target >> 8) as u8;
+            }
+            Patch::UnpackLow => {
+                let target = self.check_range(target, 0xFFF, "address", token)?;
+                self.memory[at] = target as u8;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_range(
+        &self,
+        value: i64,
+        max: i64,
+        what: &str,
+        token: &Token,
+    ) -> Result<i64, AsmError> {
+        if (0..=max).contains(&value) {
+            Ok(value)
+        } else {
+            Err(self.error_at(
+                token,
+                format!("{what} {value} is out of range (0 to {max:#X})"),
+            ))
+        }
+    }
+
+    /// Emits `word`, with the operand `value` filled in by `patch` now or
+    /// once its label is defined.
+    fn emit_with(
+        &mut self,
+        word: u16,
+        value: Value,
+        patch: Patch,
+        token: &Token,
+    ) -> Result<(), AsmError> {
+        let at = self.here;
+        self.emit_word(word)?;
+        self.fill(at, patch, value, token)
+    }
+
+    /// Patches `at` with `value` now or once its label is defined.
+    fn fill(
+        &mut self,
+        at: usize,
+        patch: Patch,
+        value: Value,
+        token: &Token,
+    ) -> Result<(), AsmError> {
+        match value {
+            Value::Known(value) => self.patch(at, patch, value, token),
+            Value::Forward(name, token) => {
+                self.fixups.push(Fixup {
+                    at,
+                    patch,
+                    name,
+                    token,
+                });
+                Ok(())
+            }
+        }
+    }
+
+    fn define_label(&mut self, name: Token, address: usize) -> Result<(), AsmError> {
+        self.check_new_name(&name)?;
+        self.labels.insert(name.text.clone(), address);
+        self.label_table
+            .entry(address as Address)
+            .or_insert(name.text);
+        Ok(())
+    }
+
+    fn check_new_name(&self, name: &Token) -> Result<(), AsmError> {
+        let text = name.text.as_str();
+        if is_reserved(text) {
+            return Err(self.error_at(name, format!("'{text}' is a reserved name")));
+        }
+        if self.labels.contains_key(text)
+            || self.constants.contains_key(text)
+            || self.macros.contains_key(text)
+        {
+            return Err(self.error_at(name, format!("the name '{text}' has already been defined")));
+        }
+        Ok(())
+    }
+
+    fn register(&mut self) -> Result<u8, AsmError> {
+        let token = self.next()?;
+        self.as_register(&token.text).ok_or_else(|| {
+            self.error_at(
+                &token,
+                format!("expected a register, found '{}'", token.text),
+            )
+        })
+    }
+
+    fn as_register(&self, text: &str) -> Option<u8> {
+        register_number(text).or_else(|| self.aliases.get(text).copied())
+    }
+
+    fn peek_is_register(&self) -> bool {
+        self.peek()
+            .is_some_and(|text| self.as_register(text).is_some())
+    }
+
+    /// A number, constant, label or `{ expression }`, or a name that may
+    /// become a label later.
+    fn value(&mut self) -> Result<(Value, Token), AsmError> {
+        let token = self.next()?;
+        if token.text == "{" {
+            let value = self.calc_block()?;
+            return Ok((Value::Known(value.floor() as i64), token));
+        }
+        if let Some(number) = parse_number(&token.text) {
+            return Ok((Value::Known(number), token));
+        }
+        if let Some(&value) = self.constants.get(&token.text) {
+            return Ok((Value::Known(value.floor() as i64), token));
+        }
+        if let Some(&address) = self.labels.get(&token.text) {
+            return Ok((Value::Known(address as i64), token));
+        }
+        if is_reserved(&token.text) || self.aliases.contains_key(&token.text) {
+            return Err(self.error_at(&token, format!("expected a value, found '{}'", token.text)));
+        }
+        Ok((Value::Forward(token.text.clone(), token.clone()), token))
+    }
+
+    /// A value that must be known now.
+    fn known_value(&mut self) -> Result<(i64, Token), AsmError> {
+        match self.value()? {
+            (Value::Known(value), token) => Ok((value, token)),
+            (Value::Forward(name, token), _) => {
+                Err(self.error_at(&token, format!("undefined name '{name}'")))
+            }
+        }
+    }
+
+    /// A byte operand; negative numbers down to -128 are two's complement.
+    fn byte_value(&mut self) -> Result<u8, AsmError> {
+        let (value, token) = self.known_value()?;
+        if !(-128..=255).contains(&value) {
+            return Err(self.error_at(&token, format!("byte {value} is out of range")));
+        }
+        Ok(value as u8)
+    }
+
+    fn nibble_value(&mut self) -> Result<u8, AsmError> {
+        let (value, token) = self.known_value()?;
+        Ok(self.check_range(value, 0xF, "nibble", &token)? as u8)
+    }
+
+    fn statement(&mut self) -> Result<(), AsmError> {
+        let token = self.next()?;
+        let text = token.text.clone();
+        match text.as_str() {
+            ":" => {
+                let name = self.next()?;
+                if self.entry_pending {
+                    self.resolve_entry(name.text == "main")?;
+                }
+                self.define_label(name, self.here)?;
+            }
+            ":next" => {
+                let name = self.next()?;
+                if self.entry_pending {
+                    self.resolve_entry(false)?;
+                }
+                self.define_label(name, self.here + 1)?;
+                self.statement()?;
+            }
+            ":unpack" => {
+                let nibble = if self.peek() == Some("long") {
+                    self.next()?;
+                    None
+                } else {
+                    Some(self.nibble_value()?)
+                };
+                let (value, token) = self.value()?;
+                match nibble {
+                    Some(nibble) => {
+                        let at = self.here;
+                        self.emit_word(0x6000)?;
+                        self.emit_word(0x6100)?;
+                        self.fill(at + 1, Patch::UnpackHigh(nibble), value.clone(), &token)?;
+                        self.fill(at + 3, Patch::UnpackLow, value, &token)?;
+                    }
+                    None => {
+                        let Value::Known(address) = value else {
+                            return Err(
+                                self.error_at(&token, "':unpack long' needs a defined address")
+                            );
+                        };
+                        let address = self.check_range(address, 0xFFFF, "address", &token)?;
+                        self.emit_word(0x6000 | (address >> 8) as u16)?;
+                        self.emit_word(0x6100 | (address & 0xFF) as u16)?;
+                    }
+                }
+            }
+            ":breakpoint" | ":proto" => {
+                self.next()?;
+            }
+            ":monitor" => {
+                self.next()?;
+                self.next()?;
+            }
+            ":alias" => {
+                let name = self.next()?;
+                if is_reserved(&name.text) {
+                    return Err(self.error_at(&name, format!("'{}' is a reserved name", name.text)));
+                }
+                let register = if self.peek() == Some("{") {
+                    let (value, token) = self.known_value()?;
+                    self.check_range(value, 0xF, "register", &token)? as u8
+                } else {
+                    self.register()?
+                };
+                self.aliases.insert(name.text, register);
+            }
+            ":const" => {
+                let name = self.next()?;
+                self.check_new_name(&name)?;
+                let (value, _) = self.known_value()?;
+                self.constants.insert(name.text, value as f64);
+            }
+            ":calc" => {
+                let name = self.next()?;
+                // Redefining a :calc constant is allowed, as in Octo.
+                if !self.constants.contains_key(&name.text) {
+                    self.check_new_name(&name)?;
+                }
+                self.expect("{")?;
+                let value = self.calc_block()?;
+                self.constants.insert(name.text, value);
+            }
+            ":org" => {
+                let (address, token) = self.known_value()?;
+                let address =
+                    self.check_range(address, XO_CHIP_MEMORY_SIZE as i64 - 1, "address", &token)?;
+                if address < FIRST_INSTRUCTION_ADDRESS as i64 {
+                    return Err(self.error_at(&token, "cannot :org below 0x200"));
+                }
+                if self.entry_pending {
+                    self.resolve_entry(false)?;
+                }
+                self.here = address as usize;
+            }
+            ":byte" => {
+                let (value, token) = self.value()?;
+                self.emit_byte_value(value, &token)?;
+            }
+            ":pointer" => {
+                let (value, token) = self.value()?;
+                self.emit_with(0, value, Patch::Long, &token)?;
+            }
+            ":call" => {
+                let (value, token) = self.value()?;
+                self.emit_with(0x2000, value, Patch::Address, &token)?;
+            }
+            ":macro" => self.define_macro()?,
+            ":assert" => {
+                let message = match self.peek() {
+                    Some(text) if text.starts_with('"') => Some(self.next()?.text),
+                    _ => None,
+                };
+                let open = self.expect("{")?;
+                if self.calc_block()? == 0.0 {
+                    let message = message
+                        .map(|m| m.trim_matches('"').to_owned())
+                        .unwrap_or_else(|| "assertion failed".to_owned());
+                    return Err(self.error_at(&open, message));
+                }
+            }
+            ":stringmode" => {
+                return Err(self.error_at(&token, ":stringmode is not supported"));
+            }
+            ";" | "return" => self.emit_word(0x00EE)?,
+            "clear" => self.emit_word(0x00E0)?,
+            "exit" => self.emit_word(0x00FD)?,
+            "lores" => self.emit_word(0x00FE)?,
+            "hires" => self.emit_word(0x00FF)?,
+            "scroll-left" => self.emit_word(0x00FC)?,
+            "scroll-right" => self.emit_word(0x00FB)?,
+            "scroll-down" => {
+                let n = self.nibble_value()?;
+                self.emit_word(0x00C0 | n as u16)?;
+            }
+            "scroll-up" => {
+                let n = self.nibble_value()?;
+                self.emit_word(0x00D0 | n as u16)?;
+            }
+            "plane" => {
+                let n = self.nibble_value()?;
+                self.emit_word(0xF001 | (n as u16) << 8)?;
+            }
+            "audio" => self.emit_word(0xF002)?,
+            "bcd" | "saveflags" | "loadflags" => {
+                let x = self.register()?;
+                let low = match text.as_str() {
+                    "bcd" => 0x33,
+                    "saveflags" => 0x75,
+                    _ => 0x85,
+                };
+                self.emit_word(0xF000 | (x as u16) << 8 | low)?;
+            }
+            "save" | "load" => {
+                let x = self.register()?;
+                if self.peek() == Some("-") {
+                    self.next()?;
+                    let y = self.register()?;
+                    let low = if text == "save" { 2 } else { 3 };
+                    self.emit_word(0x5000 | (x as u16) << 8 | (y as u16) << 4 | low)?;
+                } else {
+                    let low = if text == "save" { 0x55 } else { 0x65 };
+                    self.emit_word(0xF000 | (x as u16) << 8 | low)?;
+                }
+            }
+            "sprite" => {
+                let x = self.register()?;
+                let y = self.register()?;
+                let n = self.nibble_value()?;
+                self.emit_word(0xD000 | (x as u16) << 8 | (y as u16) << 4 | n as u16)?;
+            }
+            "jump" | "jump0" | "native" => {
+                let (value, token) = self.value()?;
+                let word = match text.as_str() {
+                    "jump" => 0x1000,
+                    "jump0" => 0xB000,
+                    _ => 0x0000,
+                };
+                self.emit_with(word, value, Patch::Address, &token)?;
+            }
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.register()?;
+                let low = match text.as_str() {
+                    "delay" => 0x15,
+                    "buzzer" => 0x18,
+                    _ => 0x3A,
+                };
+                self.emit_word(0xF000 | (x as u16) << 8 | low)?;
+            }
+            "i" => self.i_statement()?,
+            "if" => {
+                let condition = self.condition()?;
+                let keyword = self.next()?;
+                match keyword.text.as_str() {
+                    "then" => {
+                        self.emit_condition(condition, false)?;
+                        self.statement()?;
+                    }
+                    "begin" => {
+                        self.emit_condition(condition, true)?;
+                        let jump_at = self.here;
+                        self.emit_word(0x1000)?;
+                        self.flow.push(Flow::If { jump_at, token });
+                    }
+                    _ => {
+                        return Err(self.error_at(
+                            &keyword,
+                            format!("expected 'then' or 'begin', found '{}'", keyword.text),
+                        ))
+                    }
+                }
+            }
+            "else" => {
+                let Some(Flow::If { jump_at, .. }) = self.flow.pop() else {
+                    return Err(self.error_at(&token, "'else' without a matching 'begin'"));
+                };
+                let else_jump = self.here;
+                self.emit_word(0x1000)?;
+                self.patch(jump_at, Patch::Address, self.here as i64, &token)?;
+                self.flow.push(Flow::Else {
+                    jump_at: else_jump,
+                    token,
+                });
+            }
+            "end" => match self.flow.pop() {
+                Some(Flow::If { jump_at, .. } | Flow::Else { jump_at, .. }) => {
+                    self.patch(jump_at, Patch::Address, self.here as i64, &token)?;
+                }
+                _ => return Err(self.error_at(&token, "'end' without a matching 'begin'")),
+            },
+            "loop" => {
+                if self.entry_pending {
+                    self.resolve_entry(false)?;
+                }
+                self.flow.push(Flow::Loop {
+                    start: self.here,
+                    whiles: Vec::new(),
+                    token,
+                });
+            }
+            "while" => {
+                if !matches!(self.flow.last(), Some(Flow::Loop { .. })) {
+                    return Err(self.error_at(&token, "'while' outside a loop"));
+                }
+                let condition = self.condition()?;
+                self.emit_condition(condition, true)?;
+                let jump_at = self.here;
+                self.emit_word(0x1000)?;
+                if let Some(Flow::Loop { whiles, .. }) = self.flow.last_mut() {
+                    whiles.push(jump_at);
+                }
+            }
+            "again" => {
+                let Some(Flow::Loop { start, whiles, .. }) = self.flow.pop() else {
+                    return Err(self.error_at(&token, "'again' without a matching 'loop'"));
+                };
+                self.emit_with(0x1000, Value::Known(start as i64), Patch::Address, &token)?;
+                for jump_at in whiles {
+                    self.patch(jump_at, Patch::Address, self.here as i64, &token)?;
+                }
+            }
+            "{" => {
+                let value = self.calc_block()?;
+                self.emit_byte_value(Value::Known(value.floor() as i64), &token)?;
+            }
+            _ if self.as_register(&text).is_some() => {
+                let x = self.as_register(&text).unwrap();
+                self.register_statement(x)?;
+            }
+            _ if parse_number(&text).is_some() => {
+                self.emit_byte_value(Value::Known(parse_number(&text).unwrap()), &token)?;
+            }
+            _ if self.macros.contains_key(&text) => self.expand_macro(&token)?,
+            _ if self.constants.contains_key(&text) => {
+                let value = self.constants[&text].floor() as i64;
+                self.emit_byte_value(Value::Known(value), &token)?;
+            }
+            _ if is_reserved(&text) => {
+                return Err(self.error_at(&token, format!("unexpected '{text}'")));
+            }
+            _ => {
+                // A bare name calls the subroutine.
+                let value = match self.labels.get(&text) {
+                    Some(&address) => Value::Known(address as i64),
+                    None => Value::Forward(text, token.clone()),
+                };
+                self.emit_with(0x2000, value, Patch::Address, &token)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn emit_byte_value(&mut self, value: Value, token: &Token) -> Result<(), AsmError> {
+        let at = self.here;
+        self.emit(&[0])?;
+        match value {
+            Value::Known(value @ -128..=-1) => {
+                self.memory[at] = value as u8;
+                Ok(())
+            }
+            value => self.fill(at, Patch::Byte, value, token),
+        }
+    }
+
+    fn condition(&mut self) -> Result<Condition, AsmError> {
+        let x = self.register()?;
+        let op = self.next()?;
+        let operand = match op.text.as_str() {
+            "key" | "-key" => None,
+            "==" | "!=" | "<" | ">" | "<=" | ">=" => Some(if self.peek_is_register() {
+                Operand::Register(self.register()?)
+            } else {
+                Operand::Byte(self.byte_value()?)
+            }),
+            _ => {
+                return Err(
+                    self.error_at(&op, format!("expected a comparison, found '{}'", op.text))
+                )
+            }
+        };
+        Ok(Condition {
+            x,
+            op: op.text,
+            operand,
+        })
+    }
+
+    /// Emits code that skips the next instruction when `condition` is false,
+    /// or when it's true if `negated`.
+    fn emit_condition(&mut self, condition: Condition, negated: bool) -> Result<(), AsmError> {
+        let mut op = condition.op.as_str();
+        if negated {
+            op = match op {
+                "==" => "!=",
+                "!=" => "==",
+                "key" => "-key",
+                "-key" => "key",
+                "<" => ">=",
+                ">" => "<=",
+                ">=" => "<",
+                _ => ">",
+            };
+        }
+        let x = (condition.x as u16) << 8;
+        let (by_register, operand) = match condition.operand {
+            Some(Operand::Register(y)) => (true, (y as u16) << 4),
+            Some(Operand::Byte(nn)) => (false, nn as u16),
+            None => (false, 0),
+        };
+        match op {
+            "key" => self.emit_word(0xE0A1 | x),
+            "-key" => self.emit_word(0xE09E | x),
+            // SKNE/SKRNE skip when not equal, SKE/SKRE when equal.
+            "==" => self.emit_word(if by_register { 0x9000 } else { 0x4000 } | x | operand),
+            "!=" => self.emit_word(if by_register { 0x5000 } else { 0x3000 } | x | operand),
+            _ => {
+                // VF := operand, then subtract so the borrow flag answers the
+                // comparison.
+                self.emit_word(if by_register { 0x8F00 } else { 0x6F00 } | operand)?;
+                let (subtract, skip) = match op {
+                    ">" => (0x5, 0x3F01),
+                    "<" => (0x7, 0x3F01),
+                    ">=" => (0x7, 0x4F01),
+                    _ => (0x5, 0x4F01),
+                };
+                self.emit_word(0x8F00 | x >> 4 | subtract)?;
+                self.emit_word(skip)
+            }
+        }
+    }
+
+    fn i_statement(&mut self) -> Result<(), AsmError> {
+        let op = self.next()?;
+        match op.text.as_str() {
+            ":=" => match self.peek() {
+                Some("hex" | "bighex") => {
+                    let big = self.next()?.text == "bighex";
+                    let x = self.register()? as u16;
+                    self.emit_word(0xF000 | x << 8 | if big { 0x30 } else { 0x29 })?;
+                }
+                Some("long") => {
+                    self.next()?;
+                    let (value, token) = self.value()?;
+                    self.emit_word(0xF000)?;
+                    self.emit_with(0, value, Patch::Long, &token)?;
+                }
+                _ => {
+                    let (value, token) = self.value()?;
+                    self.emit_with(0xA000, value, Patch::Address, &token)?;
+                }
+            },
+            "+=" => {
+                let x = self.register()? as u16;
+                self.emit_word(0xF01E | x << 8)?;
+            }
+            _ => {
+                return Err(self.error_at(
+                    &op,
+                    format!("expected ':=' or '+=' after i, found '{}'", op.text),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn register_statement(&mut self, x: u8) -> Result<(), AsmError> {
+        let x = x as u16;
+        let op = self.next()?;
+        let register_op = |low: u16| -> Option<u16> { Some(0x8000 | x << 8 | low) };
+        let alu = match op.text.as_str() {
+            ":=" => register_op(0x0),
+            "|=" => register_op(0x1),
+            "&=" => register_op(0x2),
+            "^=" => register_op(0x3),
+            "+=" => register_op(0x4),
+            "-=" => register_op(0x5),
+            ">>=" => register_op(0x6),
+            "=-" => register_op(0x7),
+            "<<=" => register_op(0xE),
+            _ => None,
+        };
+        let Some(alu) = alu else {
+            return Err(self.error_at(&op, format!("expected an assignment, found '{}'", op.text)));
+        };
+        if self.peek_is_register() {
+            let y = self.register()? as u16;
+            return self.emit_word(alu | y << 4);
+        }
+        match (op.text.as_str(), self.peek()) {
+            (":=", Some("key")) => {
+                self.next()?;
+                self.emit_word(0xF00A | x << 8)
+            }
+            (":=", Some("delay")) => {
+                self.next()?;
+                self.emit_word(0xF007 | x << 8)
+            }
+            (":=", Some("random")) => {
+                self.next()?;
+                let nn = self.byte_value()? as u16;
+                self.emit_word(0xC000 | x << 8 | nn)
+            }
+            (":=", _) => {
+                let nn = self.byte_value()? as u16;
+                self.emit_word(0x6000 | x << 8 | nn)
+            }
+            ("+=", _) => {
+                let nn = self.byte_value()? as u16;
+                self.emit_word(0x7000 | x << 8 | nn)
+            }
+            ("-=", _) => {
+                let nn = self.byte_value()?;
+                self.emit_word(0x7000 | x << 8 | nn.wrapping_neg() as u16)
+            }
+            _ => Err(self.error_at(&op, format!("'{}' needs a register operand", op.text))),
+        }
+    }
+
+    fn define_macro(&mut self) -> Result<(), AsmError> {
+        let name = self.next()?;
+        self.check_new_name(&name)?;
+        let mut params = Vec::new();
+        loop {
+            let token = self.next()?;
+            if token.text == "{" {
+                break;
+            }
+            params.push(token.text);
+        }
+        let mut body = Vec::new();
+        let mut depth = 1;
+        loop {
+            let token = self.next()?;
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+            body.push(token);
+        }
+        self.macros.insert(name.text, Macro { params, body });
+        Ok(())
+    }
+
+    fn expand_macro(&mut self, name: &Token) -> Result<(), AsmError> {
+        self.expansions += 1;
+        if self.expansions > MAX_MACRO_EXPANSIONS {
+            return Err(self.error_at(
+                name,
+                "too many macro expansions; does a macro invoke itself?",
+            ));
+        }
+        let params = self.macros[&name.text].params.clone();
+        let mut arguments = HashMap::new();
+        for param in params {
+            let argument = self.next()?;
+            arguments.insert(param, argument.text);
+        }
+        let body: Vec<Token> = self.macros[&name.text]
+            .body
+            .iter()
+            .map(|token| Token {
+                text: arguments
+                    .get(&token.text)
+                    .cloned()
+                    .unwrap_or_else(|| token.text.clone()),
+                ..token.clone()
+            })
+            .collect();
+        self.tokens.splice(self.pos..self.pos, body);
+        Ok(())
+    }
+
+    /// Evaluates an expression up to the closing `}`. Octo evaluates right to
+    /// left with no precedence; parenthesize to group.
+    fn calc_block(&mut self) -> Result<f64, AsmError> {
+        let value = self.calc_expression()?;
+        self.expect("}")?;
+        Ok(value)
+    }
+
+    fn calc_expression(&mut self) -> Result<f64, AsmError> {
+        let lhs = self.calc_term()?;
+        if matches!(self.peek(), Some("}" | ")") | None) {
+            return Ok(lhs);
+        }
+        let op = self.next()?;
+        let rhs = self.calc_expression()?;
+        let (a, b) = (lhs as i64, rhs as i64);
+        let bool = |b: bool| b as i64 as f64;
+        Ok(match op.text.as_str() {
+            "+" => lhs + rhs,
+            "-" => lhs - rhs,
+            "*" => lhs * rhs,
+            "/" => lhs / rhs,
+            "%" => lhs % rhs,
+            "pow" => lhs.powf(rhs),
+            "min" => lhs.min(rhs),
+            "max" => lhs.max(rhs),
+            "&" => (a & b) as f64,
+            "|" => (a | b) as f64,
+            "^" => (a ^ b) as f64,
+            "<<" => a.checked_shl(b as u32).unwrap_or(0) as f64,
+            ">>" => a.checked_shr(b as u32).unwrap_or(0) as f64,
+            "<" => bool(lhs < rhs),
+            ">" => bool(lhs > rhs),
+            "<=" => bool(lhs <= rhs),
+            ">=" => bool(lhs >= rhs),
+            "==" => bool(lhs == rhs),
+            "!=" => bool(lhs != rhs),
+            _ => return Err(self.error_at(&op, format!("unknown operator '{}'", op.text))),
+        })
+    }
+
+    fn calc_term(&mut self) -> Result<f64, AsmError> {
+        let token = self.next()?;
+        let function: fn(f64) -> f64 = match token.text.as_str() {
+            "(" => {
+                let value = self.calc_expression()?;
+                self.expect(")")?;
+                return Ok(value);
+            }
+            "-" => |x| -x,
+            "~" => |x| !(x as i64) as f64,
+            "!" => |x| (x == 0.0) as i64 as f64,
+            "sin" => f64::sin,
+            "cos" => f64::cos,
+            "tan" => f64::tan,
+            "exp" => f64::exp,
+            "log" => f64::ln,
+            "abs" => f64::abs,
+            "sqrt" => f64::sqrt,
+            "sign" => f64::signum,
+            "ceil" => f64::ceil,
+            "floor" => f64::floor,
+            "@" => {
+                let address = self.calc_term()?;
+                return Ok(self.memory.get(address as usize).copied().unwrap_or(0) as f64);
+            }
+            "HERE" => return Ok(self.here as f64),
+            "PI" => return Ok(std::f64::consts::PI),
+            "E" => return Ok(std::f64::consts::E),
+            text => {
+                if let Some(number) = parse_number(text) {
+                    return Ok(number as f64);
+                }
+                if let Some(&value) = self.constants.get(text) {
+                    return Ok(value);
+                }
+                if let Some(&address) = self.labels.get(text) {
+                    return Ok(address as f64);
+                }
+                return Err(self.error_at(&token, format!("undefined name '{text}' in expression")));
+            }
+        };
+        Ok(function(self.calc_term()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom(source: &str) -> Vec<u8> {
+        compile(source).unwrap_or_else(|e| panic!("{e}")).rom
+    }
+
+    #[test]
+    fn main_first_needs_no_entry_jump() {
+        assert_eq!(
+            rom(": main clear v0 := 5 ;"),
+            [0x00, 0xE0, 0x60, 0x05, 0x00, 0xEE]
+        );
+    }
+
+    #[test]
+    fn entry_jump_skips_leading_data() {
+        assert_eq!(
+            rom(": dot 0x80 : main i := dot sprite v0 v1 1 draw-more : draw-more return"),
+            [0x12, 0x03, 0x80, 0xA2, 0x02, 0xD0, 0x11, 0x22, 0x09, 0x00, 0xEE]
+        );
+    }
+
+    #[test]
+    fn control_flow() {
+        let source = "
+            :alias counter v2
+            :const LIMIT 3
+            : main
+                loop
+                    counter += 1
+                    if counter == LIMIT then return
+                    while counter != 9
+                    if counter > v3 begin v0 := 1 else v0 := 2 end
+                again
+        ";
+        let expected = [
+            &[0x72, 0x01][..],                     // 200: counter += 1
+            &[0x42, 0x03, 0x00, 0xEE],             // 202: if counter == LIMIT then return
+            &[0x42, 0x09, 0x12, 0x1A],             // 206: while counter != 9
+            &[0x8F, 0x30, 0x8F, 0x25, 0x4F, 0x01], // 20A: if counter > v3 begin
+            &[0x12, 0x16, 0x60, 0x01],             // 210: v0 := 1
+            &[0x12, 0x18, 0x60, 0x02],             // 214: else v0 := 2 end
+            &[0x12, 0x00],                         // 218: again
+        ]
+        .concat();
+        assert_eq!(rom(source), expected);
+    }
+
+    #[test]
+    fn macros_calc_and_labels() {
+        let source = "
+            :macro twice op { op op }
+            :calc HALF { 0x10 / 2 }
+            : main
+                twice clear
+                v1 := { HALF + 1 }
+                i := long data
+                :unpack 0xA data
+            : data
+                :byte { HALF * 2 }
+        ";
+        let program = compile(source).unwrap();
+        assert_eq!(
+            program.rom,
+            [
+                0x00, 0xE0, 0x00, 0xE0, 0x61, 0x09, 0xF0, 0x00, 0x02, 0x0E, 0x60, 0xA2, 0x61, 0x0E,
+                0x10
+            ]
+        );
+        assert_eq!(
+            program.labels,
+            BTreeMap::from([(0x200, "main".to_owned()), (0x20E, "data".to_owned())])
+        );
+    }
+
+    #[test]
+    fn errors_point_at_the_token() {
+        let error = compile(": main\n  v0 := 300\n").unwrap_err();
+        assert_eq!(error.to_string(), "<input>:2:9: byte 300 is out of range");
+        let error = compile(": main\n  jump nowhere\n").unwrap_err();
+        assert_eq!(error.to_string(), "<input>:2:8: undefined name 'nowhere'");
+        let error = compile("clear").unwrap_err();
+        assert_eq!(error.message, "this program is missing a 'main' label");
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Behaviors that differ between CHIP-8 interpreters. ROMs written for one
+/// platform often misbehave under another's semantics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    /// `SHR`/`SHL` shift VY into VX rather than shifting VX in place.
+    pub shift_uses_vy: bool,
+    /// `STORE`/`READ` leave I pointing past the last register transferred.
+    pub load_store_increments_i: bool,
+    /// `JUMPI` (BNNN) adds VX, where X is the high nibble of NNN, instead of V0.
+    pub jump_uses_vx: bool,
+    /// `OR`/`AND`/`XOR` set VF to 0.
+    pub logic_resets_vf: bool,
+    /// Sprites crossing a screen edge wrap around instead of being clipped.
+    pub wrap_sprites: bool,
+    /// `DRAW` waits for the next 60 Hz vertical blank before execution
+    /// continues, limiting sprite draws to one per frame.
+    pub display_wait: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        QuirkProfile::default().quirks()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QuirkProfile {
+    #[default]
+    CosmacVip,
+    Chip48,
+    SuperChip,
+    XoChip,
+}
+
+impl QuirkProfile {
+    pub const ALL: [QuirkProfile; 4] = [
+        QuirkProfile::CosmacVip,
+        QuirkProfile::Chip48,
+        QuirkProfile::SuperChip,
+        QuirkProfile::XoChip,
+    ];
+
+    pub fn quirks(self) -> Quirks {
+        match self {
+            QuirkProfile::CosmacVip => Quirks {
+                shift_uses_vy: true,
+                load_store_increments_i: true,
+                jump_uses_vx: false,
+                logic_resets_vf: true,
+                wrap_sprites: false,
+                display_wait: true,
+            },
+            QuirkProfile::Chip48 => Quirks {
+                shift_uses_vy: false,
+                load_store_increments_i: true,
+                jump_uses_vx: true,
+                logic_resets_vf: false,
+                wrap_sprites: false,
+                display_wait: false,
+            },
+            QuirkProfile::SuperChip => Quirks {
+                shift_uses_vy: false,
+                load_store_increments_i: false,
+                jump_uses_vx: true,
+                logic_resets_vf: false,
+                wrap_sprites: false,
+                display_wait: false,
+            },
+            QuirkProfile::XoChip => Quirks {
+                shift_uses_vy: true,
+                load_store_increments_i: true,
+                jump_uses_vx: false,
+                logic_resets_vf: false,
+                wrap_sprites: true,
+                display_wait: false,
+            },
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            QuirkProfile::CosmacVip => "COSMAC VIP",
+            QuirkProfile::Chip48 => "CHIP-48",
+            QuirkProfile::SuperChip => "SUPER-CHIP",
+            QuirkProfile::XoChip => "XO-CHIP",
+        }
+    }
+
+    /// The preset whose settings match `quirks` exactly, if any.
+    pub fn matching(quirks: &Quirks) -> Option<QuirkProfile> {
+        QuirkProfile::ALL
+            .into_iter()
+            .find(|profile| profile.quirks() == *quirks)
+    }
+}
+
+impl fmt::Display for QuirkProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug)]
+pub struct UnknownQuirkProfile(pub String);
+
+impl fmt::Display for UnknownQuirkProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown quirk profile '{}' (expected vip, chip48, schip or xochip)",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownQuirkProfile {}
+
+impl FromStr for QuirkProfile {
+    type Err = UnknownQuirkProfile;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace(['-', '_', ' '], "").as_str() {
+            "vip" | "cosmacvip" | "chip8" => Ok(QuirkProfile::CosmacVip),
+            "chip48" => Ok(QuirkProfile::Chip48),
+            "schip" | "superchip" => Ok(QuirkProfile::SuperChip),
+            "xochip" => Ok(QuirkProfile::XoChip),
+            _ => Err(UnknownQuirkProfile(s.to_owned())),
+        }
+    }
+}
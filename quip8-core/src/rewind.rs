@@ -0,0 +1,300 @@
+use std::collections::VecDeque;
+
+use crate::cpu::Chip8;
+
+pub const DEFAULT_REWIND_BUDGET: usize = 16 * 1024 * 1024;
+pub const DEFAULT_REWIND_INTERVAL: u32 = 2;
+
+/// Appends `value` as a LEB128 varint.
+fn push_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = data[*pos];
+        *pos += 1;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+/// Encodes the XOR of two equally long states as alternating runs: the
+/// length of an unchanged run, the length of a changed run, then the changed
+/// run's XORed bytes. Consecutive save states mostly differ in a handful of
+/// registers and framebuffer rows, so this is usually tiny.
+fn encode_delta(from: &[u8], to: &[u8]) -> Vec<u8> {
+    let mut delta = Vec::new();
+    let mut pos = 0;
+    while pos < from.len() {
+        let unchanged = from[pos..]
+            .iter()
+            .zip(&to[pos..])
+            .take_while(|(a, b)| a == b)
+            .count();
+        pos += unchanged;
+        let changed = from[pos..]
+            .iter()
+            .zip(&to[pos..])
+            .take_while(|(a, b)| a != b)
+            .count();
+        push_varint(&mut delta, unchanged);
+        push_varint(&mut delta, changed);
+        delta.extend(
+            from[pos..pos + changed]
+                .iter()
+                .zip(&to[pos..])
+                .map(|(a, b)| a ^ b),
+        );
+        pos += changed;
+    }
+    delta
+}
+
+/// Applies a delta from [`encode_delta`] in place, turning one of its two
+/// states into the other.
+fn apply_delta(state: &mut [u8], delta: &[u8]) {
+    let mut pos = 0;
+    let mut offset = 0;
+    while pos < delta.len() {
+        offset += read_varint(delta, &mut pos);
+        let changed = read_varint(delta, &mut pos);
+        for (byte, xor) in state[offset..offset + changed]
+            .iter_mut()
+            .zip(&delta[pos..pos + changed])
+        {
+            *byte ^= xor;
+        }
+        offset += changed;
+        pos += changed;
+    }
+}
+
+/// Ring buffer of recent machine states for running the emulation
+/// backwards. Only the newest snapshot is kept whole; each older one is
+/// stored as a delta against its successor, and the oldest are dropped once
+/// the total exceeds the memory budget.
+pub struct RewindBuffer {
+    /// Emulated frames between snapshots.
+    pub interval: u32,
+    budget: usize,
+    newest: Option<Vec<u8>>,
+    // Oldest first; `deltas[n]` turns snapshot n + 1 back into snapshot n.
+    deltas: VecDeque<Vec<u8>>,
+    delta_bytes: usize,
+    frames_since_capture: u32,
+    // Frames of rewinding left before the next snapshot is restored.
+    frames_until_rewind: u32,
+}
+
+impl Default for RewindBuffer {
+    fn default() -> Self {
+        Self::new(DEFAULT_REWIND_BUDGET, DEFAULT_REWIND_INTERVAL)
+    }
+}
+
+impl RewindBuffer {
+    pub fn new(budget: usize, interval: u32) -> Self {
+        Self {
+            interval,
+            budget,
+            newest: None,
+            deltas: VecDeque::new(),
+            delta_bytes: 0,
+            frames_since_capture: 0,
+            frames_until_rewind: 0,
+        }
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Changes the memory budget in bytes, dropping the oldest snapshots if
+    /// they no longer fit.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict();
+    }
+
+    /// Bytes currently held.
+    pub fn memory_used(&self) -> usize {
+        self.newest.as_ref().map_or(0, Vec::len) + self.delta_bytes
+    }
+
+    /// Number of snapshots available to rewind to.
+    pub fn len(&self) -> usize {
+        self.newest.as_ref().map_or(0, |_| self.deltas.len() + 1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.newest.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.newest = None;
+        self.deltas.clear();
+        self.delta_bytes = 0;
+        self.frames_since_capture = 0;
+        self.frames_until_rewind = 0;
+    }
+
+    /// Called once per emulated frame; takes a snapshot every `interval`
+    /// frames.
+    pub fn frame(&mut self, chip8: &Chip8) {
+        self.frames_until_rewind = 0;
+        self.frames_since_capture += 1;
+        if self.frames_since_capture >= self.interval.max(1) {
+            self.capture(chip8);
+        }
+    }
+
+    pub fn capture(&mut self, chip8: &Chip8) {
+        self.frames_since_capture = 0;
+        let state = chip8.save_state();
+        match self.newest.take() {
+            // A reset into a different memory size can't be diffed against.
+            Some(previous) if previous.len() == state.len() => {
+                let delta = encode_delta(&state, &previous);
+                self.delta_bytes += delta.len();
+                self.deltas.push_back(delta);
+            }
+            Some(_) => self.clear(),
+            None => {}
+        }
+        self.newest = Some(state);
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.memory_used() > self.budget {
+            match self.deltas.pop_front() {
+                Some(delta) => self.delta_bytes -= delta.len(),
+                None => {
+                    self.newest = None;
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Called once per emulated frame while running backwards; restores a
+    /// snapshot every `interval` frames, so history replays at the speed it
+    /// was recorded.
+    pub fn rewind_frame(&mut self, chip8: &mut Chip8) {
+        if self.frames_until_rewind == 0 {
+            self.rewind(chip8);
+            self.frames_until_rewind = self.interval.max(1);
+        }
+        self.frames_until_rewind -= 1;
+    }
+
+    /// Restores the newest snapshot into `chip8` and discards it, so repeated
+    /// calls step further back. The oldest snapshot is kept once reached.
+    /// Returns false if there was nothing to restore.
+    pub fn rewind(&mut self, chip8: &mut Chip8) -> bool {
+        let Some(newest) = self.newest.as_mut() else {
+            return false;
+        };
+        if chip8.load_state(newest).is_err() {
+            self.clear();
+            return false;
+        }
+        if let Some(delta) = self.deltas.pop_back() {
+            apply_delta(newest, &delta);
+            self.delta_bytes -= delta.len();
+        }
+        self.frames_since_capture = 0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A machine whose saved state differs from others by `n`.
+    fn machine(n: u16) -> Chip8 {
+        let mut chip8 = Chip8::new(&[]);
+        chip8.pc = 0x200 + 2 * n;
+        chip8.memory[0x300 + n as usize] = 0xFF;
+        chip8
+    }
+
+    #[test]
+    fn delta_round_trips() {
+        let from = [1, 2, 3, 4, 5, 6, 7, 8];
+        let to = [9, 2, 3, 0, 0, 6, 7, 1];
+        let delta = encode_delta(&from, &to);
+        let mut state = from;
+        apply_delta(&mut state, &delta);
+        assert_eq!(state, to);
+        apply_delta(&mut state, &delta);
+        assert_eq!(state, from);
+        assert_eq!(encode_delta(&from, &from), [8, 0]);
+    }
+
+    #[test]
+    fn rewinds_newest_first_and_stops_at_the_oldest() {
+        let mut rewind = RewindBuffer::default();
+        let mut chip8 = machine(0);
+        assert!(!rewind.rewind(&mut chip8));
+        for n in 1..=3 {
+            rewind.capture(&machine(n));
+        }
+        assert_eq!(rewind.len(), 3);
+        for n in [3, 2, 1, 1] {
+            assert!(rewind.rewind(&mut chip8));
+            assert_eq!(chip8.pc, machine(n).pc);
+        }
+        assert_eq!(rewind.len(), 1);
+        rewind.clear();
+        assert!(rewind.is_empty());
+        assert!(!rewind.rewind(&mut chip8));
+    }
+
+    #[test]
+    fn drops_the_oldest_over_budget() {
+        let state_size = machine(0).save_state().len();
+        let mut rewind = RewindBuffer::new(state_size + 64, 1);
+        for n in 0..20 {
+            rewind.capture(&machine(n));
+            assert!(rewind.memory_used() <= rewind.budget());
+        }
+        let kept = rewind.len() as u16;
+        assert!(kept < 20);
+        let mut chip8 = machine(0);
+        for _ in 0..20 {
+            rewind.rewind(&mut chip8);
+        }
+        assert_eq!(chip8.pc, machine(20 - kept).pc);
+        rewind.set_budget(0);
+        assert!(rewind.is_empty());
+    }
+
+    #[test]
+    fn rewinds_one_snapshot_per_interval() {
+        let mut rewind = RewindBuffer::new(DEFAULT_REWIND_BUDGET, 2);
+        for n in 1..=3 {
+            rewind.capture(&machine(n));
+        }
+        let mut chip8 = machine(0);
+        let pcs: Vec<u16> = (0..4)
+            .map(|_| {
+                rewind.rewind_frame(&mut chip8);
+                chip8.pc
+            })
+            .collect();
+        let [two, three] = [2, 3].map(|n| machine(n).pc);
+        assert_eq!(pcs, [three, three, two, two]);
+    }
+}
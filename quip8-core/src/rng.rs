@@ -0,0 +1,208 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// The address of the COSMAC VIP interpreter's code page, which its
+/// generator reads as a table of bytes.
+const VIP_TABLE_ADDRESS: usize = 0x100;
+
+/// How [`Rng`] generates bytes for `RAND`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RngAlgorithm {
+    /// xorshift64*, which gives good quality bytes whatever the ROM does.
+    #[default]
+    Xorshift,
+    /// The COSMAC VIP interpreter's generator. It mixes a counter bumped on
+    /// each `RAND` with a byte of the interpreter's own code, which on a VIP
+    /// lives at 0x100-0x1FF, so its sequence depends on what's there. The
+    /// interpreter isn't bundled: the sequence only matches a real VIP's once
+    /// its code is loaded there, and an empty page makes a poor table.
+    CosmacVip,
+}
+
+impl RngAlgorithm {
+    pub const ALL: [RngAlgorithm; 2] = [RngAlgorithm::Xorshift, RngAlgorithm::CosmacVip];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RngAlgorithm::Xorshift => "Xorshift",
+            RngAlgorithm::CosmacVip => "COSMAC VIP",
+        }
+    }
+}
+
+impl fmt::Display for RngAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug)]
+pub struct UnknownRngAlgorithm(pub String);
+
+impl fmt::Display for UnknownRngAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown random number algorithm '{}' (expected xorshift or vip)",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownRngAlgorithm {}
+
+impl FromStr for RngAlgorithm {
+    type Err = UnknownRngAlgorithm;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace(['-', '_', ' '], "").as_str() {
+            "xorshift" => Ok(RngAlgorithm::Xorshift),
+            "vip" | "cosmacvip" => Ok(RngAlgorithm::CosmacVip),
+            _ => Err(UnknownRngAlgorithm(s.to_owned())),
+        }
+    }
+}
+
+/// A seed for when the user hasn't picked one.
+pub fn random_seed() -> u64 {
+    rand::random()
+}
+
+/// Random source behind `RAND`. Its whole state is a single `u64`, so it can
+/// be saved and restored with the rest of the machine, and the same seed
+/// always gives the same sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rng {
+    algorithm: RngAlgorithm,
+    state: u64,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(RngAlgorithm::default(), random_seed())
+    }
+}
+
+impl Rng {
+    /// Starts a generator from `seed`. Passing a value previously returned
+    /// by [`state`](Self::state) resumes that generator where it was.
+    pub fn new(algorithm: RngAlgorithm, seed: u64) -> Self {
+        let state = match algorithm {
+            // An all-zero state would make xorshift return zeros forever.
+            RngAlgorithm::Xorshift if seed == 0 => 0x9E37_79B9_7F4A_7C15,
+            RngAlgorithm::Xorshift => seed,
+            // The VIP's counter is the 16-bit register R9.
+            RngAlgorithm::CosmacVip => seed & 0xFFFF,
+        };
+        Self { algorithm, state }
+    }
+
+    pub fn algorithm(&self) -> RngAlgorithm {
+        self.algorithm
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    /// The next byte, before `RAND` masks it. `memory` is only read by
+    /// [`RngAlgorithm::CosmacVip`].
+    pub fn next_u8(&mut self, memory: &[u8]) -> u8 {
+        match self.algorithm {
+            RngAlgorithm::Xorshift => {
+                self.state ^= self.state >> 12;
+                self.state ^= self.state << 25;
+                self.state ^= self.state >> 27;
+                (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+            }
+            RngAlgorithm::CosmacVip => {
+                // The interpreter's CXNN routine: INC R9, then R9's low byte
+                // indexes the interpreter's code page and R9's high byte is
+                // added to the byte found there. That sum, rotated right
+                // through the carry and added to itself, becomes both the
+                // result and R9's new high byte.
+                let counter = (self.state as u16).wrapping_add(1);
+                let [high, low] = counter.to_be_bytes();
+                let table = memory[(VIP_TABLE_ADDRESS + low as usize) % memory.len()];
+                let (sum, carry) = table.overflowing_add(high);
+                let rotated = sum >> 1 | (carry as u8) << 7;
+                let result = rotated.wrapping_add(sum);
+                self.state = u16::from_be_bytes([result, low]) as u64;
+                result
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(rng: &mut Rng, memory: &[u8]) -> Vec<u8> {
+        (0..8).map(|_| rng.next_u8(memory)).collect()
+    }
+
+    /// A machine whose interpreter page holds `7 * n + 3` at offset `n`.
+    fn vip_memory() -> Vec<u8> {
+        let mut memory = vec![0; 4096];
+        for (n, byte) in memory[VIP_TABLE_ADDRESS..0x200].iter_mut().enumerate() {
+            *byte = (n * 7 + 3) as u8;
+        }
+        memory
+    }
+
+    #[test]
+    fn same_seed_gives_same_sequence() {
+        let memory = vec![0; 4096];
+        let first = bytes(&mut Rng::new(RngAlgorithm::Xorshift, 42), &memory);
+        let again = bytes(&mut Rng::new(RngAlgorithm::Xorshift, 42), &memory);
+        let other = bytes(&mut Rng::new(RngAlgorithm::Xorshift, 43), &memory);
+        assert_eq!(again, first);
+        assert_ne!(other, first);
+    }
+
+    #[test]
+    fn resumes_from_state() {
+        let memory = vip_memory();
+        for algorithm in RngAlgorithm::ALL {
+            let mut rng = Rng::new(algorithm, 42);
+            rng.next_u8(&memory);
+            let mut resumed = Rng::new(algorithm, rng.state());
+            assert_eq!(bytes(&mut resumed, &memory), bytes(&mut rng, &memory));
+        }
+    }
+
+    #[test]
+    fn zero_seed_still_varies() {
+        let memory = vec![0; 4096];
+        let sequence = bytes(&mut Rng::new(RngAlgorithm::Xorshift, 0), &memory);
+        assert!(sequence.iter().any(|&byte| byte != sequence[0]));
+    }
+
+    #[test]
+    fn cosmac_vip_sequence() {
+        let memory = vip_memory();
+        let mut rng = Rng::new(RngAlgorithm::CosmacVip, 0);
+        assert_eq!(
+            bytes(&mut rng, &memory),
+            [0x0F, 0x30, 0x6C, 0xD0, 0x71, 0xED, 0xB1, 0x62]
+        );
+        // R9: the last result above, and the counter bumped eight times.
+        assert_eq!(rng.state(), 0x6208);
+
+        let mut rng = Rng::new(RngAlgorithm::CosmacVip, 0xABCD);
+        assert_eq!(
+            bytes(&mut rng, &memory),
+            [0xF8, 0x76, 0xBD, 0x32, 0x6C, 0xCE, 0x6B, 0xE1]
+        );
+        assert_eq!(rng.state(), 0xE1D5);
+    }
+
+    #[test]
+    fn cosmac_vip_seed_is_sixteen_bits() {
+        let memory = vip_memory();
+        let mut wide = Rng::new(RngAlgorithm::CosmacVip, 0x1_0000_ABCD);
+        let mut narrow = Rng::new(RngAlgorithm::CosmacVip, 0xABCD);
+        assert_eq!(bytes(&mut wide, &memory), bytes(&mut narrow, &memory));
+    }
+}
@@ -0,0 +1,320 @@
+use std::fmt;
+
+use crate::cpu::Chip8;
+use crate::display::{HIRES_DISPLAY_HEIGHT, PLANES};
+use crate::memory::{MEMORY_SIZE, XO_CHIP_MEMORY_SIZE};
+use crate::quirks::Quirks;
+use crate::rng::{Rng, RngAlgorithm};
+
+const MAGIC: &[u8; 8] = b"QUIP8STA";
+/// Bumped whenever the layout below changes. States written by any other
+/// version are rejected rather than misread.
+pub const STATE_VERSION: u16 = 3;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StateError {
+    /// The data doesn't start with the save state header.
+    NotASaveState,
+    /// The state was written by an incompatible version of the format.
+    UnsupportedVersion(u16),
+    /// The data ends before the state does.
+    Truncated,
+    /// A field holds a value no machine could be in.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::NotASaveState => f.write_str("not a save state"),
+            StateError::UnsupportedVersion(version) => write!(
+                f,
+                "save state version {version} is not supported (expected {STATE_VERSION})"
+            ),
+            StateError::Truncated => f.write_str("save state is truncated"),
+            StateError::Invalid(what) => write!(f, "save state has an invalid {what}"),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.data.len() < len {
+            return Err(StateError::Truncated);
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, StateError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::Invalid("flag")),
+        }
+    }
+
+    fn u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, StateError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, StateError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn u128(&mut self) -> Result<u128, StateError> {
+        Ok(u128::from_le_bytes(self.array()?))
+    }
+}
+
+fn quirk_bits(quirks: &Quirks) -> u8 {
+    quirks.shift_uses_vy as u8
+        | (quirks.load_store_increments_i as u8) << 1
+        | (quirks.jump_uses_vx as u8) << 2
+        | (quirks.logic_resets_vf as u8) << 3
+        | (quirks.wrap_sprites as u8) << 4
+        | (quirks.display_wait as u8) << 5
+}
+
+fn quirks_from_bits(bits: u8) -> Quirks {
+    Quirks {
+        shift_uses_vy: bits & 1 != 0,
+        load_store_increments_i: bits & 1 << 1 != 0,
+        jump_uses_vx: bits & 1 << 2 != 0,
+        logic_resets_vf: bits & 1 << 3 != 0,
+        wrap_sprites: bits & 1 << 4 != 0,
+        display_wait: bits & 1 << 5 != 0,
+    }
+}
+
+impl Chip8 {
+    /// Serializes everything needed to resume execution exactly where it is:
+    /// memory, registers, stack, timers, framebuffer (including the deflicker
+    /// ghost), RNG algorithm and state, and quirks. Keypad state, `paused`,
+    /// the error policy, the debugger and the tracer belong to the frontend
+    /// and are not included.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.memory.len() + 4096);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&STATE_VERSION.to_le_bytes());
+        out.push(quirk_bits(&self.quirks));
+
+        out.extend_from_slice(&(self.memory.len() as u32).to_le_bytes());
+        out.extend_from_slice(&self.memory);
+        out.extend_from_slice(&self.v);
+        out.extend_from_slice(&self.i.to_le_bytes());
+        out.extend_from_slice(&self.pc.to_le_bytes());
+        for address in self.stack {
+            out.extend_from_slice(&address.to_le_bytes());
+        }
+        out.extend_from_slice(&self.sp.to_le_bytes());
+        out.push(self.delay_timer);
+        out.push(self.sound_timer);
+        out.extend_from_slice(&self.rpl);
+        out.push(self.audio_pattern.is_some() as u8);
+        out.extend_from_slice(&self.audio_pattern.unwrap_or_default());
+        out.push(self.pitch);
+        out.push(self.vblank_wait as u8);
+
+        let display = &self.display;
+        out.push(display.hires as u8);
+        out.push(display.selected_planes);
+        for gfx in [&display.gfx, &display.last_gfx] {
+            for row in gfx.iter().flatten() {
+                out.extend_from_slice(&row.to_le_bytes());
+            }
+        }
+        out.push(display.last_gfx_ttl);
+
+        out.push(self.rng.algorithm() as u8);
+        out.extend_from_slice(&self.rng.state().to_le_bytes());
+        out
+    }
+
+    /// Restores a state written by [`save_state`](Self::save_state). On error
+    /// the machine is left untouched.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        let mut reader = Reader { data: state };
+        if reader.bytes(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+            return Err(StateError::NotASaveState);
+        }
+        let version = reader.u16()?;
+        if version != STATE_VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+        let quirks = quirks_from_bits(reader.u8()?);
+
+        let memory_size = reader.u32()? as usize;
+        if memory_size != MEMORY_SIZE && memory_size != XO_CHIP_MEMORY_SIZE {
+            return Err(StateError::Invalid("memory size"));
+        }
+        let mut loaded = Chip8::with_memory_size(&[], memory_size);
+        loaded.quirks = quirks;
+        loaded.memory.copy_from_slice(reader.bytes(memory_size)?);
+        loaded.v = reader.array()?;
+        loaded.i = reader.u16()?;
+        loaded.pc = reader.u16()?;
+        for address in &mut loaded.stack {
+            *address = reader.u16()?;
+        }
+        loaded.sp = reader.u16()?;
+        if loaded.sp as usize > loaded.stack.len() {
+            return Err(StateError::Invalid("stack pointer"));
+        }
+        loaded.delay_timer = reader.u8()?;
+        loaded.sound_timer = reader.u8()?;
+        loaded.rpl = reader.array()?;
+        let has_audio_pattern = reader.bool()?;
+        let audio_pattern = reader.array()?;
+        loaded.audio_pattern = has_audio_pattern.then_some(audio_pattern);
+        loaded.pitch = reader.u8()?;
+        loaded.vblank_wait = reader.bool()?;
+
+        let display = &mut loaded.display;
+        display.hires = reader.bool()?;
+        display.selected_planes = reader.u8()?;
+        if display.selected_planes > 0b11 {
+            return Err(StateError::Invalid("plane selection"));
+        }
+        for plane in 0..PLANES {
+            for row in 0..HIRES_DISPLAY_HEIGHT {
+                display.gfx[plane][row] = reader.u128()?;
+            }
+        }
+        for plane in 0..PLANES {
+            for row in 0..HIRES_DISPLAY_HEIGHT {
+                display.last_gfx[plane][row] = reader.u128()?;
+            }
+        }
+        display.last_gfx_ttl = reader.u8()?;
+        display.deflicker = self.display.deflicker;
+
+        let algorithm = *RngAlgorithm::ALL
+            .get(reader.u8()? as usize)
+            .ok_or(StateError::Invalid("random number algorithm"))?;
+        loaded.rng = Rng::new(algorithm, reader.u64()?);
+        if !reader.data.is_empty() {
+            return Err(StateError::Invalid("length"));
+        }
+
+        loaded.keys = self.keys;
+        loaded.paused = self.paused;
+        loaded.error_policy = self.error_policy;
+        loaded.debugger = std::mem::take(&mut self.debugger);
+        loaded.trace = self.trace.take();
+        *self = loaded;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEMORY_SIZE_OFFSET: usize = MAGIC.len() + 3;
+    const SP_OFFSET: usize = MEMORY_SIZE_OFFSET + 4 + MEMORY_SIZE + 16 + 2 + 2 + 32;
+
+    /// A machine with something in every part of the state.
+    fn machine() -> Chip8 {
+        let mut chip8 = Chip8::new(&[0x12, 0x34]);
+        chip8.v[3] = 0x42;
+        chip8.i = 0x345;
+        chip8.pc = 0x280;
+        chip8.stack[..2].copy_from_slice(&[0x202, 0x246]);
+        chip8.sp = 2;
+        chip8.delay_timer = 9;
+        chip8.rpl[0] = 7;
+        chip8.audio_pattern = Some([0xAA; 16]);
+        chip8.display.set_hires(true);
+        chip8.display.draw(100, 50, &[0x80], false);
+        chip8.rng = Rng::new(RngAlgorithm::CosmacVip, 0x1234);
+        chip8.rng.next_u8(&chip8.memory);
+        chip8
+    }
+
+    #[test]
+    fn round_trips() {
+        let original = machine();
+        let state = original.save_state();
+        let mut loaded = Chip8::new(&[]);
+        loaded.load_state(&state).unwrap();
+        assert_eq!(loaded.save_state(), state);
+        assert_eq!(loaded.pc, 0x280);
+        assert_eq!(loaded.stack[..2], [0x202, 0x246]);
+        assert_eq!(loaded.rng, original.rng);
+        assert!(loaded.display.hires());
+    }
+
+    #[test]
+    fn rejects_other_data() {
+        let mut state = machine().save_state();
+        let mut chip8 = Chip8::new(&[]);
+        assert_eq!(
+            chip8.load_state(b"not a state"),
+            Err(StateError::NotASaveState)
+        );
+        state[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&(STATE_VERSION + 1).to_le_bytes());
+        assert_eq!(
+            chip8.load_state(&state),
+            Err(StateError::UnsupportedVersion(STATE_VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_states() {
+        let state = machine().save_state();
+        let mut chip8 = Chip8::new(&[]);
+        for len in MAGIC.len()..state.len() {
+            assert_eq!(
+                chip8.load_state(&state[..len]),
+                Err(StateError::Truncated),
+                "{len} bytes"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_impossible_fields_and_keeps_the_machine() {
+        let state = machine().save_state();
+        let with = |offset: usize, bytes: &[u8]| {
+            let mut state = state.clone();
+            state[offset..offset + bytes.len()].copy_from_slice(bytes);
+            state
+        };
+        let mut chip8 = Chip8::new(&[]);
+        let cases = [
+            (with(SP_OFFSET, &17u16.to_le_bytes()), "stack pointer"),
+            (
+                with(MEMORY_SIZE_OFFSET, &5000u32.to_le_bytes()),
+                "memory size",
+            ),
+            (with(state.len() - 9, &[2]), "random number algorithm"),
+            ([state.as_slice(), &[0]].concat(), "length"),
+        ];
+        for (state, what) in cases {
+            assert_eq!(chip8.load_state(&state), Err(StateError::Invalid(what)));
+            assert_eq!(chip8.pc, 0x200);
+        }
+        let sixteen_deep = with(SP_OFFSET, &16u16.to_le_bytes());
+        assert_eq!(chip8.load_state(&sixteen_deep), Ok(()));
+    }
+}
@@ -0,0 +1,310 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::cpu::Chip8;
+use crate::decode::{Address, Opcode, UnknownOpcode};
+use crate::disasm::{format_opcode, Syntax};
+
+/// How each traced instruction is written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// Aligned columns for reading:
+    /// `      12 0204 6005     LOAD V0, 0x05   v=00 ... i=0000 sp=0 dt=00 st=00`
+    #[default]
+    Text,
+    /// One JSON object per line, with every number in decimal, for diffing
+    /// against other emulators' traces.
+    JsonLines,
+}
+
+#[derive(Debug)]
+pub struct UnknownTraceFormat(pub String);
+
+impl fmt::Display for UnknownTraceFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown trace format '{}' (expected text or json)",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownTraceFormat {}
+
+impl FromStr for TraceFormat {
+    type Err = UnknownTraceFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" | "txt" => Ok(TraceFormat::Text),
+            "json" | "jsonl" | "json-lines" => Ok(TraceFormat::JsonLines),
+            _ => Err(UnknownTraceFormat(s.to_owned())),
+        }
+    }
+}
+
+/// Parses a hex address range like `200-2FF` or `0x200-0x2FF`. A single
+/// address is a range of one.
+pub fn parse_address_range(text: &str) -> Option<RangeInclusive<Address>> {
+    let parse = |s: &str| {
+        let s = s.trim();
+        Address::from_str_radix(s.strip_prefix("0x").unwrap_or(s), 16).ok()
+    };
+    match text.split_once('-') {
+        Some((start, end)) => Some(parse(start)?..=parse(end)?),
+        None => parse(text).map(|address| address..=address),
+    }
+}
+
+/// Writes a line for every instruction a [`Chip8`] executes, describing the
+/// machine just before the instruction runs. Attach one through
+/// `Chip8::trace`.
+pub struct Tracer {
+    out: Box<dyn Write>,
+    pub format: TraceFormat,
+    /// Only instructions at these addresses are written.
+    pub range: Option<RangeInclusive<Address>>,
+    /// Stop tracing after this many instructions.
+    pub limit: Option<u64>,
+    cycles: u64,
+    lines: u64,
+    error: Option<io::Error>,
+}
+
+impl Tracer {
+    pub fn new(out: impl Write + 'static, format: TraceFormat) -> Self {
+        Self {
+            out: Box::new(out),
+            format,
+            range: None,
+            limit: None,
+            cycles: 0,
+            lines: 0,
+            error: None,
+        }
+    }
+
+    /// Traces to a newly created file at `path`.
+    pub fn create(path: &Path, format: TraceFormat) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?), format))
+    }
+
+    /// Instructions executed since tracing started, traced or not.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Lines written so far.
+    pub fn lines(&self) -> u64 {
+        self.lines
+    }
+
+    /// Whether the cycle limit has been reached or writing failed.
+    pub fn finished(&self) -> bool {
+        self.error.is_some() || self.limit.is_some_and(|limit| self.cycles >= limit)
+    }
+
+    /// The write error that stopped tracing, if any.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    /// Records the instruction `chip8` is about to execute.
+    pub(crate) fn record(&mut self, chip8: &Chip8) {
+        if self.finished() {
+            return;
+        }
+        let cycle = self.cycles;
+        self.cycles += 1;
+        if self.range.as_ref().is_some_and(|r| !r.contains(&chip8.pc)) {
+            return;
+        }
+        let line = match self.format {
+            TraceFormat::Text => text_line(cycle, chip8),
+            TraceFormat::JsonLines => json_line(cycle, chip8),
+        };
+        match writeln!(self.out, "{line}") {
+            Ok(()) => self.lines += 1,
+            Err(e) => self.error = Some(e),
+        }
+        if self.finished() {
+            // Nothing more is coming, so don't leave the tail sitting in a
+            // buffer until the tracer is dropped.
+            if let Err(e) = self.out.flush() {
+                self.error.get_or_insert(e);
+            }
+        }
+    }
+}
+
+/// The instruction at `pc` as bytes, sized like the decoder sees it.
+fn instruction_bytes(chip8: &Chip8, opcode: &Result<Opcode, UnknownOpcode>) -> Vec<u8> {
+    let size = opcode.as_ref().map_or(2, Opcode::size) as usize;
+    (0..size)
+        .map(|n| chip8.memory[(chip8.pc as usize + n) % chip8.memory.len()])
+        .collect()
+}
+
+fn text_line(cycle: u64, chip8: &Chip8) -> String {
+    let opcode = Opcode::decode_at(&chip8.memory, chip8.pc);
+    let raw: String = instruction_bytes(chip8, &opcode)
+        .iter()
+        .map(|b| format!("{b:02X}"))
+        .collect();
+    let text = match &opcode {
+        Ok(opcode) => format_opcode(opcode, Syntax::Quip8, &|a| format!("{a:#05X}")),
+        Err(_) => "UNKNOWN".to_owned(),
+    };
+    let v: Vec<String> = chip8.v.iter().map(|v| format!("{v:02X}")).collect();
+    format!(
+        "{cycle:>8} {:04X} {raw:<8} {text:<20} v={} i={:04X} sp={:X} dt={:02X} st={:02X}",
+        chip8.pc,
+        v.join(" "),
+        chip8.i,
+        chip8.sp,
+        chip8.delay_timer,
+        chip8.sound_timer,
+    )
+}
+
+fn json_line(cycle: u64, chip8: &Chip8) -> String {
+    let opcode = Opcode::decode_at(&chip8.memory, chip8.pc);
+    let raw = instruction_bytes(chip8, &opcode)
+        .iter()
+        .fold(0u32, |word, &b| word << 8 | b as u32);
+    let (mnemonic, operands) = match &opcode {
+        Ok(opcode) => (
+            opcode.mnemonic(),
+            (0..3).map_while(|i| opcode.operand(i)).collect(),
+        ),
+        Err(_) => ("UNKNOWN", Vec::new()),
+    };
+    let list = |values: &mut dyn Iterator<Item = String>| values.collect::<Vec<_>>().join(",");
+    format!(
+        r#"{{"cycle":{cycle},"pc":{},"opcode":{raw},"mnemonic":"{mnemonic}","operands":[{}],"v":[{}],"i":{},"sp":{},"dt":{},"st":{}}}"#,
+        chip8.pc,
+        list(&mut operands.iter().map(u16::to_string)),
+        list(&mut chip8.v.iter().map(u8::to_string)),
+        chip8.i,
+        chip8.sp,
+        chip8.delay_timer,
+        chip8.sound_timer,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// A writer the test can still read after handing it to a [`Tracer`].
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Traces `cycles` instructions of: V0 := 5; loop: V0 += 1; jump loop
+    fn trace(tracer: impl FnOnce(Tracer) -> Tracer, cycles: usize) -> (Chip8, String) {
+        let out = Shared::default();
+        let mut chip8 = Chip8::new(&[0x60, 0x05, 0x70, 0x01, 0x12, 0x02]);
+        chip8.paused = false;
+        chip8.trace = Some(tracer(Tracer::new(out.clone(), TraceFormat::Text)));
+        for _ in 0..cycles {
+            chip8.emulate_cycle().unwrap();
+        }
+        let text = String::from_utf8(out.0.take()).unwrap();
+        (chip8, text)
+    }
+
+    /// The register columns of a text line with only V0 set.
+    fn registers(v0: &str) -> String {
+        format!("v={v0}{} i=0000 sp=0 dt=00 st=00", " 00".repeat(15))
+    }
+
+    #[test]
+    fn writes_text() {
+        let (_, text) = trace(|tracer| tracer, 3);
+        let expected = [
+            format!(
+                "       0 0200 6005     LOAD V0, 0x05        {}",
+                registers("00")
+            ),
+            format!(
+                "       1 0202 7001     ADD V0, 0x01         {}",
+                registers("05")
+            ),
+            format!(
+                "       2 0204 1202     JUMP 0x202           {}",
+                registers("06")
+            ),
+        ];
+        assert_eq!(text.lines().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn writes_json_lines() {
+        let (_, text) = trace(
+            |mut tracer| {
+                tracer.format = TraceFormat::JsonLines;
+                tracer
+            },
+            2,
+        );
+        let zeros = ",0".repeat(15);
+        let expected = [
+            format!(
+                r#"{{"cycle":0,"pc":512,"opcode":24581,"mnemonic":"LOAD","operands":[0,5],"v":[0{zeros}],"i":0,"sp":0,"dt":0,"st":0}}"#
+            ),
+            format!(
+                r#"{{"cycle":1,"pc":514,"opcode":28673,"mnemonic":"ADD","operands":[0,1],"v":[5{zeros}],"i":0,"sp":0,"dt":0,"st":0}}"#
+            ),
+        ];
+        assert_eq!(text.lines().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn filters_by_range_and_stops_at_the_limit() {
+        let (chip8, text) = trace(
+            |mut tracer| {
+                tracer.range = parse_address_range("202");
+                tracer.limit = Some(4);
+                tracer
+            },
+            7,
+        );
+        let cycles: Vec<&str> = text
+            .lines()
+            .map(|line| line.split_whitespace().next().unwrap())
+            .collect();
+        assert_eq!(cycles, ["1", "3"]);
+        let tracer = chip8.trace.as_ref().unwrap();
+        assert!(tracer.finished());
+        assert_eq!((tracer.cycles(), tracer.lines()), (4, 2));
+    }
+
+    #[test]
+    fn parses_address_ranges() {
+        assert_eq!(parse_address_range("200-2FF"), Some(0x200..=0x2FF));
+        assert_eq!(parse_address_range("0x300"), Some(0x300..=0x300));
+        assert_eq!(parse_address_range("20G"), None);
+    }
+}
@@ -0,0 +1,289 @@
+//! Runs the test ROMs in `tests/roms` and compares the final screen with the
+//! one expected. See `tests/roms/README.md` for what each ROM covers.
+//!
+//! Expected screens never come from the emulator. Each is drawn here on a
+//! [`Screen`], a deliberately naive model that XORs the ROM's sprite data
+//! pixel by pixel, so a bug in an opcode or in `Display` shows up as a
+//! mismatch instead of being recorded as the right answer.
+
+use std::path::{Path, PathBuf};
+
+use quip8_core::{
+    image, octo, Chip8, QuirkProfile, Quirks, Scheduler, BIG_FONT_SET, FONT_SET, MEMORY_SIZE,
+    XO_CHIP_MEMORY_SIZE,
+};
+
+use QuirkProfile::*;
+
+/// Long enough for every ROM to finish even when draws wait for vblank.
+const FRAMES: u32 = 60;
+
+fn tests_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests")
+}
+
+/// Loads `roms/<rom>.ch8` as is, or else assembles `roms/<rom>.8o`.
+fn load(rom: &str) -> Vec<u8> {
+    let roms = tests_dir().join("roms");
+    let binary = roms.join(format!("{rom}.ch8"));
+    if binary.exists() {
+        return std::fs::read(&binary).unwrap_or_else(|e| panic!("{}: {e}", binary.display()));
+    }
+    let path = roms.join(format!("{rom}.8o"));
+    octo::compile_file(&path)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .rom
+}
+
+/// Runs `rom` for `FRAMES` frames, pressing `keys[n].1` from frame
+/// `keys[n].0` on, and returns the screen.
+fn run(rom: &str, profile: QuirkProfile, keys: &[(u32, u16)]) -> String {
+    let memory_size = if profile == QuirkProfile::XoChip {
+        XO_CHIP_MEMORY_SIZE
+    } else {
+        MEMORY_SIZE
+    };
+    let mut chip8 = Chip8::with_memory_size(&load(rom), memory_size);
+    chip8.quirks = profile.quirks();
+    chip8.display.deflicker = false;
+    chip8.paused = false;
+
+    let mut scheduler = Scheduler::default();
+    for frame in 0..FRAMES {
+        if let Some(&(_, pressed)) = keys.iter().rev().find(|(at, _)| *at <= frame) {
+            chip8.set_keys(pressed);
+        }
+        scheduler.run_frame(&mut chip8);
+        if let Some(reason) = chip8.debugger.take_break() {
+            panic!("{rom} under {profile}: {reason} at frame {frame}");
+        }
+    }
+    image::ascii(&chip8.display)
+}
+
+/// Runs `rom` under each of `profiles` and compares the screen with the one
+/// `expected` draws for that profile.
+fn check(
+    rom: &str,
+    profiles: &[QuirkProfile],
+    keys: &[(u32, u16)],
+    expected: impl Fn(QuirkProfile) -> Screen,
+) {
+    let mut failures = Vec::new();
+    for &profile in profiles {
+        let screen = run(rom, profile, keys);
+        let expected = expected(profile).text();
+        if screen != expected {
+            failures.push(format!(
+                "{rom} under {profile}\nexpected:\n{expected}\ngot:\n{screen}"
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+/// Reference framebuffer: one color per pixel, bit 0 for plane 1 and bit 1
+/// for plane 2, printed like `image::ascii`.
+struct Screen {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec<u8>>,
+}
+
+impl Screen {
+    fn lores() -> Self {
+        Self::new(64, 32)
+    }
+
+    fn hires() -> Self {
+        Self::new(128, 64)
+    }
+
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![vec![0; width]; height],
+        }
+    }
+
+    /// XORs `rows`, each `bits` wide and most significant bit first, onto
+    /// `plane` with the top left corner at (`x`, `y`). Pixels past the right
+    /// or bottom edge wrap if `wrap` is set and are dropped otherwise.
+    fn draw(&mut self, plane: u8, x: usize, y: usize, rows: &[u16], bits: usize, wrap: bool) {
+        for (dy, row) in rows.iter().enumerate() {
+            for dx in 0..bits {
+                if row >> (bits - 1 - dx) & 1 == 0 {
+                    continue;
+                }
+                let (px, py) = (x + dx, y + dy);
+                if !wrap && (px >= self.width || py >= self.height) {
+                    continue;
+                }
+                self.pixels[py % self.height][px % self.width] ^= 1 << plane;
+            }
+        }
+    }
+
+    /// Draws 8 pixel wide `sprite` on plane 1.
+    fn sprite(&mut self, x: usize, y: usize, sprite: &[u8], wrap: bool) {
+        let rows: Vec<u16> = sprite.iter().map(|&row| row as u16).collect();
+        self.draw(0, x, y, &rows, 8, wrap);
+    }
+
+    /// Draws hex digit `digit` from the small font.
+    fn digit(&mut self, x: usize, y: usize, digit: u8) {
+        let start = digit as usize * 5;
+        self.sprite(x, y, &FONT_SET[start..start + 5], false);
+    }
+
+    /// Moves `plane` by (`dx`, `dy`), dropping what leaves the screen.
+    fn scroll(&mut self, plane: u8, dx: isize, dy: isize) {
+        let mask = 1 << plane;
+        let old = self.pixels.clone();
+        for (y, row) in self.pixels.iter_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let (sx, sy) = (x as isize - dx, y as isize - dy);
+                let source = old
+                    .get(sy as usize)
+                    .and_then(|row| row.get(sx as usize))
+                    .filter(|_| sx >= 0 && sy >= 0);
+                *pixel = *pixel & !mask | source.map_or(0, |source| source & mask);
+            }
+        }
+    }
+
+    fn text(&self) -> String {
+        self.pixels
+            .iter()
+            .map(|row| {
+                let mut line: String = row
+                    .iter()
+                    .map(|&c| ['.', '#', '+', '*'][c as usize])
+                    .collect();
+                line.push('\n');
+                line
+            })
+            .collect()
+    }
+}
+
+/// Runs a self-checking ROM under every profile and expects every check in
+/// it to pass: a 3x3 block per check, 16 to a row.
+fn check_passes(rom: &str) {
+    let path = tests_dir().join("roms").join(format!("{rom}.8o"));
+    let source = std::fs::read_to_string(&path).unwrap();
+    let checks = source.lines().filter(|line| line.trim() == "check").count();
+    assert!(checks > 0, "{rom} has no checks");
+    check(rom, &QuirkProfile::ALL, &[], |_| {
+        let mut screen = Screen::lores();
+        for check in 0..checks {
+            screen.sprite(check % 16 * 4, check / 16 * 5, &[0xE0; 3], false);
+        }
+        screen
+    });
+}
+
+#[test]
+fn logo() {
+    let letters: [&[u8]; 6] = [
+        &[0x7C, 0xC6, 0xC6, 0xC6, 0xD6, 0xCC, 0x76],
+        &[0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0xC6, 0x7C],
+        &[0x7E, 0x18, 0x18, 0x18, 0x18, 0x18, 0x7E],
+        &[0xFC, 0xC6, 0xC6, 0xFC, 0xC0, 0xC0, 0xC0],
+        &[0x00, 0x00, 0x00, 0x78, 0x00, 0x00, 0x00],
+        &[0x7C, 0xC6, 0xC6, 0x7C, 0xC6, 0xC6, 0x7C],
+    ];
+    check("logo", &QuirkProfile::ALL, &[], |profile| {
+        let mut screen = Screen::lores();
+        for (x, letter) in [8, 18, 28, 38, 48, 56].into_iter().zip(letters) {
+            screen.sprite(x, 12, letter, false);
+        }
+        let wrap = profile.quirks().wrap_sprites;
+        let corner_box = [0xFF, 0x81, 0x81, 0x81, 0x81, 0x81, 0x81, 0xFF];
+        screen.sprite(60, 28, &corner_box, wrap);
+        screen
+    });
+}
+
+#[test]
+fn opcodes() {
+    check_passes("opcodes");
+}
+
+#[test]
+fn flags() {
+    check_passes("flags");
+}
+
+#[test]
+fn quirks() {
+    check("quirks", &QuirkProfile::ALL, &[], |profile| {
+        let Quirks {
+            shift_uses_vy,
+            load_store_increments_i,
+            jump_uses_vx,
+            logic_resets_vf,
+            wrap_sprites,
+            display_wait,
+        } = profile.quirks();
+        let mut screen = Screen::lores();
+        let shown = [
+            jump_uses_vx,
+            shift_uses_vy,
+            load_store_increments_i,
+            logic_resets_vf,
+            wrap_sprites,
+            display_wait,
+        ];
+        for (n, on) in shown.into_iter().enumerate() {
+            screen.digit(n * 5, 0, on as u8);
+        }
+        screen
+    });
+}
+
+#[test]
+fn keypad() {
+    // Press A, release, press 5 and 7 together, release. The ROM shows the
+    // key FX0A saw, the 5 it waited for, then F once 5 is released.
+    let keys = [(5, 1 << 0xA), (8, 0), (12, 1 << 0x5 | 1 << 0x7), (16, 0)];
+    check("keypad", &QuirkProfile::ALL, &keys, |_| {
+        let mut screen = Screen::lores();
+        for (n, digit) in [0xA, 0x5, 0xF].into_iter().enumerate() {
+            screen.digit(n * 5, 0, digit);
+        }
+        screen
+    });
+}
+
+#[test]
+fn hires() {
+    check("hires", &[SuperChip, XoChip], &[], |profile| {
+        let wrap = profile.quirks().wrap_sprites;
+        let mut square = [0x8001; 16];
+        square[0] = 0xFFFF;
+        square[15] = 0xFFFF;
+        let seven: Vec<u16> = BIG_FONT_SET[70..80].iter().map(|&row| row as u16).collect();
+        let mut screen = Screen::hires();
+        screen.draw(0, 16, 16, &square, 16, wrap);
+        screen.draw(0, 120, 56, &square, 16, wrap);
+        screen.draw(0, 48, 20, &seven, 8, wrap);
+        screen.scroll(0, 4, 0);
+        screen.scroll(0, 0, 2);
+        screen
+    });
+}
+
+#[test]
+fn planes() {
+    check("planes", &[XoChip], &[], |_| {
+        let mut screen = Screen::lores();
+        screen.draw(0, 8, 8, &[0xFF; 8], 8, true);
+        screen.draw(1, 12, 12, &[0xFF; 8], 8, true);
+        screen.draw(0, 40, 8, &[0xF0; 8], 8, true);
+        screen.draw(1, 40, 8, &[0x3C; 8], 8, true);
+        screen.scroll(1, -4, 0);
+        screen
+    });
+}
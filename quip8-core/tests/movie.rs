@@ -0,0 +1,112 @@
+//! Records `tests/roms/keypad.8o` being played as a movie and plays it back.
+
+use std::path::Path;
+
+use quip8_core::{
+    octo, ErrorPolicy, Movie, MovieError, MovieSession, QuirkProfile, RngAlgorithm, Scheduler,
+    MEMORY_SIZE,
+};
+
+/// Long enough for two checksums.
+const FRAMES: u32 = 150;
+
+fn rom() -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/roms/keypad.8o");
+    octo::compile_file(&path)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .rom
+}
+
+/// Records the keypad ROM at `ips` with the key presses
+/// `tests/conformance.rs` uses.
+fn record(rom: &[u8], ips: u32) -> Movie {
+    let movie = Movie::new(
+        rom,
+        QuirkProfile::CosmacVip.quirks(),
+        MEMORY_SIZE,
+        ips,
+        ErrorPolicy::Halt,
+        RngAlgorithm::Xorshift,
+        1234,
+    );
+    let mut chip8 = movie.machine(rom).unwrap();
+    chip8.paused = false;
+    let mut scheduler = Scheduler::default();
+    scheduler.start_movie(MovieSession::record(movie));
+    let keys = [(5, 1 << 0xA), (8, 0), (12, 1 << 0x5 | 1 << 0x7), (16, 0)];
+    for frame in 0..FRAMES {
+        if let Some(&(_, pressed)) = keys.iter().find(|(at, _)| *at == frame) {
+            chip8.set_keys(pressed);
+        }
+        scheduler.run_frame(&mut chip8);
+    }
+    scheduler.movie.unwrap().movie
+}
+
+/// Plays `movie` to the end and returns the session and final checksum.
+fn play(rom: &[u8], movie: Movie) -> (MovieSession, u64) {
+    let mut chip8 = movie.machine(rom).unwrap();
+    chip8.paused = false;
+    let mut scheduler = Scheduler::default();
+    scheduler.start_movie(MovieSession::play(movie));
+    while !scheduler.movie.as_ref().unwrap().finished() {
+        scheduler.run_frame(&mut chip8);
+    }
+    (scheduler.movie.unwrap(), chip8.checksum())
+}
+
+#[test]
+fn text_round_trips() {
+    let movie = record(&rom(), 700);
+    assert_eq!(movie.frames.len(), FRAMES as usize);
+    assert_eq!(movie.checksums.len(), 2);
+    assert_eq!(Movie::parse(&movie.to_text()), Ok(movie));
+}
+
+#[test]
+fn zero_speed_round_trips() {
+    // Nothing runs, but what was recorded still has to play back.
+    let rom = rom();
+    let movie = record(&rom, 0);
+    assert_eq!(Movie::parse(&movie.to_text()), Ok(movie.clone()));
+    let (session, _) = play(&rom, movie);
+    assert_eq!(session.desync(), None);
+}
+
+#[test]
+fn playback_matches_recording() {
+    let rom = rom();
+    let movie = record(&rom, 700);
+    let (session, first) = play(&rom, movie.clone());
+    assert_eq!(session.desync(), None);
+    assert_eq!(session.frame(), FRAMES as u64);
+    let (_, second) = play(&rom, movie);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn changed_input_desyncs() {
+    let rom = rom();
+    let mut movie = record(&rom, 700);
+    // Press 3 where A was recorded, which the ROM marks as a failed check.
+    movie.frames[5].keys = 1 << 0x3;
+    movie.frames[5].key_pressed = Some(0x3);
+    let (session, _) = play(&rom, movie);
+    assert_eq!(session.desync().map(|desync| desync.frame), Some(60));
+}
+
+#[test]
+fn other_rom_is_rejected() {
+    let movie = record(&rom(), 700);
+    assert!(movie.machine(&[0x12, 0x00]).is_err());
+}
+
+#[test]
+fn endless_input_is_rejected() {
+    let mut text = record(&rom(), 700).to_text();
+    text.push_str("input 99999999999999 0000\n");
+    match Movie::parse(&text) {
+        Err(MovieError::Parse { message, .. }) => assert!(message.contains("frames"), "{message}"),
+        other => panic!("parsed as {other:?}"),
+    }
+}
@@ -0,0 +1,1058 @@
+//! One row per `Opcode` variant and edge case. Each row sets up a machine
+//! with the instruction at `START`, runs a single `emulate_cycle` and checks
+//! the whole machine against a second one given only the changes the
+//! instruction should make, so anything else it touches is caught too.
+
+use std::collections::BTreeSet;
+
+use quip8_core::{
+    image, Chip8, Chip8Error, ErrorPolicy, Opcode, QuirkProfile, Rng, RngAlgorithm, StepEvent,
+    BIG_FONT_START_ADDRESS, FIRST_INSTRUCTION_ADDRESS as START, FONT_START_ADDRESS,
+};
+
+type Change = fn(&mut Chip8);
+
+struct Case {
+    name: &'static str,
+    opcode: Opcode,
+    profile: QuirkProfile,
+    /// Applied to both machines before the instruction runs.
+    setup: Change,
+    /// Applied to the expected machine after `setup` and moving `pc` past
+    /// the instruction.
+    expect: Change,
+    /// Pixels lit afterwards.
+    lit: &'static [(usize, usize)],
+    result: Result<StepEvent, Chip8Error>,
+}
+
+fn case(name: &'static str, opcode: Opcode, setup: Change, expect: Change) -> Case {
+    Case {
+        name,
+        opcode,
+        profile: QuirkProfile::CosmacVip,
+        setup,
+        expect,
+        lit: &[],
+        result: Ok(StepEvent::Executed),
+    }
+}
+
+impl Case {
+    fn on(mut self, profile: QuirkProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    fn lit(mut self, lit: &'static [(usize, usize)]) -> Self {
+        self.lit = lit;
+        self
+    }
+
+    fn result(mut self, result: Result<StepEvent, Chip8Error>) -> Self {
+        self.result = result;
+        self
+    }
+
+    /// Expects the instruction to fault, leaving the machine paused on it.
+    fn halts(self, error: Chip8Error) -> Self {
+        let mut case = self.result(Err(error));
+        case.expect = |c| {
+            c.pc = START;
+            c.paused = true;
+        };
+        case
+    }
+
+    fn machine(&self) -> Chip8 {
+        let mut chip8 = Chip8::new(&self.opcode.encode());
+        chip8.quirks = self.profile.quirks();
+        chip8.rng = Rng::new(RngAlgorithm::Xorshift, 1);
+        chip8.display.deflicker = false;
+        chip8.paused = false;
+        (self.setup)(&mut chip8);
+        chip8
+    }
+
+    fn run(&self) {
+        let name = format!("{} {}", self.opcode.mnemonic(), self.name);
+        let mut got = self.machine();
+        let result = got.emulate_cycle();
+        assert_eq!(result, self.result, "{name}: result");
+
+        let mut want = self.machine();
+        want.pc += self.opcode.size();
+        (self.expect)(&mut want);
+        assert_eq!(got.v, want.v, "{name}: V");
+        assert_eq!(got.i, want.i, "{name}: I");
+        assert_eq!(got.pc, want.pc, "{name}: pc");
+        assert_eq!(got.sp, want.sp, "{name}: sp");
+        assert_eq!(got.stack, want.stack, "{name}: stack");
+        assert_eq!(got.delay_timer, want.delay_timer, "{name}: delay timer");
+        assert_eq!(got.sound_timer, want.sound_timer, "{name}: sound timer");
+        assert_eq!(got.rng, want.rng, "{name}: RNG");
+        assert_eq!(got.key_pressed, want.key_pressed, "{name}: key pressed");
+        assert_eq!(got.rpl, want.rpl, "{name}: RPL flags");
+        assert_eq!(got.audio_pattern, want.audio_pattern, "{name}: audio");
+        assert_eq!(got.pitch, want.pitch, "{name}: pitch");
+        assert_eq!(got.vblank_wait, want.vblank_wait, "{name}: vblank wait");
+        assert_eq!(got.paused, want.paused, "{name}: paused");
+        if let Some(address) = (0..want.memory.len()).find(|&a| got.memory[a] != want.memory[a]) {
+            panic!(
+                "{name}: memory at {address:#05X} is {:#04X}, expected {:#04X}",
+                got.memory[address], want.memory[address]
+            );
+        }
+        assert_eq!(got.display.hires(), want.display.hires(), "{name}: hires");
+        assert_eq!(
+            got.display.selected_planes(),
+            want.display.selected_planes(),
+            "{name}: planes"
+        );
+
+        let width = want.display.width();
+        let mut screen = String::new();
+        for y in 0..want.display.height() {
+            for x in 0..width {
+                screen.push(if self.lit.contains(&(x, y)) { '#' } else { '.' });
+            }
+            screen.push('\n');
+        }
+        assert_eq!(image::ascii(&got.display), screen, "{name}: screen");
+    }
+}
+
+fn dot(c: &mut Chip8, x: usize, y: usize) {
+    c.display.draw(x, y, &[0x80], false);
+}
+
+fn cases() -> Vec<Case> {
+    use QuirkProfile::{Chip48, SuperChip, XoChip};
+    vec![
+        case("calls machine code", Opcode::SYS(0x300), |_| {}, |_| {}).halts(
+            Chip8Error::MachineCodeCall {
+                pc: START,
+                address: 0x300,
+            },
+        ),
+        case("clears the screen", Opcode::CLR, |c| dot(c, 3, 4), |_| {}),
+        case(
+            "pops the return address",
+            Opcode::RTS,
+            |c| {
+                c.sp = 1;
+                c.stack[0] = 0x346;
+            },
+            |c| {
+                c.pc = 0x346;
+                c.sp = 0;
+            },
+        ),
+        case("with an empty stack", Opcode::RTS, |_| {}, |_| {})
+            .halts(Chip8Error::StackUnderflow { pc: START }),
+        case(
+            "with an empty stack wraps",
+            Opcode::RTS,
+            |c| {
+                c.error_policy = ErrorPolicy::Wrap;
+                c.stack[15] = 0x250;
+            },
+            |c| {
+                c.pc = 0x250;
+                c.sp = 15;
+            },
+        ),
+        case("scrolls down", Opcode::SCD(3), |c| dot(c, 0, 0), |_| {}).lit(&[(0, 3)]),
+        case("off the bottom", Opcode::SCD(3), |c| dot(c, 0, 30), |_| {}),
+        case("scrolls up", Opcode::SCU(2), |c| dot(c, 5, 4), |_| {}).lit(&[(5, 2)]),
+        case("scrolls right", Opcode::SCR, |c| dot(c, 0, 0), |_| {}).lit(&[(4, 0)]),
+        case("off the right edge", Opcode::SCR, |c| dot(c, 62, 0), |_| {}),
+        case("scrolls left", Opcode::SCL, |c| dot(c, 8, 1), |_| {}).lit(&[(4, 1)]),
+        case("off the left edge", Opcode::SCL, |c| dot(c, 2, 1), |_| {}),
+        case(
+            "pauses on itself",
+            Opcode::EXIT,
+            |_| {},
+            |c| {
+                c.pc = START;
+                c.paused = true;
+            },
+        )
+        .result(Ok(StepEvent::Exited)),
+        case(
+            "clears the screen",
+            Opcode::LOW,
+            |c| {
+                c.display.set_hires(true);
+                dot(c, 100, 50);
+            },
+            |c| c.display.set_hires(false),
+        ),
+        case(
+            "clears the screen",
+            Opcode::HIGH,
+            |c| dot(c, 1, 1),
+            |c| c.display.set_hires(true),
+        ),
+        case("jumps", Opcode::JUMP(0x345), |_| {}, |c| c.pc = 0x345),
+        case(
+            "pushes the return address",
+            Opcode::CALL(0x400),
+            |_| {},
+            |c| {
+                c.sp = 1;
+                c.stack[0] = START + 2;
+                c.pc = 0x400;
+            },
+        ),
+        case(
+            "fills the last stack entry",
+            Opcode::CALL(0x400),
+            |c| c.sp = 15,
+            |c| {
+                c.sp = 16;
+                c.stack[15] = START + 2;
+                c.pc = 0x400;
+            },
+        ),
+        case(
+            "with a full stack",
+            Opcode::CALL(0x400),
+            |c| c.sp = 16,
+            |_| {},
+        )
+        .halts(Chip8Error::StackOverflow { pc: START }),
+        case(
+            "with a full stack wraps",
+            Opcode::CALL(0x400),
+            |c| {
+                c.error_policy = ErrorPolicy::Wrap;
+                c.sp = 16;
+            },
+            |c| {
+                c.sp = 1;
+                c.stack[0] = START + 2;
+                c.pc = 0x400;
+            },
+        ),
+        case(
+            "skips when equal",
+            Opcode::SKE((3, 0x42)),
+            |c| c.v[3] = 0x42,
+            |c| c.pc += 2,
+        ),
+        case(
+            "doesn't skip when different",
+            Opcode::SKE((3, 0x41)),
+            |c| c.v[3] = 0x42,
+            |_| {},
+        ),
+        case(
+            "skips a long load whole",
+            Opcode::SKE((3, 0)),
+            |c| c.memory[START as usize + 2] = 0xF0,
+            |c| c.pc += 4,
+        ),
+        case(
+            "skips when different",
+            Opcode::SKNE((3, 0x41)),
+            |c| c.v[3] = 0x42,
+            |c| c.pc += 2,
+        ),
+        case(
+            "doesn't skip when equal",
+            Opcode::SKNE((3, 0x42)),
+            |c| c.v[3] = 0x42,
+            |_| {},
+        ),
+        case(
+            "skips when equal",
+            Opcode::SKRE((1, 2)),
+            |c| c.v[..3].copy_from_slice(&[0, 7, 7]),
+            |c| c.pc += 2,
+        ),
+        case(
+            "doesn't skip when different",
+            Opcode::SKRE((1, 2)),
+            |c| c.v[..3].copy_from_slice(&[0, 7, 8]),
+            |_| {},
+        ),
+        case(
+            "stores upwards",
+            Opcode::STORER((2, 4)),
+            |c| {
+                c.v[2..5].copy_from_slice(&[1, 2, 3]);
+                c.i = 0x300;
+            },
+            |c| c.memory[0x300..0x303].copy_from_slice(&[1, 2, 3]),
+        )
+        .on(XoChip),
+        case(
+            "stores downwards",
+            Opcode::STORER((4, 2)),
+            |c| {
+                c.v[2..5].copy_from_slice(&[1, 2, 3]);
+                c.i = 0x300;
+            },
+            |c| c.memory[0x300..0x303].copy_from_slice(&[3, 2, 1]),
+        )
+        .on(XoChip),
+        case(
+            "reads upwards",
+            Opcode::READR((1, 3)),
+            |c| {
+                c.memory[0x300..0x303].copy_from_slice(&[7, 8, 9]);
+                c.i = 0x300;
+            },
+            |c| c.v[1..4].copy_from_slice(&[7, 8, 9]),
+        )
+        .on(XoChip),
+        case(
+            "reads downwards",
+            Opcode::READR((3, 1)),
+            |c| {
+                c.memory[0x300..0x303].copy_from_slice(&[7, 8, 9]);
+                c.i = 0x300;
+            },
+            |c| c.v[1..4].copy_from_slice(&[9, 8, 7]),
+        )
+        .on(XoChip),
+        case(
+            "past the end of memory",
+            Opcode::STORER((4, 2)),
+            |c| c.i = 0xFFE,
+            |_| {},
+        )
+        .on(XoChip)
+        .halts(Chip8Error::MemoryOutOfBounds {
+            pc: START,
+            address: 0xFFE,
+            len: 3,
+        }),
+        case(
+            "past the end of memory wraps",
+            Opcode::STORER((2, 4)),
+            |c| {
+                c.error_policy = ErrorPolicy::Wrap;
+                c.v[2..5].copy_from_slice(&[1, 2, 3]);
+                c.i = 0xFFE;
+            },
+            |c| {
+                c.memory[0xFFE..].copy_from_slice(&[1, 2]);
+                c.memory[0] = 3;
+            },
+        )
+        .on(XoChip),
+        case(
+            "past the end of memory",
+            Opcode::READR((1, 3)),
+            |c| c.i = 0xFFF,
+            |_| {},
+        )
+        .on(XoChip)
+        .halts(Chip8Error::MemoryOutOfBounds {
+            pc: START,
+            address: 0xFFF,
+            len: 3,
+        }),
+        case(
+            "sets VX",
+            Opcode::LOAD((5, 0xAB)),
+            |_| {},
+            |c| c.v[5] = 0xAB,
+        ),
+        case(
+            "adds without touching VF",
+            Opcode::ADD((2, 0x10)),
+            |c| {
+                c.v[2] = 0x20;
+                c.v[0xF] = 0x55;
+            },
+            |c| c.v[2] = 0x30,
+        ),
+        case(
+            "wraps past 0xFF without a carry",
+            Opcode::ADD((2, 0x02)),
+            |c| c.v[2] = 0xFF,
+            |c| c.v[2] = 0x01,
+        ),
+        case(
+            "copies VY",
+            Opcode::MOVE((1, 2)),
+            |c| c.v[2] = 0x99,
+            |c| c.v[1] = 0x99,
+        ),
+        case(
+            "resets VF",
+            Opcode::OR((1, 2)),
+            |c| {
+                c.v[..3].copy_from_slice(&[0, 0x0C, 0x0A]);
+                c.v[0xF] = 0x55;
+            },
+            |c| {
+                c.v[1] = 0x0E;
+                c.v[0xF] = 0;
+            },
+        ),
+        case(
+            "keeps VF",
+            Opcode::OR((1, 2)),
+            |c| {
+                c.v[..3].copy_from_slice(&[0, 0x0C, 0x0A]);
+                c.v[0xF] = 0x55;
+            },
+            |c| c.v[1] = 0x0E,
+        )
+        .on(Chip48),
+        case(
+            "resets VF",
+            Opcode::AND((1, 2)),
+            |c| {
+                c.v[..3].copy_from_slice(&[0, 0x0C, 0x0A]);
+                c.v[0xF] = 0x55;
+            },
+            |c| {
+                c.v[1] = 0x08;
+                c.v[0xF] = 0;
+            },
+        ),
+        case(
+            "keeps VF",
+            Opcode::AND((1, 2)),
+            |c| {
+                c.v[..3].copy_from_slice(&[0, 0x0C, 0x0A]);
+                c.v[0xF] = 0x55;
+            },
+            |c| c.v[1] = 0x08,
+        )
+        .on(Chip48),
+        case(
+            "resets VF",
+            Opcode::XOR((1, 2)),
+            |c| {
+                c.v[..3].copy_from_slice(&[0, 0x0C, 0x0A]);
+                c.v[0xF] = 0x55;
+            },
+            |c| {
+                c.v[1] = 0x06;
+                c.v[0xF] = 0;
+            },
+        ),
+        case(
+            "keeps VF",
+            Opcode::XOR((1, 2)),
+            |c| {
+                c.v[..3].copy_from_slice(&[0, 0x0C, 0x0A]);
+                c.v[0xF] = 0x55;
+            },
+            |c| c.v[1] = 0x06,
+        )
+        .on(Chip48),
+        case(
+            "without a carry",
+            Opcode::ADDR((1, 2)),
+            |c| {
+                c.v[..3].copy_from_slice(&[0, 0x10, 0x20]);
+                c.v[0xF] = 0x55;
+            },
+            |c| {
+                c.v[1] = 0x30;
+                c.v[0xF] = 0;
+            },
+        ),
+        case(
+            "with a carry",
+            Opcode::ADDR((1, 2)),
+            |c| c.v[..3].copy_from_slice(&[0, 0xFF, 0x01]),
+            |c| {
+                c.v[1] = 0x00;
+                c.v[0xF] = 1;
+            },
+        ),
+        case(
+            "into VF keeps the carry",
+            Opcode::ADDR((0xF, 1)),
+            |c| {
+                c.v[1] = 0x02;
+                c.v[0xF] = 0xFF;
+            },
+            |c| c.v[0xF] = 1,
+        ),
+        case(
+            "without a borrow",
+            Opcode::SUB((1, 2)),
+            |c| c.v[..3].copy_from_slice(&[0, 0x30, 0x10]),
+            |c| {
+                c.v[1] = 0x20;
+                c.v[0xF] = 1;
+            },
+        ),
+        case(
+            "of equal values has no borrow",
+            Opcode::SUB((1, 2)),
+            |c| c.v[..3].copy_from_slice(&[0, 0x10, 0x10]),
+            |c| {
+                c.v[1] = 0x00;
+                c.v[0xF] = 1;
+            },
+        ),
+        case(
+            "with a borrow",
+            Opcode::SUB((1, 2)),
+            |c| {
+                c.v[..3].copy_from_slice(&[0, 0x10, 0x30]);
+                c.v[0xF] = 0x55;
+            },
+            |c| {
+                c.v[1] = 0xE0;
+                c.v[0xF] = 0;
+            },
+        ),
+        case(
+            "shifts VY",
+            Opcode::SHR((1, 2)),
+            |c| c.v[..3].copy_from_slice(&[0, 0xFF, 0x05]),
+            |c| {
+                c.v[1] = 0x02;
+                c.v[0xF] = 1;
+            },
+        ),
+        case(
+            "shifts VX",
+            Opcode::SHR((1, 2)),
+            |c| c.v[..3].copy_from_slice(&[0, 0x04, 0x05]),
+            |c| {
+                c.v[1] = 0x02;
+                c.v[0xF] = 0;
+            },
+        )
+        .on(SuperChip),
+        case(
+            "into VF keeps the flag",
+            Opcode::SHR((0xF, 0xF)),
+            |c| c.v[0xF] = 0x03,
+            |c| c.v[0xF] = 1,
+        )
+        .on(SuperChip),
+        case(
+            "without a borrow",
+            Opcode::RSUB((1, 2)),
+            |c| c.v[..3].copy_from_slice(&[0, 0x10, 0x30]),
+            |c| {
+                c.v[1] = 0x20;
+                c.v[0xF] = 1;
+            },
+        ),
+        case(
+            "with a borrow",
+            Opcode::RSUB((1, 2)),
+            |c| c.v[..3].copy_from_slice(&[0, 0x30, 0x10]),
+            |c| {
+                c.v[1] = 0xE0;
+                c.v[0xF] = 0;
+            },
+        ),
+        case(
+            "into VF keeps the flag",
+            Opcode::RSUB((0xF, 1)),
+            |c| {
+                c.v[1] = 0x10;
+                c.v[0xF] = 0x30;
+            },
+            |c| c.v[0xF] = 0,
+        ),
+        case(
+            "shifts VY",
+            Opcode::SHL((1, 2)),
+            |c| c.v[..3].copy_from_slice(&[0, 0x00, 0x81]),
+            |c| {
+                c.v[1] = 0x02;
+                c.v[0xF] = 1;
+            },
+        ),
+        case(
+            "shifts VX",
+            Opcode::SHL((1, 2)),
+            |c| c.v[..3].copy_from_slice(&[0, 0x40, 0x81]),
+            |c| {
+                c.v[1] = 0x80;
+                c.v[0xF] = 0;
+            },
+        )
+        .on(SuperChip),
+        case(
+            "into VF keeps the flag",
+            Opcode::SHL((0xF, 0xF)),
+            |c| c.v[0xF] = 0x80,
+            |c| c.v[0xF] = 1,
+        )
+        .on(SuperChip),
+        case(
+            "skips when different",
+            Opcode::SKRNE((1, 2)),
+            |c| c.v[..3].copy_from_slice(&[0, 7, 8]),
+            |c| c.pc += 2,
+        ),
+        case(
+            "doesn't skip when equal",
+            Opcode::SKRNE((1, 2)),
+            |c| c.v[..3].copy_from_slice(&[0, 7, 7]),
+            |_| {},
+        ),
+        case("sets I", Opcode::LOADI(0x123), |_| {}, |c| c.i = 0x123),
+        case(
+            "sets all 16 bits of I",
+            Opcode::LOADIL(0xBEEF),
+            |_| {},
+            |c| c.i = 0xBEEF,
+        )
+        .on(XoChip),
+        case(
+            "adds V0",
+            Opcode::JUMPI(0x300),
+            |c| c.v[..4].copy_from_slice(&[4, 0, 0, 9]),
+            |c| c.pc = 0x304,
+        ),
+        case(
+            "adds VX",
+            Opcode::JUMPI(0x310),
+            |c| c.v[..4].copy_from_slice(&[4, 0, 0, 9]),
+            |c| c.pc = 0x319,
+        )
+        .on(SuperChip),
+        case(
+            "masks a random byte",
+            Opcode::RAND((1, 0x0F)),
+            |_| {},
+            |c| c.v[1] = c.rng.next_u8(&c.memory) & 0x0F,
+        ),
+        case(
+            "with a zero mask",
+            Opcode::RAND((1, 0x00)),
+            |c| c.v[1] = 0x55,
+            |c| {
+                c.rng.next_u8(&c.memory);
+                c.v[1] = 0;
+            },
+        ),
+        case(
+            "like the COSMAC VIP",
+            Opcode::RAND((1, 0xF0)),
+            |c| {
+                c.rng = Rng::new(RngAlgorithm::CosmacVip, 0x00FF);
+                c.memory[0x100] = 0xFF;
+            },
+            |c| {
+                // 0xFF + 0x01 carries; rotating 0x00 through it gives 0x80.
+                c.rng = Rng::new(RngAlgorithm::CosmacVip, 0x8000);
+                c.v[1] = 0x80;
+            },
+        ),
+        case(
+            "draws and waits for vblank",
+            Opcode::DRAW((1, 2, 2)),
+            |c| {
+                c.v[..3].copy_from_slice(&[0, 1, 2]);
+                c.memory[0x300..0x302].copy_from_slice(&[0xC0, 0x40]);
+                c.i = 0x300;
+                c.v[0xF] = 0x55;
+            },
+            |c| {
+                c.v[0xF] = 0;
+                c.vblank_wait = true;
+            },
+        )
+        .lit(&[(1, 2), (2, 2), (2, 3)]),
+        case(
+            "without waiting",
+            Opcode::DRAW((1, 2, 1)),
+            |c| {
+                c.v[..3].copy_from_slice(&[0, 1, 2]);
+                c.memory[0x300] = 0x80;
+                c.i = 0x300;
+            },
+            |_| {},
+        )
+        .on(Chip48)
+        .lit(&[(1, 2)]),
+        case(
+            "erasing a pixel sets VF",
+            Opcode::DRAW((1, 2, 1)),
+            |c| {
+                c.v[..3].copy_from_slice(&[0, 1, 2]);
+                c.memory[0x300] = 0xC0;
+                c.i = 0x300;
+                dot(c, 1, 2);
+            },
+            |c| c.v[0xF] = 1,
+        )
+        .on(Chip48)
+        .lit(&[(2, 2)]),
+        case(
+            "clips at the right edge",
+            Opcode::DRAW((1, 2, 1)),
+            |c| {
+                c.v[..3].copy_from_slice(&[0, 62, 0]);
+                c.memory[0x300] = 0xF0;
+                c.i = 0x300;
+            },
+            |_| {},
+        )
+        .on(Chip48)
+        .lit(&[(62, 0), (63, 0)]),
+        case(
+            "clips at the bottom edge",
+            Opcode::DRAW((1, 2, 2)),
+            |c| {
+                c.v[..3].copy_from_slice(&[0, 5, 31]);
+                c.memory[0x300..0x302].copy_from_slice(&[0x80, 0x80]);
+                c.i = 0x300;
+            },
+            |_| {},
+        )
+        .on(Chip48)
+        .lit(&[(5, 31)]),
+        case(
+            "wraps at the edges",
+            Opcode::DRAW((1, 2, 2)),
+            |c| {
+                c.v[..3].copy_from_slice(&[0, 63, 31]);
+                c.memory[0x300..0x302].copy_from_slice(&[0xC0, 0xC0]);
+                c.i = 0x300;
+            },
+            |_| {},
+        )
+        .on(XoChip)
+        .lit(&[(63, 31), (0, 31), (63, 0), (0, 0)]),
+        case(
+            "wraps the starting position",
+            Opcode::DRAW((1, 2, 1)),
+            |c| {
+                c.v[..3].copy_from_slice(&[0, 64 + 3, 32 + 4]);
+                c.memory[0x300] = 0x80;
+                c.i = 0x300;
+            },
+            |_| {},
+        )
+        .on(Chip48)
+        .lit(&[(3, 4)]),
+        case(
+            "draws 16x16 sprites with height 0",
+            Opcode::DRAW((1, 2, 0)),
+            |c| {
+                c.display.set_hires(true);
+                c.v[..3].copy_from_slice(&[0, 100, 60]);
+                c.memory[0x300..0x302].copy_from_slice(&[0x80, 0x01]);
+                c.memory[0x306..0x308].copy_from_slice(&[0x80, 0x01]);
+                c.i = 0x300;
+            },
+            |_| {},
+        )
+        .on(SuperChip)
+        .lit(&[(100, 60), (115, 60), (100, 63), (115, 63)]),
+        case(
+            "past the end of memory",
+            Opcode::DRAW((1, 2, 2)),
+            |c| c.i = 0xFFF,
+            |_| {},
+        )
+        .halts(Chip8Error::MemoryOutOfBounds {
+            pc: START,
+            address: 0xFFF,
+            len: 2,
+        }),
+        case(
+            "skips when pressed",
+            Opcode::SKPR(1),
+            |c| {
+                c.v[1] = 0xA;
+                c.set_keys(1 << 0xA);
+            },
+            |c| c.pc += 2,
+        ),
+        case(
+            "uses the low nibble",
+            Opcode::SKPR(1),
+            |c| {
+                c.v[1] = 0x1A;
+                c.set_keys(1 << 0xA);
+            },
+            |c| c.pc += 2,
+        ),
+        case(
+            "doesn't skip when released",
+            Opcode::SKPR(1),
+            |c| {
+                c.v[1] = 0xA;
+                c.set_keys(1 << 0xB);
+            },
+            |_| {},
+        ),
+        case(
+            "skips when released",
+            Opcode::SKUP(1),
+            |c| {
+                c.v[1] = 0xA;
+                c.set_keys(1 << 0xB);
+            },
+            |c| c.pc += 2,
+        ),
+        case(
+            "doesn't skip when pressed",
+            Opcode::SKUP(1),
+            |c| {
+                c.v[1] = 0xA;
+                c.set_keys(1 << 0xA);
+            },
+            |_| {},
+        ),
+        case(
+            "reads the delay timer",
+            Opcode::MOVED(4),
+            |c| c.delay_timer = 0x33,
+            |c| c.v[4] = 0x33,
+        ),
+        case(
+            "waits for a key",
+            Opcode::KEYD(2),
+            |c| c.set_keys(1 << 7),
+            |c| {
+                c.v[2] = 7;
+                c.key_pressed = None;
+            },
+        ),
+        case(
+            "stays put until a key is pressed",
+            Opcode::KEYD(2),
+            |_| {},
+            |c| c.pc = START,
+        )
+        .result(Ok(StepEvent::WaitingForKey)),
+        case(
+            "sets the delay timer",
+            Opcode::LOADD(4),
+            |c| c.v[4] = 0x33,
+            |c| c.delay_timer = 0x33,
+        ),
+        case(
+            "sets the sound timer",
+            Opcode::LOADS(4),
+            |c| c.v[4] = 0x33,
+            |c| c.sound_timer = 0x33,
+        ),
+        case(
+            "selects planes",
+            Opcode::PLANE(2),
+            |_| {},
+            |c| c.display.select_planes(2),
+        )
+        .on(XoChip),
+        case(
+            "loads the pattern",
+            Opcode::AUDIO,
+            |c| {
+                c.memory[0x300..0x310].copy_from_slice(&[0xAA; 16]);
+                c.i = 0x300;
+            },
+            |c| c.audio_pattern = Some([0xAA; 16]),
+        )
+        .on(XoChip),
+        case(
+            "sets the pitch",
+            Opcode::PITCH(1),
+            |c| c.v[1] = 0x70,
+            |c| c.pitch = 0x70,
+        )
+        .on(XoChip),
+        case(
+            "adds without touching VF",
+            Opcode::ADDI(1),
+            |c| {
+                c.v[1] = 0x10;
+                c.i = 0x100;
+            },
+            |c| c.i = 0x110,
+        ),
+        case(
+            "wraps past 0xFFFF",
+            Opcode::ADDI(1),
+            |c| {
+                c.v[1] = 0x02;
+                c.i = 0xFFFF;
+            },
+            |c| c.i = 0x0001,
+        ),
+        case(
+            "points at the digit",
+            Opcode::LDSPR(1),
+            |c| c.v[1] = 0xB,
+            |c| c.i = FONT_START_ADDRESS + 0xB * 5,
+        ),
+        case(
+            "uses the low nibble",
+            Opcode::LDSPR(1),
+            |c| c.v[1] = 0x1B,
+            |c| c.i = FONT_START_ADDRESS + 0xB * 5,
+        ),
+        case(
+            "points at the digit",
+            Opcode::LDBSPR(1),
+            |c| c.v[1] = 3,
+            |c| c.i = BIG_FONT_START_ADDRESS + 3 * 10,
+        )
+        .on(SuperChip),
+        case(
+            "of 0",
+            Opcode::BCD(1),
+            |c| {
+                c.memory[0x300..0x303].fill(0xFF);
+                c.i = 0x300;
+            },
+            |c| c.memory[0x300..0x303].copy_from_slice(&[0, 0, 0]),
+        ),
+        case(
+            "of 9",
+            Opcode::BCD(1),
+            |c| {
+                c.v[1] = 9;
+                c.i = 0x300;
+            },
+            |c| c.memory[0x300..0x303].copy_from_slice(&[0, 0, 9]),
+        ),
+        case(
+            "of 255",
+            Opcode::BCD(1),
+            |c| {
+                c.v[1] = 255;
+                c.i = 0x300;
+            },
+            |c| c.memory[0x300..0x303].copy_from_slice(&[2, 5, 5]),
+        ),
+        case(
+            "past the end of memory",
+            Opcode::BCD(1),
+            |c| c.i = 0xFFE,
+            |_| {},
+        )
+        .halts(Chip8Error::MemoryOutOfBounds {
+            pc: START,
+            address: 0xFFE,
+            len: 3,
+        }),
+        case(
+            "moves I past the registers",
+            Opcode::STORE(2),
+            |c| {
+                c.v[..3].copy_from_slice(&[1, 2, 3]);
+                c.i = 0x300;
+            },
+            |c| {
+                c.memory[0x300..0x303].copy_from_slice(&[1, 2, 3]);
+                c.i = 0x303;
+            },
+        ),
+        case(
+            "leaves I",
+            Opcode::STORE(2),
+            |c| {
+                c.v[..3].copy_from_slice(&[1, 2, 3]);
+                c.i = 0x300;
+            },
+            |c| c.memory[0x300..0x303].copy_from_slice(&[1, 2, 3]),
+        )
+        .on(SuperChip),
+        case(
+            "past the end of memory",
+            Opcode::STORE(1),
+            |c| c.i = 0xFFF,
+            |_| {},
+        )
+        .halts(Chip8Error::MemoryOutOfBounds {
+            pc: START,
+            address: 0xFFF,
+            len: 2,
+        }),
+        case(
+            "moves I past the registers",
+            Opcode::READ(2),
+            |c| {
+                c.memory[0x300..0x303].copy_from_slice(&[7, 8, 9]);
+                c.i = 0x300;
+            },
+            |c| {
+                c.v[..3].copy_from_slice(&[7, 8, 9]);
+                c.i = 0x303;
+            },
+        ),
+        case(
+            "leaves I",
+            Opcode::READ(2),
+            |c| {
+                c.memory[0x300..0x303].copy_from_slice(&[7, 8, 9]);
+                c.i = 0x300;
+            },
+            |c| c.v[..3].copy_from_slice(&[7, 8, 9]),
+        )
+        .on(SuperChip),
+        case(
+            "saves V0 to VX",
+            Opcode::STOREF(2),
+            |c| c.v[..4].copy_from_slice(&[1, 2, 3, 4]),
+            |c| c.rpl[..3].copy_from_slice(&[1, 2, 3]),
+        )
+        .on(SuperChip),
+        case(
+            "restores V0 to VX",
+            Opcode::READF(1),
+            |c| c.rpl[..3].copy_from_slice(&[1, 2, 3]),
+            |c| c.v[..2].copy_from_slice(&[1, 2]),
+        )
+        .on(SuperChip),
+    ]
+}
+
+#[test]
+fn every_case_matches() {
+    for case in cases() {
+        case.run();
+    }
+}
+
+#[test]
+fn every_opcode_has_a_case() {
+    let tested: BTreeSet<&str> = cases().iter().map(|case| case.opcode.mnemonic()).collect();
+    let mut missing = BTreeSet::new();
+    for word in 0..=u16::MAX {
+        let [high, low] = word.to_be_bytes();
+        if let Ok(opcode) = Opcode::decode_at(&[high, low, 0, 0], 0) {
+            if !tested.contains(opcode.mnemonic()) {
+                missing.insert(opcode.mnemonic());
+            }
+        }
+    }
+    assert!(missing.is_empty(), "no cases for {missing:?}");
+}
+
+#[test]
+fn sixteen_calls_fit_on_the_stack() {
+    // Each instruction calls the one after it.
+    let rom: Vec<u8> = (0..17)
+        .flat_map(|n| Opcode::CALL(START + 2 * (n + 1)).encode())
+        .collect();
+    let mut chip8 = Chip8::new(&rom);
+    chip8.paused = false;
+    for depth in 1..=16 {
+        assert_eq!(
+            chip8.emulate_cycle(),
+            Ok(StepEvent::Executed),
+            "call {depth}"
+        );
+        assert_eq!(chip8.sp, depth);
+    }
+    let returns: Vec<u16> = (1..=16).map(|n| START + 2 * n).collect();
+    assert_eq!(chip8.stack.to_vec(), returns);
+    assert_eq!(
+        chip8.emulate_cycle(),
+        Err(Chip8Error::StackOverflow { pc: START + 32 })
+    );
+}
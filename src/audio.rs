@@ -0,0 +1,68 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use quip8_core::AudioSink;
+
+/// Plays beeper samples on the default output device. Samples are queued from
+/// the UI thread and drained by cpal's callback, which pads with silence when
+/// the emulator falls behind.
+pub struct CpalSink {
+    _stream: cpal::Stream,
+    queue: Arc<Mutex<VecDeque<f32>>>,
+    sample_rate: u32,
+}
+
+impl CpalSink {
+    pub fn open() -> Result<Self, String> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or("no audio output device")?;
+        let config: cpal::StreamConfig = device
+            .default_output_config()
+            .map_err(|e| e.to_string())?
+            .into();
+        let channels = config.channels as usize;
+        let queue = Arc::new(Mutex::new(VecDeque::new()));
+
+        let callback_queue = queue.clone();
+        let stream = device
+            .build_output_stream(
+                &config,
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                    let mut queue = callback_queue.lock().unwrap();
+                    for frame in data.chunks_mut(channels) {
+                        frame.fill(queue.pop_front().unwrap_or(0.0));
+                    }
+                },
+                |e| eprintln!("Audio stream error: {e}"),
+                None,
+            )
+            .map_err(|e| e.to_string())?;
+        stream.play().map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            _stream: stream,
+            queue,
+            sample_rate: config.sample_rate.0,
+        })
+    }
+}
+
+impl AudioSink for CpalSink {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn push_samples(&mut self, samples: &[f32]) {
+        let mut queue = self.queue.lock().unwrap();
+        queue.extend(samples);
+        // Keep latency bounded to ~100ms if the device drains slower than we
+        // produce.
+        let max_len = self.sample_rate as usize / 10;
+        if queue.len() > max_len {
+            let excess = queue.len() - max_len;
+            queue.drain(..excess);
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+use egui::{Key, RichText};
+
+/// Extensions listed when "ROMs only" is ticked.
+const ROM_EXTENSIONS: [&str; 6] = ["ch8", "c8", "sc8", "xo8", "8o", "rom"];
+
+struct Entry {
+    name: String,
+    path: PathBuf,
+    is_dir: bool,
+    size: u64,
+}
+
+/// An in-app file picker for opening ROMs, so no native dialog is needed.
+pub struct FileBrowser {
+    pub open: bool,
+    dir: PathBuf,
+    /// Contents of `dir`, directories first, refreshed on navigation.
+    entries: Vec<Entry>,
+    selected: Option<PathBuf>,
+    /// The path field above the listing.
+    path_text: String,
+    roms_only: bool,
+    error: Option<String>,
+}
+
+impl Default for FileBrowser {
+    fn default() -> Self {
+        Self {
+            open: false,
+            dir: PathBuf::new(),
+            entries: Vec::new(),
+            selected: None,
+            path_text: String::new(),
+            roms_only: true,
+            error: None,
+        }
+    }
+}
+
+fn is_rom(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| ROM_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+fn format_size(size: u64) -> String {
+    if size < 1024 {
+        format!("{size} B")
+    } else {
+        format!("{:.1} KiB", size as f64 / 1024.0)
+    }
+}
+
+impl FileBrowser {
+    /// Shows the browser listing `dir`, or the current directory if `dir` is
+    /// `None` or can't be read.
+    pub fn open_in(&mut self, dir: Option<&Path>) {
+        self.open = true;
+        let start = dir
+            .filter(|dir| dir.is_dir())
+            .map(Path::to_owned)
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        self.navigate(start);
+    }
+
+    fn navigate(&mut self, dir: PathBuf) {
+        let dir = dir.canonicalize().unwrap_or(dir);
+        let read = match std::fs::read_dir(&dir) {
+            Ok(read) => read,
+            Err(e) => {
+                self.error = Some(format!("{}: {e}", dir.display()));
+                return;
+            }
+        };
+        self.entries = read
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                // Follows symlinks, so linked directories can be entered.
+                let metadata = std::fs::metadata(entry.path()).ok()?;
+                (!name.starts_with('.')).then(|| Entry {
+                    name,
+                    path: entry.path(),
+                    is_dir: metadata.is_dir(),
+                    size: metadata.len(),
+                })
+            })
+            .collect();
+        self.entries.sort_by(|a, b| {
+            b.is_dir
+                .cmp(&a.is_dir)
+                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+        });
+        self.path_text = dir.display().to_string();
+        self.dir = dir;
+        self.selected = None;
+        self.error = None;
+    }
+
+    /// Draws the browser if it's open. Returns the file the user chose to
+    /// open, closing the browser.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<PathBuf> {
+        let mut open = self.open;
+        let mut chosen = None;
+        egui::Window::new("Open ROM")
+            .open(&mut open)
+            .default_size([460.0, 360.0])
+            .show(ctx, |ui| chosen = self.contents(ui));
+        // Cancel closes from inside the window.
+        self.open &= open && chosen.is_none();
+        chosen
+    }
+
+    fn contents(&mut self, ui: &mut egui::Ui) -> Option<PathBuf> {
+        let mut chosen = None;
+        let mut go_to = None;
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.dir.parent().is_some(), egui::Button::new("Up"))
+                .clicked()
+            {
+                go_to = self.dir.parent().map(Path::to_owned);
+            }
+            let response = ui
+                .add(egui::TextEdit::singleline(&mut self.path_text).desired_width(f32::INFINITY));
+            if response.lost_focus() && ui.input().key_pressed(Key::Enter) {
+                let path = PathBuf::from(self.path_text.trim());
+                if path.is_file() {
+                    chosen = Some(path);
+                } else {
+                    go_to = Some(path);
+                }
+            }
+        });
+        ui.checkbox(&mut self.roms_only, "ROMs and Octo sources only");
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        ui.separator();
+
+        let footer_height = ui.spacing().interact_size.y + ui.spacing().item_spacing.y * 2.0;
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .max_height(ui.available_height() - footer_height)
+            .show(ui, |ui| {
+                for entry in &self.entries {
+                    if !entry.is_dir && self.roms_only && !is_rom(&entry.path) {
+                        continue;
+                    }
+                    let selected = self.selected.as_ref() == Some(&entry.path);
+                    let response = ui.horizontal(|ui| {
+                        let text = if entry.is_dir {
+                            RichText::new(format!("{}/", entry.name)).strong()
+                        } else {
+                            RichText::new(&entry.name)
+                        };
+                        let response = ui.selectable_label(selected, text);
+                        if !entry.is_dir {
+                            ui.weak(format_size(entry.size));
+                        }
+                        response
+                    });
+                    let response = response.inner;
+                    if response.double_clicked() {
+                        if entry.is_dir {
+                            go_to = Some(entry.path.clone());
+                        } else {
+                            chosen = Some(entry.path.clone());
+                        }
+                    } else if response.clicked() {
+                        self.selected = Some(entry.path.clone());
+                    }
+                }
+            });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            let selected = self.selected.clone();
+            if ui
+                .add_enabled(selected.is_some(), egui::Button::new("Open"))
+                .clicked()
+            {
+                match selected {
+                    Some(path) if path.is_dir() => go_to = Some(path),
+                    path => chosen = path,
+                }
+            }
+            if ui.button("Cancel").clicked() {
+                self.open = false;
+            }
+        });
+
+        if let Some(dir) = go_to {
+            self.navigate(dir);
+        }
+        chosen
+    }
+}
@@ -0,0 +1,370 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+use egui::{Key, RichText};
+
+use crate::ANALAGOUS2_COLOR;
+
+/// The COSMAC VIP keypad, row by row.
+const KEYPAD: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// Host keys that can be bound. Function keys, Backspace and Escape are
+/// taken by save slots, rewind and cancelling.
+const BINDABLE: [Key; 51] = [
+    Key::ArrowDown,
+    Key::ArrowLeft,
+    Key::ArrowRight,
+    Key::ArrowUp,
+    Key::Tab,
+    Key::Enter,
+    Key::Space,
+    Key::Insert,
+    Key::Delete,
+    Key::Home,
+    Key::End,
+    Key::PageUp,
+    Key::PageDown,
+    Key::Minus,
+    Key::PlusEquals,
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+];
+
+fn key_named(name: &str) -> Option<Key> {
+    BINDABLE
+        .into_iter()
+        .find(|key| key.name().eq_ignore_ascii_case(name))
+}
+
+/// Host keys bound to each CHIP-8 key. A key can have any number of
+/// bindings, e.g. a letter and an arrow key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: [Vec<Key>; 16],
+}
+
+impl Default for Keymap {
+    /// 1234/QWER/ASDF/ZXCV as keys 0-F in reading order.
+    fn default() -> Self {
+        let host = [
+            Key::Num1,
+            Key::Num2,
+            Key::Num3,
+            Key::Num4,
+            Key::Q,
+            Key::W,
+            Key::E,
+            Key::R,
+            Key::A,
+            Key::S,
+            Key::D,
+            Key::F,
+            Key::Z,
+            Key::X,
+            Key::C,
+            Key::V,
+        ];
+        Keymap {
+            bindings: host.map(|host| vec![host]),
+        }
+    }
+}
+
+impl Keymap {
+    /// Host keys bound to CHIP-8 key `key`.
+    pub fn bindings(&self, key: u8) -> &[Key] {
+        &self.bindings[key as usize]
+    }
+
+    /// The keypad state for `Chip8::set_keys`.
+    pub fn keys_down(&self, input: &egui::InputState) -> u16 {
+        let mut keys = 0;
+        for (key, bindings) in self.bindings.iter().enumerate() {
+            if bindings.iter().any(|host| input.key_down(*host)) {
+                keys |= 1 << key;
+            }
+        }
+        keys
+    }
+
+    /// Binds `host` to `key`, taking it away from any other key.
+    fn bind(&mut self, key: u8, host: Key) {
+        for bindings in &mut self.bindings {
+            bindings.retain(|&bound| bound != host);
+        }
+        self.bindings[key as usize].push(host);
+    }
+
+    /// Reads lines like `5 = W, ArrowUp` following a section header.
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let (key, hosts) = line
+            .split_once('=')
+            .ok_or_else(|| format!("expected 'KEY = HOST, ...' in '{line}'"))?;
+        let key = u8::from_str_radix(key.trim(), 16)
+            .ok()
+            .filter(|&key| key < 16)
+            .ok_or_else(|| format!("'{}' isn't a keypad key", key.trim()))?;
+        self.bindings[key as usize].clear();
+        for host in hosts.split(',').map(str::trim).filter(|h| !h.is_empty()) {
+            let host = key_named(host).ok_or_else(|| format!("unknown key '{host}'"))?;
+            self.bind(key, host);
+        }
+        Ok(())
+    }
+
+    fn write(&self, out: &mut String) {
+        for (key, bindings) in self.bindings.iter().enumerate() {
+            let names: Vec<&str> = bindings.iter().map(|host| host.name()).collect();
+            out.push_str(&format!("{key:X} = {}\n", names.join(", ")));
+        }
+    }
+}
+
+/// The default keymap and per-ROM overrides, kept in `keymap` in the config
+/// directory. Overrides are keyed by ROM file name so they follow a ROM
+/// between folders.
+#[derive(Default)]
+pub struct Keymaps {
+    default: Keymap,
+    overrides: BTreeMap<String, Keymap>,
+}
+
+fn rom_name(rom: &Path) -> Option<String> {
+    rom.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+impl Keymaps {
+    fn file() -> Option<PathBuf> {
+        crate::config_dir().map(|dir| dir.join("keymap"))
+    }
+
+    /// Reads the saved keymaps. A missing file gives the defaults.
+    ///
+    /// ```text
+    /// [default]
+    /// 1 = Num1
+    /// ...
+    /// [pong.ch8]
+    /// 1 = W, ArrowUp
+    /// ```
+    pub fn load() -> Result<Self, String> {
+        let Some(file) = Self::file() else {
+            return Ok(Self::default());
+        };
+        let text = match std::fs::read_to_string(&file) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("{}: {e}", file.display())),
+        };
+        let mut keymaps = Self::default();
+        let mut section: Option<&mut Keymap> = None;
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| format!("{}:{}: {message}", file.display(), number + 1);
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = Some(if name == "default" {
+                    &mut keymaps.default
+                } else {
+                    keymaps.overrides.entry(name.to_owned()).or_default()
+                });
+                continue;
+            }
+            section
+                .as_mut()
+                .ok_or_else(|| error("binding outside a section".to_owned()))?
+                .parse_line(line)
+                .map_err(error)?;
+        }
+        Ok(keymaps)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(file) = Self::file() else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut text = String::from("# CHIP-8 key = host keys\n[default]\n");
+        self.default.write(&mut text);
+        for (rom, keymap) in &self.overrides {
+            text.push_str(&format!("\n[{rom}]\n"));
+            keymap.write(&mut text);
+        }
+        std::fs::write(file, text)
+    }
+
+    /// The keymap in effect for `rom`.
+    pub fn for_rom(&self, rom: Option<&Path>) -> &Keymap {
+        rom.and_then(rom_name)
+            .and_then(|name| self.overrides.get(&name))
+            .unwrap_or(&self.default)
+    }
+}
+
+/// The keymap editor window.
+#[derive(Default)]
+pub struct KeymapEditor {
+    pub open: bool,
+    /// The keypad key waiting for a host key press.
+    capturing: Option<u8>,
+    error: Option<String>,
+}
+
+impl KeymapEditor {
+    pub fn show(&mut self, ctx: &egui::Context, keymaps: &mut Keymaps, rom: Option<&Path>) {
+        let mut open = self.open;
+        egui::Window::new("Keymap")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| self.contents(ui, keymaps, rom));
+        self.open = open;
+        if !open {
+            self.capturing = None;
+        }
+    }
+
+    fn contents(&mut self, ui: &mut egui::Ui, keymaps: &mut Keymaps, rom: Option<&Path>) {
+        let rom_name = rom.and_then(rom_name);
+        let mut changed = false;
+        if let Some(name) = &rom_name {
+            let mut overridden = keymaps.overrides.contains_key(name);
+            if ui
+                .checkbox(&mut overridden, format!("Separate keys for {name}"))
+                .changed()
+            {
+                if overridden {
+                    let keymap = keymaps.default.clone();
+                    keymaps.overrides.insert(name.clone(), keymap);
+                } else {
+                    keymaps.overrides.remove(name);
+                }
+                changed = true;
+            }
+        }
+        let keymap = match rom_name.and_then(|name| keymaps.overrides.get_mut(&name)) {
+            Some(keymap) => keymap,
+            None => &mut keymaps.default,
+        };
+
+        if let Some(key) = self.capturing {
+            ui.label(
+                RichText::new(format!("Press a key for {key:X} (Escape cancels)"))
+                    .color(ANALAGOUS2_COLOR),
+            );
+            let pressed = ui.input().events.iter().find_map(|event| match event {
+                egui::Event::Key {
+                    key, pressed: true, ..
+                } => Some(*key),
+                _ => None,
+            });
+            match pressed {
+                Some(Key::Escape) => self.capturing = None,
+                Some(host) if BINDABLE.contains(&host) => {
+                    keymap.bind(key, host);
+                    self.capturing = None;
+                    changed = true;
+                }
+                _ => {}
+            }
+        } else {
+            ui.weak("Click + to add a key, or a key to remove it");
+        }
+        ui.separator();
+
+        egui::Grid::new("keypad")
+            .striped(true)
+            .min_col_width(96.0)
+            .show(ui, |ui| {
+                for row in KEYPAD {
+                    for key in row {
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new(format!("{key:X}")).monospace().strong());
+                            let bindings = &mut keymap.bindings[key as usize];
+                            let mut removed = None;
+                            for (index, host) in bindings.iter().enumerate() {
+                                if ui
+                                    .small_button(host.name())
+                                    .on_hover_text("Click to remove")
+                                    .clicked()
+                                {
+                                    removed = Some(index);
+                                }
+                            }
+                            if let Some(index) = removed {
+                                bindings.remove(index);
+                                changed = true;
+                            }
+                            if ui
+                                .selectable_label(self.capturing == Some(key), "+")
+                                .clicked()
+                            {
+                                self.capturing = Some(key);
+                            }
+                        });
+                    }
+                    ui.end_row();
+                }
+            });
+
+        ui.separator();
+        if ui.button("Reset to defaults").clicked() {
+            *keymap = Keymap::default();
+            changed = true;
+        }
+        if changed {
+            self.error = keymaps
+                .save()
+                .err()
+                .map(|e| format!("Could not save keymap: {e}"));
+        }
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+    }
+}
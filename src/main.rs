@@ -1,69 +1,674 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use eframe::egui;
-use egui::{Color32, Key, Pos2, Rect, RichText, Sense, Stroke, Vec2};
-use rand::prelude::*;
-use std::cmp;
+#[cfg(feature = "audio")]
+mod audio;
+mod file_browser;
+mod keymap;
+mod memory_view;
+mod recent;
+mod registers;
 
-const FONT_START_ADDRESS: u16 = 0x0;
-static FONT_SET: [u8; 80] = [
-    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-    0x20, 0x60, 0x20, 0x20, 0x70, // 1
-    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-];
-const FIRST_INSTRUCTION_ADDRESS: u16 = 0x200;
-const DISPLAY_HEIGHT: usize = 32;
-const DISPLAY_WIDTH: usize = 64;
-const PIXEL_ERASE_CYCLE_DELAY: u8 = 12;
+use std::collections::BTreeMap;
+
+use eframe::egui;
+use egui::{Color32, Key, Modifiers, Pos2, Rect, RichText, Sense, Stroke, Vec2};
+use file_browser::FileBrowser;
+use keymap::{KeymapEditor, Keymaps};
+use memory_view::MemoryView;
+use quip8_core::{
+    image, octo, random_seed, Address, AudioSettings, AudioSink, Beeper, Chip8, ErrorPolicy, Movie,
+    MovieSession, NullSink, Opcode, QuirkProfile, Quirks, RewindBuffer, Rng, RngAlgorithm,
+    Scheduler, Speed, TraceFormat, Tracer, WatchKind, Watchpoint, Waveform,
+    FIRST_INSTRUCTION_ADDRESS, MEMORY_SIZE, XO_CHIP_MEMORY_SIZE,
+};
+use recent::RecentRoms;
+use registers::{Field, RegisterEditor};
 
 const PRIMARY_COLOR: Color32 = Color32::from_rgb(2, 238, 179);
-//const COMPLEMENTARY_COLOR: Color32 = Color32::from_rgb(238, 2, 61);
-//const ANALAGOUS1_COLOR: Color32 = Color32::from_rgb(2, 238, 61);
+const COMPLEMENTARY_COLOR: Color32 = Color32::from_rgb(238, 2, 61);
+const ANALAGOUS1_COLOR: Color32 = Color32::from_rgb(2, 238, 61);
 const ANALAGOUS2_COLOR: Color32 = Color32::from_rgb(2, 179, 238);
+const BACKGROUND_COLOR: Color32 = Color32::from_rgb(5, 10, 5);
+
+/// Display colors indexed by pixel color: background, first plane only,
+/// second plane only, both planes.
+const DEFAULT_PALETTE: [Color32; 4] = [
+    BACKGROUND_COLOR,
+    PRIMARY_COLOR,
+    COMPLEMENTARY_COLOR,
+    ANALAGOUS1_COLOR,
+];
+
+/// What a ROM gets loaded into on open and reset.
+#[derive(Clone, Copy)]
+struct MachineConfig {
+    quirks: Quirks,
+    memory_size: usize,
+    rng: RngAlgorithm,
+    /// Seeds `RAND` so runs repeat exactly. A fresh random seed is used for
+    /// each load when unset.
+    seed: Option<u64>,
+}
+
+impl Default for MachineConfig {
+    fn default() -> Self {
+        let mut config = Self {
+            quirks: Quirks::default(),
+            memory_size: MEMORY_SIZE,
+            rng: RngAlgorithm::default(),
+            seed: None,
+        };
+        config.set_profile(QuirkProfile::default());
+        config
+    }
+}
+
+impl MachineConfig {
+    fn set_profile(&mut self, profile: QuirkProfile) {
+        self.quirks = profile.quirks();
+        self.memory_size = if profile == QuirkProfile::XoChip {
+            XO_CHIP_MEMORY_SIZE
+        } else {
+            MEMORY_SIZE
+        };
+    }
+}
 
-#[derive(Default)]
 struct Quip8App {
     chip8: Option<Chip8>,
+    loaded_rom_path: Option<std::path::PathBuf>,
+    /// Label names from the loaded Octo source; empty for binary ROMs.
+    labels: BTreeMap<Address, String>,
+    scheduler: Scheduler,
+    config: MachineConfig,
+    /// The seed field in the Quirks menu, parsed into `config.seed`.
+    seed_text: String,
+    /// Applied to the running machine, like the quirks.
+    error_policy: ErrorPolicy,
+    palette: [Color32; 4],
+    /// Size of a display pixel in saved screenshots, in image pixels.
+    screenshot_scale: usize,
+    /// Last outcome worth telling the user about, shown in the status bar.
+    status: Option<String>,
+    new_watchpoint: WatchpointForm,
+    memory_view: MemoryView,
+    registers: RegisterEditor,
+    trace_settings: TraceSettings,
+    /// Where the running trace is being written.
+    trace_path: Option<std::path::PathBuf>,
+    /// The file of the movie being recorded or played.
+    movie_path: Option<std::path::PathBuf>,
+    file_browser: FileBrowser,
+    recent: RecentRoms,
+    keymaps: Keymaps,
+    keymap_editor: KeymapEditor,
+}
+
+/// Contents of the "add watchpoint" fields in the debugger panel.
+struct WatchpointForm {
+    start: String,
+    end: String,
+    kind: WatchKind,
+}
+
+impl Default for WatchpointForm {
+    fn default() -> Self {
+        Self {
+            start: String::new(),
+            end: String::new(),
+            kind: WatchKind::Write,
+        }
+    }
+}
+
+impl WatchpointForm {
+    /// The watchpoint described by the fields. An empty end watches the start
+    /// address alone.
+    fn parse(&self) -> Option<Watchpoint> {
+        let parse_hex = |s: &str| {
+            let s = s.trim();
+            Address::from_str_radix(s.strip_prefix("0x").unwrap_or(s), 16).ok()
+        };
+        let start = parse_hex(&self.start)?;
+        let end = if self.end.trim().is_empty() {
+            start
+        } else {
+            parse_hex(&self.end)?
+        };
+        Some(Watchpoint {
+            start: start.min(end),
+            end: start.max(end),
+            kind: self.kind,
+            enabled: true,
+        })
+    }
+}
+
+/// Trace options, from the command line or the Trace menu.
+#[derive(Clone, Default)]
+struct TraceSettings {
+    format: TraceFormat,
+    /// Hex address range to trace, e.g. `200-2FF`; empty traces everything.
+    range: String,
+    /// Stop after this many instructions; 0 for no limit.
+    limit: u64,
+}
+
+impl TraceSettings {
+    fn start(&self, path: &std::path::Path) -> Result<Tracer, String> {
+        let range = match self.range.trim() {
+            "" => None,
+            range => Some(
+                quip8_core::trace::parse_address_range(range)
+                    .ok_or_else(|| format!("invalid trace range '{range}'"))?,
+            ),
+        };
+        let mut tracer = Tracer::create(path, self.format)
+            .map_err(|e| format!("Could not trace to {}: {e}", path.display()))?;
+        tracer.range = range;
+        tracer.limit = (self.limit > 0).then_some(self.limit);
+        Ok(tracer)
+    }
+}
+
+/// Traces started from the menu go next to the ROM, e.g. `pong.trace`.
+fn trace_path(rom_path: &std::path::Path, format: TraceFormat) -> std::path::PathBuf {
+    rom_path.with_extension(match format {
+        TraceFormat::Text => "trace",
+        TraceFormat::JsonLines => "trace.jsonl",
+    })
+}
+
+/// Movies recorded from the menu go next to the ROM, e.g. `pong.movie`.
+fn movie_path(rom_path: &std::path::Path) -> std::path::PathBuf {
+    rom_path.with_extension("movie")
+}
+
+/// Screenshots go next to the ROM, numbered so none is overwritten, e.g.
+/// `pong-1.png`.
+fn screenshot_path(rom_path: &std::path::Path) -> std::path::PathBuf {
+    let stem = rom_path.file_stem().unwrap_or_default().to_string_lossy();
+    (1..)
+        .map(|n| rom_path.with_file_name(format!("{stem}-{n}.png")))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+/// Where a movie is up to, for the menu and status bar.
+fn movie_progress(movie: &MovieSession) -> String {
+    let frames = movie.movie.frames.len();
+    match (movie.is_playing(), movie.finished()) {
+        (false, _) => format!("Recording movie: {frames} frames"),
+        (true, false) => format!("Playing movie: frame {} of {frames}", movie.frame()),
+        (true, true) => format!("Movie finished after {frames} frames"),
+    }
+}
+
+/// Hotkeys for the numbered save state slots: the key loads, Shift+key saves.
+const SLOT_KEYS: [Key; 9] = [
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+];
+
+enum SlotAction {
+    Save(usize),
+    Load(usize),
+}
+
+enum Screenshot {
+    Png,
+    Text,
+}
+
+enum MovieAction {
+    Record,
+    Play(std::path::PathBuf),
+    Stop,
+}
+
+/// Where settings that outlive a session are kept: `$XDG_CONFIG_HOME/quip-8`,
+/// `~/.config/quip-8` or `%APPDATA%\quip-8`.
+fn config_dir() -> Option<std::path::PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(std::path::PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".config"))
+        })
+        .or_else(|| std::env::var_os("APPDATA").map(std::path::PathBuf::from))?;
+    Some(base.join("quip-8"))
+}
+
+/// Save states live next to the ROM, e.g. `pong.ch8` slot 3 is `pong.state3`.
+fn state_path(rom_path: &std::path::Path, slot: usize) -> std::path::PathBuf {
+    rom_path.with_extension(format!("state{slot}"))
+}
+
+fn open_audio_sink() -> Box<dyn AudioSink> {
+    #[cfg(feature = "audio")]
+    match audio::CpalSink::open() {
+        Ok(sink) => return Box::new(sink),
+        Err(e) => eprintln!("Audio disabled: {e}"),
+    }
+    Box::new(NullSink)
+}
+
+/// `label` or `label+offset` for the nearest label at or before `address`,
+/// or nothing when there are no labels.
+fn label_near(labels: &BTreeMap<Address, String>, address: Address) -> String {
+    match labels.range(..=address).next_back() {
+        Some((&start, label)) if start == address => label.clone(),
+        Some((&start, label)) => format!("{label}+{}", address - start),
+        None => String::new(),
+    }
+}
+
+/// Reads a ROM, compiling it first if it's Octo source (`.8o`). Returns the
+/// ROM and the source's label table.
+fn read_rom(rom_path: &std::path::Path) -> Result<(Vec<u8>, BTreeMap<Address, String>), String> {
+    if rom_path.extension().is_some_and(|e| e == "8o") {
+        let program = octo::compile_file(rom_path).map_err(|e| e.to_string())?;
+        Ok((program.rom, program.labels))
+    } else {
+        let rom = std::fs::read(rom_path).map_err(|e| format!("{}: {e}", rom_path.display()))?;
+        Ok((rom, BTreeMap::new()))
+    }
+}
+
+/// Loads a ROM with [`read_rom`]. Returns the machine and the source's label
+/// table.
+fn load_rom(
+    rom_path: &std::path::Path,
+    config: MachineConfig,
+) -> Result<(Chip8, BTreeMap<Address, String>), String> {
+    let (rom, labels) = read_rom(rom_path)?;
+    let capacity = config.memory_size - FIRST_INSTRUCTION_ADDRESS as usize;
+    if rom.len() > capacity {
+        return Err(format!(
+            "{}: ROM is {} bytes but only {capacity} fit in memory",
+            rom_path.display(),
+            rom.len()
+        ));
+    }
+    let mut chip8 = Chip8::with_memory_size(&rom, config.memory_size);
+    chip8.quirks = config.quirks;
+    chip8.rng = Rng::new(config.rng, config.seed.unwrap_or_else(random_seed));
+    Ok((chip8, labels))
 }
 
 impl Quip8App {
     #[allow(unused_variables)]
-    fn new(cc: &eframe::CreationContext<'_>, initial_rom: Option<std::path::PathBuf>) -> Self {
+    fn new(cc: &eframe::CreationContext<'_>, args: Args) -> Self {
         // egui customizations go here
-        Self {
-            chip8: initial_rom.map_or(None, |r| Some(Chip8::new(r))),
-            ..Self::default()
+        let mut scheduler = Scheduler::default();
+        scheduler.beeper = Some(Beeper::new(open_audio_sink(), AudioSettings::default()));
+        scheduler.rewind = Some(RewindBuffer::default());
+        let (mut chip8, labels, mut status) =
+            match args.rom.as_deref().map(|r| load_rom(r, args.config)) {
+                Some(Ok((chip8, labels))) => (Some(chip8), labels, None),
+                Some(Err(e)) => (None, BTreeMap::new(), Some(e)),
+                None => (None, BTreeMap::new(), None),
+            };
+        let keymaps = Keymaps::load().unwrap_or_else(|e| {
+            status = Some(format!("Using the default keymap: {e}"));
+            Keymaps::default()
+        });
+        let mut recent = RecentRoms::load();
+        if let (Some(_), Some(rom)) = (&chip8, &args.rom) {
+            if let Err(e) = recent.add(rom) {
+                status = Some(format!("Could not save recent ROMs: {e}"));
+            }
+        }
+        let mut trace_path = None;
+        if let (Some(chip8), Some(path)) = (chip8.as_mut(), args.trace) {
+            match args.trace_settings.start(&path) {
+                Ok(tracer) => {
+                    chip8.trace = Some(tracer);
+                    status = Some(format!("Tracing to {}", path.display()));
+                    trace_path = Some(path);
+                }
+                Err(e) => status = Some(e),
+            }
         }
+        let mut app = Self {
+            chip8,
+            loaded_rom_path: args.rom,
+            labels,
+            scheduler,
+            config: args.config,
+            seed_text: args
+                .config
+                .seed
+                .map(|seed| seed.to_string())
+                .unwrap_or_default(),
+            error_policy: args.error_policy,
+            palette: DEFAULT_PALETTE,
+            screenshot_scale: 8,
+            status,
+            new_watchpoint: WatchpointForm::default(),
+            memory_view: MemoryView::default(),
+            registers: RegisterEditor::default(),
+            trace_settings: args.trace_settings,
+            trace_path,
+            movie_path: None,
+            file_browser: FileBrowser::default(),
+            recent,
+            keymaps,
+            keymap_editor: KeymapEditor::default(),
+        };
+        if let Some(path) = args.play {
+            app.run_movie_action(MovieAction::Play(path));
+        }
+        app
+    }
+
+    /// Replaces the running machine with `rom_path`, loaded paused with the
+    /// current configuration. On failure the current machine keeps running.
+    fn open_rom(&mut self, rom_path: &std::path::Path) {
+        let (chip8, labels) = match load_rom(rom_path, self.config) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                self.status = Some(e);
+                // Drop recent entries for files that have gone away. The load
+                // error is the more useful message, so a failure here isn't
+                // reported.
+                if !rom_path.exists() {
+                    let _ = self.recent.remove(rom_path);
+                }
+                return;
+            }
+        };
+        // Breakpoints, the trace, rewind history and any movie belonged to
+        // the old ROM.
+        let stopped_movie = self.stop_movie();
+        self.chip8 = Some(chip8);
+        self.labels = labels;
+        self.loaded_rom_path = Some(rom_path.to_owned());
+        self.trace_path = None;
+        self.memory_view.clear_history();
+        if let Some(rewind) = self.scheduler.rewind.as_mut() {
+            rewind.clear();
+        }
+        let loaded = match self.recent.add(rom_path) {
+            Ok(()) => format!("Loaded {}", rom_path.display()),
+            Err(e) => format!(
+                "Loaded {} (could not save recent ROMs: {e})",
+                rom_path.display()
+            ),
+        };
+        self.status = Some(match stopped_movie {
+            Some(stopped) => format!("{stopped}. {loaded}"),
+            None => loaded,
+        });
+    }
+
+    /// Restarts the loaded ROM recording a movie with the current settings or
+    /// playing one with the settings it was recorded with, or stops the
+    /// running movie.
+    fn run_movie_action(&mut self, action: MovieAction) {
+        let Some(rom_path) = self.loaded_rom_path.clone() else {
+            return;
+        };
+        let (play, path) = match action {
+            MovieAction::Record => (false, movie_path(&rom_path)),
+            MovieAction::Play(path) => (true, path),
+            MovieAction::Stop => {
+                if let Some(stopped) = self.stop_movie() {
+                    self.status = Some(stopped);
+                }
+                return;
+            }
+        };
+        let result = read_rom(&rom_path).and_then(|(rom, labels)| {
+            let movie = if play {
+                let text = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("{}: {e}", path.display()))?;
+                Movie::parse(&text).map_err(|e| format!("{}: {e}", path.display()))?
+            } else {
+                let Speed::InstructionsPerSecond(ips) = self.scheduler.speed else {
+                    return Err("Movies can't be recorded at unlimited speed".to_owned());
+                };
+                Movie::new(
+                    &rom,
+                    self.config.quirks,
+                    self.config.memory_size,
+                    ips,
+                    self.error_policy,
+                    self.config.rng,
+                    self.config.seed.unwrap_or_else(random_seed),
+                )
+            };
+            let chip8 = movie
+                .machine(&rom)
+                .map_err(|e| format!("{}: {e}", path.display()))?;
+            Ok((chip8, labels, movie))
+        });
+        let (mut loaded, labels, movie) = match result {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                self.status = Some(e);
+                return;
+            }
+        };
+        self.stop_movie();
+        if let Some(chip8) = self.chip8.as_mut() {
+            loaded.debugger = std::mem::take(&mut chip8.debugger);
+            loaded.trace = chip8.trace.take();
+        }
+        loaded.paused = false;
+        self.chip8 = Some(loaded);
+        self.labels = labels;
+        self.memory_view.clear_history();
+        if let Some(rewind) = self.scheduler.rewind.as_mut() {
+            rewind.clear();
+        }
+        // The menus, which are locked while the movie runs, show its settings.
+        self.config.quirks = movie.quirks;
+        self.config.memory_size = movie.memory_size;
+        self.error_policy = movie.error_policy;
+        self.status = Some(if play {
+            format!("Playing {}", path.display())
+        } else {
+            format!("Recording to {}", path.display())
+        });
+        self.movie_path = Some(path);
+        self.scheduler.start_movie(if play {
+            MovieSession::play(movie)
+        } else {
+            MovieSession::record(movie)
+        });
+    }
+
+    /// Ends the running movie, saving it if it was being recorded. Returns
+    /// what happened, or nothing when no movie was running.
+    fn stop_movie(&mut self) -> Option<String> {
+        let path = self.movie_path.take();
+        let session = self.scheduler.movie.take()?;
+        let frames = session.frame();
+        let Some(path) = path else {
+            return Some(if session.is_playing() {
+                "Stopped playing movie".to_owned()
+            } else {
+                format!("Discarded {frames} recorded frames: no file to save them to")
+            });
+        };
+        if session.is_playing() {
+            return Some(format!("Stopped playing {}", path.display()));
+        }
+        Some(match std::fs::write(&path, session.movie.to_text()) {
+            Ok(()) => format!("Saved {frames} frames to {}", path.display()),
+            Err(e) => format!("Could not save movie to {}: {e}", path.display()),
+        })
+    }
+
+    /// Saves the display as a PNG in the current palette, or copies it to the
+    /// clipboard as block characters.
+    fn take_screenshot(&mut self, ctx: &egui::Context, kind: Screenshot) {
+        let (Some(chip8), Some(rom_path)) = (self.chip8.as_ref(), self.loaded_rom_path.as_deref())
+        else {
+            return;
+        };
+        self.status = Some(match kind {
+            Screenshot::Png => {
+                let path = screenshot_path(rom_path);
+                let palette = self.palette.map(|color| [color.r(), color.g(), color.b()]);
+                let result = std::fs::File::create(&path)
+                    .map(std::io::BufWriter::new)
+                    .and_then(|mut out| {
+                        image::write_png(
+                            &mut out,
+                            &chip8.display,
+                            &palette,
+                            self.screenshot_scale,
+                        )?;
+                        std::io::Write::flush(&mut out)
+                    });
+                match result {
+                    Ok(()) => format!("Saved screenshot to {}", path.display()),
+                    Err(e) => format!("Could not save screenshot to {}: {e}", path.display()),
+                }
+            }
+            Screenshot::Text => {
+                ctx.output().copied_text = image::blocks(&chip8.display);
+                "Copied the screen to the clipboard".to_owned()
+            }
+        });
+    }
+
+    fn run_slot_action(&mut self, action: SlotAction) {
+        let (Some(chip8), Some(rom_path)) = (self.chip8.as_mut(), self.loaded_rom_path.as_deref())
+        else {
+            return;
+        };
+        self.status = Some(match action {
+            SlotAction::Save(slot) => {
+                match std::fs::write(state_path(rom_path, slot), chip8.save_state()) {
+                    Ok(()) => format!("Saved state to slot {slot}"),
+                    Err(e) => format!("Could not save slot {slot}: {e}"),
+                }
+            }
+            // The movie couldn't reproduce a run that jumped to another state.
+            SlotAction::Load(_) if self.scheduler.movie.is_some() => {
+                "Stop the movie before loading a state".to_owned()
+            }
+            SlotAction::Load(slot) => {
+                let result = std::fs::read(state_path(rom_path, slot))
+                    .map_err(|e| e.to_string())
+                    .and_then(|state| chip8.load_state(&state).map_err(|e| e.to_string()));
+                match result {
+                    Ok(()) => {
+                        self.config = MachineConfig {
+                            quirks: chip8.quirks,
+                            memory_size: chip8.memory.len(),
+                            ..self.config
+                        };
+                        self.memory_view.clear_history();
+                        format!("Loaded state from slot {slot}")
+                    }
+                    Err(e) => format!("Could not load slot {slot}: {e}"),
+                }
+            }
+        });
     }
 }
 
 impl eframe::App for Quip8App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         let mut requested_run_cycles: Option<u32> = None;
+        let mut slot_action = None;
+        let mut screenshot = None;
+        let mut movie_action = None;
+        let mut open_path = None;
+        // Anything that changes how the machine runs would make the movie
+        // desync, so those controls are locked while it's active.
+        let movie_active = self.scheduler.movie.is_some();
+        // Rewinds while the toolbar button or Backspace is held.
+        let mut rewinding = false;
 
         egui::TopBottomPanel::top("top").show(ctx, |ui| {
             use egui::menu;
             menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
-                    if ui.button("Open").clicked() {
-                        // …
+                    if ui.button("Open…  (Ctrl+O)").clicked() {
+                        let dir = self.loaded_rom_path.as_deref().and_then(|p| p.parent());
+                        self.file_browser.open_in(dir);
+                        ui.close_menu();
                     }
+                    ui.add_enabled_ui(!self.recent.paths().is_empty(), |ui| {
+                        ui.menu_button("Open recent", |ui| {
+                            for path in self.recent.paths() {
+                                if ui.button(path.display().to_string()).clicked() {
+                                    open_path = Some(path.clone());
+                                    ui.close_menu();
+                                }
+                            }
+                            ui.separator();
+                            if ui.button("Clear").clicked() {
+                                if let Err(e) = self.recent.clear() {
+                                    self.status = Some(format!("Could not save recent ROMs: {e}"));
+                                }
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                    if ui.button("Keymap…").clicked() {
+                        self.keymap_editor.open = true;
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    let rom_path = self.loaded_rom_path.as_deref();
+                    ui.add_enabled_ui(rom_path.is_some(), |ui| {
+                        ui.menu_button("Save state", |ui| {
+                            for slot in 1..=SLOT_KEYS.len() {
+                                if ui.button(format!("Slot {slot}  (Shift+F{slot})")).clicked() {
+                                    slot_action = Some(SlotAction::Save(slot));
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                        ui.menu_button("Load state", |ui| {
+                            for slot in 1..=SLOT_KEYS.len() {
+                                let exists = rom_path.is_some_and(|p| state_path(p, slot).exists());
+                                if ui
+                                    .add_enabled(
+                                        exists,
+                                        egui::Button::new(format!("Slot {slot}  (F{slot})")),
+                                    )
+                                    .clicked()
+                                {
+                                    slot_action = Some(SlotAction::Load(slot));
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                        ui.menu_button("Screenshot", |ui| {
+                            if ui.button("Save PNG  (F12)").clicked() {
+                                screenshot = Some(Screenshot::Png);
+                                ui.close_menu();
+                            }
+                            ui.add(
+                                egui::DragValue::new(&mut self.screenshot_scale)
+                                    .clamp_range(1..=32)
+                                    .prefix("Scale: ")
+                                    .suffix("\u{00D7}"),
+                            );
+                            if ui.button("Copy as text  (Shift+F12)").clicked() {
+                                screenshot = Some(Screenshot::Text);
+                                ui.close_menu();
+                            }
+                        });
+                    });
                 });
                 ui.separator();
-                if let Some(chip8) = self.chip8.as_mut() {
+                if let (Some(chip8), Some(rom_path)) =
+                    (self.chip8.as_mut(), self.loaded_rom_path.as_deref())
+                {
                     ui.add_enabled_ui(!chip8.paused, |ui| {
                         if ui.button("Pause").clicked() {
                             chip8.paused = true;
@@ -73,227 +678,630 @@ impl eframe::App for Quip8App {
                         if ui.button("Run").clicked() {
                             chip8.paused = false;
                         }
-                        if ui.button("Step").clicked() {
-                            requested_run_cycles = Some(1);
+                        ui.add_enabled_ui(!movie_active, |ui| {
+                            if ui.button("Step").clicked() {
+                                requested_run_cycles = Some(1);
+                            }
+                            if ui.button("Step 5").clicked() {
+                                requested_run_cycles = Some(5);
+                            }
+                        });
+                    });
+                    if ui.button("Reset").clicked() {
+                        if movie_active {
+                            movie_action = Some(MovieAction::Stop);
                         }
-                        if ui.button("Step 5").clicked() {
-                            requested_run_cycles = Some(5);
+                        // Octo source is recompiled, picking up any edits.
+                        match load_rom(rom_path, self.config) {
+                            Ok((mut loaded, labels)) => {
+                                loaded.debugger = std::mem::take(&mut chip8.debugger);
+                                loaded.trace = chip8.trace.take();
+                                *chip8 = loaded;
+                                self.labels = labels;
+                                self.memory_view.clear_history();
+                            }
+                            Err(e) => self.status = Some(e),
+                        }
+                    }
+                    if self.scheduler.rewind.is_some() {
+                        rewinding = ui
+                            .add_enabled(!movie_active, egui::Button::new("\u{23EA} Rewind"))
+                            .on_hover_text("Hold (or hold Backspace) to run backwards")
+                            .is_pointer_button_down_on();
+                    }
+                    ui.toggle_value(&mut chip8.display.deflicker, "Deflicker");
+                    ui.toggle_value(&mut self.memory_view.open, "Memory");
+                }
+                ui.separator();
+                ui.menu_button("Quirks", |ui| {
+                    ui.set_enabled(!movie_active);
+                    let matching = QuirkProfile::matching(&self.config.quirks);
+                    for profile in QuirkProfile::ALL {
+                        if ui
+                            .radio(matching == Some(profile), profile.name())
+                            .clicked()
+                        {
+                            self.config.set_profile(profile);
+                        }
+                    }
+                    ui.separator();
+                    let quirks = &mut self.config.quirks;
+                    ui.checkbox(&mut quirks.shift_uses_vy, "Shifts use VY");
+                    ui.checkbox(
+                        &mut quirks.load_store_increments_i,
+                        "Load/store increments I",
+                    );
+                    ui.checkbox(&mut quirks.jump_uses_vx, "BNNN jumps to NNN + VX");
+                    ui.checkbox(&mut quirks.logic_resets_vf, "Logic ops reset VF");
+                    ui.checkbox(&mut quirks.wrap_sprites, "Wrap sprites");
+                    ui.checkbox(&mut quirks.display_wait, "Wait for vblank on draw");
+                    ui.separator();
+                    let mut xo_chip_memory = self.config.memory_size == XO_CHIP_MEMORY_SIZE;
+                    if ui
+                        .checkbox(&mut xo_chip_memory, "64 KiB memory (on reset)")
+                        .changed()
+                    {
+                        self.config.memory_size = if xo_chip_memory {
+                            XO_CHIP_MEMORY_SIZE
+                        } else {
+                            MEMORY_SIZE
+                        };
+                    }
+                    ui.separator();
+                    ui.label("Random numbers (on reset)");
+                    for algorithm in RngAlgorithm::ALL {
+                        ui.radio_value(&mut self.config.rng, algorithm, algorithm.name());
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Seed");
+                        let response = ui.add(
+                            egui::TextEdit::singleline(&mut self.seed_text)
+                                .hint_text("random")
+                                .desired_width(160.0),
+                        );
+                        if response.changed() {
+                            self.config.seed = self.seed_text.trim().parse().ok();
+                        }
+                    });
+                    if self.config.seed.is_none() && !self.seed_text.trim().is_empty() {
+                        ui.colored_label(
+                            ui.visuals().error_fg_color,
+                            "Not a number; each reset picks a random seed",
+                        );
+                    }
+                });
+                ui.menu_button("On error", |ui| {
+                    ui.set_enabled(!movie_active);
+                    for policy in ErrorPolicy::ALL {
+                        let hint = match policy {
+                            ErrorPolicy::Halt => "Pause on the faulting instruction and report it",
+                            ErrorPolicy::Ignore => "Skip the faulting instruction",
+                            ErrorPolicy::Wrap => "Wrap addresses and the stack pointer around",
+                        };
+                        ui.radio_value(&mut self.error_policy, policy, policy.name())
+                            .on_hover_text(hint);
+                    }
+                });
+                if let Some(chip8) = self.chip8.as_mut() {
+                    chip8.quirks = self.config.quirks;
+                    chip8.error_policy = self.error_policy;
+                }
+                ui.menu_button("Speed", |ui| {
+                    ui.set_enabled(!movie_active);
+                    let speed = &mut self.scheduler.speed;
+                    for ips in [500, 700, 1000, 2000] {
+                        ui.radio_value(
+                            speed,
+                            Speed::InstructionsPerSecond(ips),
+                            format!("{ips} IPS"),
+                        );
+                    }
+                    ui.radio_value(speed, Speed::Unlimited, "Unlimited");
+                    ui.separator();
+                    if let Speed::InstructionsPerSecond(ips) = speed {
+                        ui.add(
+                            egui::DragValue::new(ips)
+                                .clamp_range(1..=1_000_000)
+                                .suffix(" IPS"),
+                        );
+                    }
+                });
+                ui.menu_button("Rewind", |ui| {
+                    let mut enabled = self.scheduler.rewind.is_some();
+                    if ui.checkbox(&mut enabled, "Record rewind history").changed() {
+                        self.scheduler.rewind = enabled.then(RewindBuffer::default);
+                    }
+                    if let Some(rewind) = self.scheduler.rewind.as_mut() {
+                        let mut budget_mib = rewind.budget() / (1024 * 1024);
+                        if ui
+                            .add(
+                                egui::DragValue::new(&mut budget_mib)
+                                    .clamp_range(1..=1024)
+                                    .prefix("Memory budget: ")
+                                    .suffix(" MiB"),
+                            )
+                            .changed()
+                        {
+                            rewind.set_budget(budget_mib * 1024 * 1024);
+                        }
+                        ui.add(
+                            egui::DragValue::new(&mut rewind.interval)
+                                .clamp_range(1..=60)
+                                .prefix("Snapshot every ")
+                                .suffix(" frames"),
+                        );
+                        ui.label(format!(
+                            "{} snapshots, {:.1} MiB",
+                            rewind.len(),
+                            rewind.memory_used() as f32 / (1024.0 * 1024.0)
+                        ));
+                        if ui.button("Clear history").clicked() {
+                            rewind.clear();
+                        }
+                    }
+                });
+                if let (Some(chip8), Some(rom_path)) =
+                    (self.chip8.as_mut(), self.loaded_rom_path.as_deref())
+                {
+                    ui.menu_button("Trace", |ui| {
+                        if let Some(trace) = chip8.trace.as_mut() {
+                            let path = self.trace_path.as_deref().unwrap_or(rom_path);
+                            ui.label(format!("Tracing to {}", path.display()));
+                            ui.label(format!(
+                                "{} instructions, {} lines written",
+                                trace.cycles(),
+                                trace.lines()
+                            ));
+                            if let Some(e) = trace.error() {
+                                ui.colored_label(COMPLEMENTARY_COLOR, format!("Stopped: {e}"));
+                            } else if trace.finished() {
+                                ui.label("Stopped at the cycle limit");
+                            }
+                            if ui.button("Stop trace").clicked() {
+                                let result = trace.flush();
+                                chip8.trace = None;
+                                self.status = Some(match result {
+                                    Ok(()) => format!("Trace saved to {}", path.display()),
+                                    Err(e) => format!("Could not save trace: {e}"),
+                                });
+                                self.trace_path = None;
+                                ui.close_menu();
+                            }
+                            return;
+                        }
+                        let settings = &mut self.trace_settings;
+                        ui.radio_value(&mut settings.format, TraceFormat::Text, "Text");
+                        ui.radio_value(&mut settings.format, TraceFormat::JsonLines, "JSON lines");
+                        ui.horizontal(|ui| {
+                            ui.label("Addresses");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut settings.range)
+                                    .hint_text("all, or 200-2FF")
+                                    .desired_width(80.0),
+                            );
+                        });
+                        ui.add(
+                            egui::DragValue::new(&mut settings.limit)
+                                .prefix("Stop after ")
+                                .suffix(" instructions (0: never)"),
+                        );
+                        if ui.button("Start trace").clicked() {
+                            let path = trace_path(rom_path, settings.format);
+                            match settings.start(&path) {
+                                Ok(tracer) => {
+                                    chip8.trace = Some(tracer);
+                                    self.status = Some(format!("Tracing to {}", path.display()));
+                                    self.trace_path = Some(path);
+                                }
+                                Err(e) => self.status = Some(e),
+                            }
+                            ui.close_menu();
                         }
                     });
+                }
+                if let Some(rom_path) = self.loaded_rom_path.as_deref() {
+                    ui.menu_button("Movie", |ui| {
+                        if let Some(movie) = self.scheduler.movie.as_ref() {
+                            ui.label(movie_progress(movie));
+                            if let Some(desync) = movie.desync() {
+                                ui.colored_label(COMPLEMENTARY_COLOR, format!("Movie {desync}"));
+                            }
+                            let stop = if movie.is_playing() {
+                                "Stop playing"
+                            } else {
+                                "Stop and save"
+                            };
+                            if ui.button(stop).clicked() {
+                                movie_action = Some(MovieAction::Stop);
+                                ui.close_menu();
+                            }
+                            return;
+                        }
+                        let path = movie_path(rom_path);
+                        if ui
+                            .button("Record from reset")
+                            .on_hover_text(format!(
+                                "Reset and record input to {} until stopped",
+                                path.display()
+                            ))
+                            .clicked()
+                        {
+                            movie_action = Some(MovieAction::Record);
+                            ui.close_menu();
+                        }
+                        if ui
+                            .add_enabled(path.exists(), egui::Button::new("Play from reset"))
+                            .on_hover_text(format!("Reset and play {}", path.display()))
+                            .clicked()
+                        {
+                            movie_action = Some(MovieAction::Play(path));
+                            ui.close_menu();
+                        }
+                    });
+                }
+                ui.menu_button("Palette", |ui| {
+                    for (color, name) in self.palette.iter_mut().zip([
+                        "Background",
+                        "Plane 1",
+                        "Plane 2",
+                        "Both planes",
+                    ]) {
+                        ui.horizontal(|ui| {
+                            ui.color_edit_button_srgba(color);
+                            ui.label(name);
+                        });
+                    }
                     if ui.button("Reset").clicked() {
-                        *chip8 = Chip8::new(chip8.loaded_rom_path.clone());
+                        self.palette = DEFAULT_PALETTE;
                     }
-                    ui.toggle_value(&mut chip8.deflicker, "Deflicker");
+                });
+                if let Some(beeper) = self.scheduler.beeper.as_mut() {
+                    ui.menu_button("Audio", |ui| {
+                        let settings = &mut beeper.settings;
+                        ui.checkbox(&mut settings.muted, "Mute");
+                        ui.add(egui::Slider::new(&mut settings.volume, 0.0..=1.0).text("Volume"));
+                        ui.add(
+                            egui::Slider::new(&mut settings.frequency, 110.0..=1760.0)
+                                .logarithmic(true)
+                                .suffix(" Hz")
+                                .text("Frequency"),
+                        );
+                        ui.separator();
+                        for (waveform, name) in [
+                            (Waveform::Square, "Square"),
+                            (Waveform::Triangle, "Triangle"),
+                            (Waveform::Sawtooth, "Sawtooth"),
+                            (Waveform::Sine, "Sine"),
+                        ] {
+                            ui.radio_value(&mut settings.waveform, waveform, name);
+                        }
+                    });
                 }
             });
         });
 
-        if self.chip8.is_none() {
-            return;
+        if self.keymap_editor.open {
+            let rom_path = self.loaded_rom_path.as_deref();
+            self.keymap_editor.show(ctx, &mut self.keymaps, rom_path);
+        }
+        if let Some(path) = self.file_browser.show(ctx) {
+            open_path = Some(path);
+        }
+        let dropped = ctx
+            .input()
+            .raw
+            .dropped_files
+            .iter()
+            .find_map(|file| file.path.clone());
+        if dropped.is_some() {
+            open_path = dropped;
+        }
+        if !ctx.input().raw.hovered_files.is_empty() {
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("drop"),
+            ));
+            let screen = ctx.input().screen_rect();
+            painter.rect_filled(screen, 0.0, Color32::from_black_alpha(192));
+            painter.text(
+                screen.center(),
+                egui::Align2::CENTER_CENTER,
+                "Drop to load ROM",
+                egui::TextStyle::Heading.resolve(&ctx.style()),
+                PRIMARY_COLOR,
+            );
         }
 
-        let chip8 = &mut self.chip8.as_mut().unwrap();
-
-        let previous_keys = chip8.keys;
-        chip8.keys = ctx.input().keys_down.contains(&Key::Num1) as u16
-            | (ctx.input().keys_down.contains(&Key::Num2) as u16) << 1
-            | (ctx.input().keys_down.contains(&Key::Num3) as u16) << 2
-            | (ctx.input().keys_down.contains(&Key::Num4) as u16) << 3
-            | (ctx.input().keys_down.contains(&Key::Q) as u16) << 4
-            | (ctx.input().keys_down.contains(&Key::W) as u16) << 5
-            | (ctx.input().keys_down.contains(&Key::E) as u16) << 6
-            | (ctx.input().keys_down.contains(&Key::R) as u16) << 7
-            | (ctx.input().keys_down.contains(&Key::A) as u16) << 8
-            | (ctx.input().keys_down.contains(&Key::S) as u16) << 9
-            | (ctx.input().keys_down.contains(&Key::D) as u16) << 10
-            | (ctx.input().keys_down.contains(&Key::F) as u16) << 11
-            | (ctx.input().keys_down.contains(&Key::Z) as u16) << 12
-            | (ctx.input().keys_down.contains(&Key::X) as u16) << 13
-            | (ctx.input().keys_down.contains(&Key::C) as u16) << 14
-            | (ctx.input().keys_down.contains(&Key::V) as u16) << 15;
+        {
+            let mut input = ctx.input_mut();
+            if input.consume_key(Modifiers::COMMAND, Key::O) {
+                let dir = self.loaded_rom_path.as_deref().and_then(|p| p.parent());
+                self.file_browser.open_in(dir);
+            }
+            if input.consume_key(Modifiers::SHIFT, Key::F12) {
+                screenshot = Some(Screenshot::Text);
+            } else if input.consume_key(Modifiers::NONE, Key::F12) {
+                screenshot = Some(Screenshot::Png);
+            }
+            for (i, key) in SLOT_KEYS.into_iter().enumerate() {
+                if input.consume_key(Modifiers::SHIFT, key) {
+                    slot_action = Some(SlotAction::Save(i + 1));
+                } else if input.consume_key(Modifiers::NONE, key) {
+                    slot_action = Some(SlotAction::Load(i + 1));
+                }
+            }
+        }
+        if let Some(action) = slot_action {
+            self.run_slot_action(action);
+        }
+        if let Some(kind) = screenshot {
+            self.take_screenshot(ctx, kind);
+        }
+        if let Some(action) = movie_action {
+            self.run_movie_action(action);
+        }
+        if let Some(path) = open_path {
+            self.open_rom(&path);
+        }
 
-        chip8.key_pressed = None;
-        let keys_pressed_since = (previous_keys & chip8.keys) ^ chip8.keys;
-        if keys_pressed_since != 0 {
-            // This could be bit twiddled out
-            let mut b = 0;
-            chip8.key_pressed = loop {
-                if keys_pressed_since & (1 << b) != 0 {
-                    break Some(b);
+        if self.status.is_some() || self.scheduler.movie.is_some() {
+            egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+                if let Some(status) = &self.status {
+                    ui.label(status);
                 }
-                b += 1;
-                if b > 15 {
-                    break None;
+                if let Some(movie) = self.scheduler.movie.as_ref() {
+                    match movie.desync() {
+                        Some(desync) => {
+                            ui.colored_label(COMPLEMENTARY_COLOR, format!("Movie {desync}"))
+                        }
+                        None => ui.label(movie_progress(movie)),
+                    };
                 }
-            };
+            });
+        }
+
+        if self.chip8.is_none() {
+            return;
+        }
+
+        let chip8 = self.chip8.as_mut().unwrap();
+
+        // A playing movie supplies the keys until it runs out.
+        let replaying = self
+            .scheduler
+            .movie
+            .as_ref()
+            .is_some_and(|movie| movie.is_playing() && !movie.finished());
+        if !replaying {
+            let keymap = self.keymaps.for_rom(self.loaded_rom_path.as_deref());
+            chip8.set_keys(keymap.keys_down(&ctx.input()));
         }
 
         egui::TopBottomPanel::bottom("bottom").show(ctx, |ui| {
+            let registers = &mut self.registers;
             ui.horizontal(|ui| {
-                egui::Grid::new("registers").show(ui, |ui| {
+                let mut cell = |ui: &mut egui::Ui, name: String, field: Field| {
                     ui.horizontal(|ui| {
-                        ui.label(RichText::new("PC").monospace());
-                        ui.label(
-                            RichText::new(format!("{:03X}", chip8.pc))
-                                .monospace()
-                                .color(ANALAGOUS2_COLOR),
-                        );
+                        ui.label(RichText::new(name).monospace());
+                        registers.value(ui, chip8, field);
                         ui.separator();
                     });
-
-                    for v in chip8.v[0..8].iter().enumerate() {
-                        ui.horizontal(|ui| {
-                            ui.label(RichText::new(format!("V{:X}", v.0)).monospace());
-                            ui.label(
-                                RichText::new(format!("{:02X}", v.1))
-                                    .monospace()
-                                    .color(ANALAGOUS2_COLOR),
-                            );
-                            ui.separator();
-                        });
+                };
+                egui::Grid::new("registers").show(ui, |ui| {
+                    cell(ui, "PC".to_owned(), Field::Pc);
+                    for x in 0..8 {
+                        cell(ui, format!("V{x:X}"), Field::V(x));
                     }
-                    ui.horizontal(|ui| {
-                        ui.label(RichText::new("DELAY").monospace());
-                        ui.label(
-                            RichText::new(format!("{:02X}", chip8.delay_timer))
-                                .monospace()
-                                .color(ANALAGOUS2_COLOR),
-                        );
-                        ui.separator();
-                    });
+                    cell(ui, "DELAY".to_owned(), Field::Delay);
                     ui.end_row();
 
-                    ui.horizontal(|ui| {
-                        ui.label(RichText::new("I ").monospace());
-                        ui.label(
-                            RichText::new(format!("{:03X}", chip8.i))
-                                .monospace()
-                                .color(ANALAGOUS2_COLOR),
-                        );
-                        ui.separator();
-                    });
-
-                    for v in chip8.v[8..].iter().enumerate() {
-                        ui.horizontal(|ui| {
-                            ui.label(RichText::new(format!("V{:X}", v.0 + 8)).monospace());
-                            ui.label(
-                                RichText::new(format!("{:02X}", v.1))
-                                    .monospace()
-                                    .color(ANALAGOUS2_COLOR),
-                            );
-                            ui.separator();
-                        });
+                    cell(ui, "I ".to_owned(), Field::I);
+                    for x in 8..16 {
+                        cell(ui, format!("V{x:X}"), Field::V(x));
                     }
-                    ui.horizontal(|ui| {
-                        ui.label(RichText::new("SOUND").monospace());
-                        ui.label(
-                            RichText::new(format!("{:02X}", chip8.sound_timer))
-                                .monospace()
-                                .color(ANALAGOUS2_COLOR),
-                        );
-                        ui.separator();
-                        ui.end_row();
-                    });
+                    cell(ui, "SOUND".to_owned(), Field::Sound);
                     ui.end_row();
                 });
+                ui.vertical(|ui| {
+                    ui.radio_value(&mut registers.decimal, false, "Hex");
+                    ui.radio_value(&mut registers.decimal, true, "Dec");
+                });
             });
         });
 
         egui::SidePanel::right("memory").show(ctx, |ui| {
             ui.heading("Keys");
-            let key_color = |key: u32| {
-                if chip8.keys & (1 << key) != 0 {
-                    ANALAGOUS2_COLOR
-                } else {
-                    Color32::GRAY
-                }
-            };
+            let keymap = self.keymaps.for_rom(self.loaded_rom_path.as_deref());
             egui::Grid::new("keys").show(ui, |ui| {
-                ui.colored_label(key_color(0), "1");
-                ui.colored_label(key_color(1), "2");
-                ui.colored_label(key_color(2), "3");
-                ui.colored_label(key_color(3), "4");
-                ui.end_row();
-
-                ui.colored_label(key_color(4), "Q");
-                ui.colored_label(key_color(5), "W");
-                ui.colored_label(key_color(6), "E");
-                ui.colored_label(key_color(7), "R");
-                ui.end_row();
-
-                ui.colored_label(key_color(8), "A");
-                ui.colored_label(key_color(9), "S");
-                ui.colored_label(key_color(10), "D");
-                ui.colored_label(key_color(11), "F");
-                ui.end_row();
-
-                ui.colored_label(key_color(12), "Z");
-                ui.colored_label(key_color(13), "X");
-                ui.colored_label(key_color(14), "C");
-                ui.colored_label(key_color(15), "V");
-                ui.end_row();
+                for key in 0..16u8 {
+                    let color = if chip8.keys & (1 << key) != 0 {
+                        ANALAGOUS2_COLOR
+                    } else {
+                        Color32::GRAY
+                    };
+                    let bindings = keymap.bindings(key);
+                    let names: Vec<&str> = bindings.iter().map(|host| host.name()).collect();
+                    let label = bindings.first().map_or("-", |host| host.name());
+                    ui.colored_label(color, label)
+                        .on_hover_text(format!("Key {key:X}: {}", names.join(", ")));
+                    if key % 4 == 3 {
+                        ui.end_row();
+                    }
+                }
             });
             ui.separator();
             ui.heading("Instructions");
+            let mut toggled_breakpoint = None;
+            let mut pc = chip8.pc.wrapping_sub(6);
             for i in 0..12 {
-                let pc = (chip8.pc as i64 + (i as i16 - 3) as i64 * 2) as usize;
-                let raw_opcode = (chip8.memory[pc] as u16) << 8 | chip8.memory[pc + 1] as u16;
-                let opcode_maybe = Opcode::decode(raw_opcode);
-                if let Ok(opcode) = opcode_maybe {
-                    let instruction_label = ui.label(
-                        RichText::new(format!(
-                            "{}{:03X} {:5} {:3} {:2} {:2}",
-                            if pc == chip8.pc as usize {
-                                "\u{2794}"
-                            } else {
-                                " "
-                            },
-                            pc,
-                            opcode.mnemonic(),
-                            opcode
-                                .operand(0)
-                                .map_or("".to_owned(), |o| format!("{:3X}", o)),
-                            opcode
-                                .operand(1)
-                                .map_or("".to_owned(), |o| format!("{:2X}", o)),
-                            opcode
-                                .operand(2)
-                                .map_or("".to_owned(), |o| format!("{:2X}", o)),
-                        ))
-                        .color(ANALAGOUS2_COLOR)
-                        .monospace(),
-                    );
+                let raw_opcode = chip8.read_opcode(pc);
+                let opcode_maybe = Opcode::decode_at(&chip8.memory, pc);
+                // Lines before pc step by whole words so they end up on it.
+                let size = match &opcode_maybe {
+                    Ok(opcode) if i >= 3 => opcode.size(),
+                    _ => 2,
+                };
+                let gutter = match chip8.debugger.breakpoint(pc) {
+                    Some(b) if b.enabled => RichText::new("\u{25CF}").color(COMPLEMENTARY_COLOR),
+                    Some(_) => RichText::new("\u{25CB}").color(COMPLEMENTARY_COLOR),
+                    None => RichText::new("\u{00B7}").color(Color32::DARK_GRAY),
+                };
+                if let Some(label) = self.labels.get(&pc) {
+                    ui.label(RichText::new(format!("  {label}:")).monospace().weak());
+                }
+                ui.horizontal(|ui| {
+                    if ui
+                        .add(egui::Label::new(gutter.monospace()).sense(Sense::click()))
+                        .on_hover_text("Toggle breakpoint")
+                        .clicked()
+                    {
+                        toggled_breakpoint = Some(pc);
+                    }
+                    if let Ok(opcode) = opcode_maybe {
+                        let target_label = match opcode {
+                            Opcode::SYS(target)
+                            | Opcode::JUMP(target)
+                            | Opcode::CALL(target)
+                            | Opcode::LOADI(target)
+                            | Opcode::LOADIL(target)
+                            | Opcode::JUMPI(target) => self.labels.get(&target),
+                            _ => None,
+                        };
+                        let instruction_label = ui.label(
+                            RichText::new(format!(
+                                "{}{:03X} {:5} {:3} {:2} {:2}",
+                                if pc == chip8.pc { "\u{2794}" } else { " " },
+                                pc,
+                                opcode.mnemonic(),
+                                match target_label {
+                                    Some(label) => label.clone(),
+                                    None => opcode
+                                        .operand(0)
+                                        .map_or("".to_owned(), |o| format!("{:3X}", o)),
+                                },
+                                opcode
+                                    .operand(1)
+                                    .map_or("".to_owned(), |o| format!("{:2X}", o)),
+                                opcode
+                                    .operand(2)
+                                    .map_or("".to_owned(), |o| format!("{:2X}", o)),
+                            ))
+                            .color(ANALAGOUS2_COLOR)
+                            .monospace(),
+                        );
 
-                    if chip8.paused {
-                        instruction_label.on_hover_text(opcode.describe(&chip8));
+                        if chip8.paused {
+                            instruction_label.on_hover_text(opcode.describe(chip8));
+                        }
+                    } else {
+                        ui.label(
+                            RichText::new(format!(
+                                "{}{:03X} UNKNOWN {:#06X}",
+                                if pc == chip8.pc { "\u{2794}" } else { " " },
+                                pc,
+                                raw_opcode
+                            ))
+                            .monospace(),
+                        );
                     }
-                } else {
-                    ui.label(
-                        RichText::new(format!(
-                            "{}{:03X} UNKNOWN {:#06X}",
-                            if pc == chip8.pc as usize {
-                                "\u{2794}"
-                            } else {
-                                " "
-                            },
-                            pc,
-                            raw_opcode
-                        ))
-                        .monospace(),
-                    );
-                }
+                });
+                pc = pc.wrapping_add(size);
+            }
+            if let Some(address) = toggled_breakpoint {
+                chip8.debugger.toggle_breakpoint(address);
             }
             ui.separator();
             ui.heading("Stack");
-            for i in 0..chip8.sp {
-                ui.label(
-                    RichText::new(format!("{:03X}", chip8.stack[(chip8.sp - i) as usize]))
-                        .color(ANALAGOUS2_COLOR)
-                        .monospace(),
-                );
+            for index in (0..chip8.sp as usize).rev() {
+                let label = label_near(&self.labels, chip8.stack[index]);
+                self.registers.stack_entry(ui, chip8, index, &label);
+            }
+            ui.separator();
+            ui.heading("Breakpoints");
+            if chip8.debugger.breakpoints().is_empty() {
+                ui.weak("Click the dot beside an instruction to add one");
+            }
+            for breakpoint in chip8.debugger.breakpoints().to_vec() {
+                ui.horizontal(|ui| {
+                    let mut enabled = breakpoint.enabled;
+                    if ui
+                        .checkbox(
+                            &mut enabled,
+                            RichText::new(format!(
+                                "{:03X} {}",
+                                breakpoint.address,
+                                label_near(&self.labels, breakpoint.address)
+                            ))
+                            .monospace(),
+                        )
+                        .changed()
+                    {
+                        chip8
+                            .debugger
+                            .set_breakpoint_enabled(breakpoint.address, enabled);
+                    }
+                    if ui.small_button("Delete").clicked() {
+                        chip8.debugger.remove_breakpoint(breakpoint.address);
+                    }
+                });
+            }
+            ui.separator();
+            ui.heading("Watchpoints");
+            let mut deleted_watchpoint = None;
+            for (index, watchpoint) in chip8.debugger.watchpoints.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    let range = if watchpoint.start == watchpoint.end {
+                        format!("{:03X}", watchpoint.start)
+                    } else {
+                        format!("{:03X}-{:03X}", watchpoint.start, watchpoint.end)
+                    };
+                    ui.checkbox(
+                        &mut watchpoint.enabled,
+                        RichText::new(format!("{range} {}", watchpoint.kind.name())).monospace(),
+                    );
+                    if ui.small_button("Delete").clicked() {
+                        deleted_watchpoint = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = deleted_watchpoint {
+                chip8.debugger.watchpoints.remove(index);
             }
+            let form = &mut self.new_watchpoint;
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut form.start)
+                        .hint_text("start")
+                        .desired_width(40.0),
+                );
+                ui.add(
+                    egui::TextEdit::singleline(&mut form.end)
+                        .hint_text("end")
+                        .desired_width(40.0),
+                );
+                egui::ComboBox::from_id_source("watch kind")
+                    .selected_text(form.kind.name())
+                    .show_ui(ui, |ui| {
+                        for kind in [WatchKind::Read, WatchKind::Write, WatchKind::ReadWrite] {
+                            ui.selectable_value(&mut form.kind, kind, kind.name());
+                        }
+                    });
+                let watchpoint = form.parse();
+                if ui
+                    .add_enabled(watchpoint.is_some(), egui::Button::new("Add"))
+                    .clicked()
+                {
+                    chip8.debugger.watchpoints.extend(watchpoint);
+                    *form = WatchpointForm {
+                        kind: form.kind,
+                        ..WatchpointForm::default()
+                    };
+                }
+            });
         });
 
+        self.memory_view.show(ctx, chip8);
+
         egui::CentralPanel::default().show(ctx, |ui| {
             let painter_size = ui.available_size();
             let (res, painter) = ui.allocate_painter(painter_size, Sense::hover());
@@ -304,55 +1312,49 @@ impl eframe::App for Quip8App {
                 Vec2::new(res.rect.width(), res.rect.width() / 2.0)
             };
             let display_rect = Rect::from_center_size(res.rect.center(), display_size);
-            painter.rect_filled(display_rect, 0.0, Color32::from_rgb(5, 10, 5));
-            for (row, row_data) in chip8.gfx.iter().enumerate() {
-                for col in 0..DISPLAY_WIDTH {
-                    if (row_data
-                        | (if chip8.last_gfx_ttl > 0 {
-                            chip8.last_gfx[row]
-                        } else {
-                            0
-                        }))
-                        & (1 << (DISPLAY_WIDTH - col - 1))
-                        > 0
-                    {
+            painter.rect_filled(display_rect, 0.0, self.palette[0]);
+            let (display_width, display_height) = (chip8.display.width(), chip8.display.height());
+            for row in 0..display_height {
+                for col in 0..display_width {
+                    let color = chip8.display.color(col, row);
+                    if color != 0 {
                         painter.rect_filled(
                             Rect {
                                 min: Pos2 {
                                     x: display_rect.left()
-                                        + display_rect.width() / DISPLAY_WIDTH as f32 * col as f32,
+                                        + display_rect.width() / display_width as f32 * col as f32,
                                     y: display_rect.top()
-                                        + display_rect.height() / DISPLAY_HEIGHT as f32
+                                        + display_rect.height() / display_height as f32
                                             * row as f32,
                                 },
                                 max: Pos2 {
                                     x: display_rect.left()
-                                        + display_rect.width() / DISPLAY_WIDTH as f32
+                                        + display_rect.width() / display_width as f32
                                             * (col + 1) as f32,
                                     y: display_rect.top()
-                                        + display_rect.height() / DISPLAY_HEIGHT as f32
+                                        + display_rect.height() / display_height as f32
                                             * (row + 1) as f32,
                                 },
                             },
                             0.0,
-                            PRIMARY_COLOR,
+                            self.palette[color as usize],
                         );
                     } else {
                         painter.rect_stroke(
                             Rect {
                                 min: Pos2 {
                                     x: display_rect.left()
-                                        + display_rect.width() / DISPLAY_WIDTH as f32 * col as f32,
+                                        + display_rect.width() / display_width as f32 * col as f32,
                                     y: display_rect.top()
-                                        + display_rect.height() / DISPLAY_HEIGHT as f32
+                                        + display_rect.height() / display_height as f32
                                             * row as f32,
                                 },
                                 max: Pos2 {
                                     x: display_rect.left()
-                                        + display_rect.width() / DISPLAY_WIDTH as f32
+                                        + display_rect.width() / display_width as f32
                                             * (col + 1) as f32,
                                     y: display_rect.top()
-                                        + display_rect.height() / DISPLAY_HEIGHT as f32
+                                        + display_rect.height() / display_height as f32
                                             * (row + 1) as f32,
                                 },
                             },
@@ -364,764 +1366,125 @@ impl eframe::App for Quip8App {
             }
         });
 
-        if !chip8.paused {
-            chip8.emulate_cycle();
+        rewinding |= !ctx.wants_keyboard_input() && ctx.input().key_down(Key::Backspace);
+        rewinding &= self.scheduler.movie.is_none();
+        let wall_time = std::time::Duration::from_secs_f32(ctx.input().unstable_dt);
+        if rewinding && self.scheduler.rewind.is_some() {
+            self.scheduler.rewind(chip8, wall_time);
             ctx.request_repaint();
+        } else {
+            self.scheduler.advance(chip8, wall_time);
+            if !chip8.paused {
+                ctx.request_repaint();
+            }
         }
 
         if let Some(cycles) = requested_run_cycles {
             for _ in 0..cycles {
-                chip8.emulate_cycle();
+                if chip8.emulate_cycle().is_err() || chip8.debugger.breaks_at(chip8.pc) {
+                    break;
+                }
             }
             ctx.request_repaint();
         }
-    }
-}
-
-type Address = u16;
-type RegisterAddress = u8;
-type Literal = u8;
-
-enum Opcode {
-    // 0x0NNN - Call
-    // Calls machine code routine (RCA 1802 for COSMAC VIP) at address NNN. Not necessary for most ROMs.
-    SYS(Address),
-
-    // 0x00E0 - Display
-    // C Pseudo: disp_clear()
-    // Clears the screen.
-    CLR,
-
-    // 0x00EE - Flow
-    // C Pseudo: return;
-    // Returns from a subroutine.
-    RTS,
-
-    // 0x1NNN - Flow
-    // C Pseudo: goto NNN;
-    // Jumps to address NNN.
-    JUMP(Address),
-
-    // 0x2NNN - Flow
-    // C Pseudo: *(0xNNN)()
-    // Calls subroutine at NNN.
-    CALL(Address),
-
-    // 0x3XNN - Cond
-    // C Pseudo: if (Vx == NN)
-    // Skips the next instruction if VX equals NN (usually the next instruction
-    // is a jump to skip a code block).
-    SKE((RegisterAddress, Literal)),
-
-    // 0x4XNN - Cond
-    // C Pseudo: if (Vx != NN)
-    // Skips the next instruction if VX does not equal NN (usually the next
-    // instruction is a jump to skip a code block).
-    SKNE((RegisterAddress, Literal)),
-
-    // 0x5XY0 - Cond
-    // C Pseudo: if (Vx == Vy)
-    // Skips the next instruction if VX equals VY (usually the next instruction
-    // is a jump to skip a code block).
-    SKRE((RegisterAddress, RegisterAddress)),
-
-    // 0x6XNN - Const
-    // C Pseudo: Vx = NN
-    // Sets VX to NN.
-    LOAD((RegisterAddress, Literal)),
-
-    // 0x7XNN - Const
-    // C Pseudo: Vx += NN
-    // Adds NN to VX (carry flag is not changed).
-    ADD((RegisterAddress, Literal)),
-
-    // 0x8XY0 - Assig
-    // C Pseudo: Vx = Vy
-    // Sets VX to the value of VY.
-    MOVE((RegisterAddress, RegisterAddress)),
-
-    // 0x8XY1 - BitOp
-    // C Pseudo: Vx |= Vy
-    // Sets VX to VX or VY. (bitwise OR operation)
-    OR((RegisterAddress, RegisterAddress)),
-
-    // 0x8XY2 - BitOp
-    // C Pseudo: Vx &= Vy
-    // Sets VX to VX and VY. (bitwise AND operation)
-    AND((RegisterAddress, RegisterAddress)),
-
-    // 0x8XY3[a] - BitOp
-    // C Pseudo: Vx ^= Vy
-    // Sets VX to VX xor VY.
-    XOR((RegisterAddress, RegisterAddress)),
-
-    // 0x8XY4 - Math
-    // C Pseudo: Vx += Vy
-    // Adds VY to VX. VF is set to 1 when there's a carry, and to 0 when there
-    // is not.
-    ADDR((RegisterAddress, RegisterAddress)),
-
-    // 0x8XY5 - Math
-    // C Pseudo: Vx -= Vy
-    // VY is subtracted from VX. VF is set to 0 when there's a borrow, and 1
-    // when there is not.
-    SUB((RegisterAddress, RegisterAddress)),
-
-    // 0x8XY6 - BitOp
-    // C Pseudo: Vx >>= 1
-    // Stores the least significant bit of VX in VF and then shifts VX to the
-    // right by 1.
-    // Better Name?
-    SHR((RegisterAddress, RegisterAddress)),
-
-    // 0x8XY7 - Math
-    // C Pseudo: Vx = Vy - Vx
-    // Sets VX to VY minus VX. VF is set to 0 when there's a borrow, and 1 when
-    //  there is not.
-    // Better Name?
-    RSUB((RegisterAddress, RegisterAddress)),
-
-    // 0x8XYE - BitOp
-    // C Pseudo: Vx <<= 1
-    // Stores the most significant bit of VX in VF and then shifts VX to the
-    // left by 1.
-    // Better Name?
-    SHL((RegisterAddress, RegisterAddress)),
-
-    // 0x9XY0 - Cond
-    // C Pseudo: if (Vx != Vy)
-    // Skips the next instruction if VX does not equal VY. (Usually the next
-    // instruction is a jump to skip a code block);
-    SKRNE((RegisterAddress, RegisterAddress)),
-
-    // 0xANNN - MEM
-    // C Pseudo: I = NNN
-    // Sets I to the address NNN.
-    LOADI(Address),
-
-    // 0xBNNN - Flow
-    // C Pseudo: PC = V0 + NNN
-    // Jumps to the address NNN plus V0.
-    JUMPI(Address),
-
-    // 0xCXNN - Rand
-    // C Pseudo: Vx = rand() & NN
-    // Sets VX to the result of a bitwise and operation on a random number
-    // (Typically: 0 to 255) and NN.
-    RAND((RegisterAddress, Literal)),
-
-    // 0xDXYN - Display
-    // C Pseudo: draw(Vx, Vy, N)
-    // Draws a sprite at coordinate (VX, VY) that has a width of 8 pixels and
-    // a height of N pixels. Each row of 8 pixels is read as bit-coded starting
-    // from memory location I; I value does not change after the execution of
-    // this instruction. As described above, VF is set to 1 if any screen pixels
-    // are flipped from set to unset when the sprite is drawn, and to 0 if that
-    // does not happen.
-    DRAW((RegisterAddress, RegisterAddress, Literal)),
-
-    // 0xEX9E - KeyOp
-    // C Pseudo: if (key() == Vx)
-    // Skips the next instruction if the key stored in VX is pressed (usually
-    // the next instruction is a jump to skip a code block).
-    SKPR(RegisterAddress),
-
-    // 0xEXA1 - KeyOp
-    // C Pseudo: if (key() != Vx)
-    // Skips the next instruction if the key stored in VX is not pressed
-    // (usually the next instruction is a jump to skip a code block).
-    SKUP(RegisterAddress),
-
-    // 0xFX07 - Timer
-    // C Pseudo: Vx = get_delay()
-    // Sets VX to the value of the delay timer.
-    MOVED(RegisterAddress),
-
-    // 0xFX0A - KeyOp
-    // C Pseudo: Vx = get_key()
-    // A key press is awaited, and then stored in VX (blocking operation, all
-    // instruction halted until next key event).
-    KEYD(RegisterAddress),
-
-    // 0xFX15 - Timer
-    // C Pseudo: delay_timer(Vx)
-    // Sets the delay timer to VX.
-    LOADD(RegisterAddress),
-
-    // 0xFX18 - Sound
-    // C Pseudo: sound_timer(Vx)
-    // Sets the sound timer to VX.
-    LOADS(RegisterAddress),
-
-    // 0xFX1E - MEM
-    // C Pseudo: I += Vx
-    // Adds VX to I. VF is not affected.[c]
-    ADDI(RegisterAddress),
-
-    // 0xFX29 - MEM
-    // C Pseudo: I = sprite_addr[Vx]
-    // Sets I to the location of the sprite for the character in VX. Characters
-    //  0-F (in hexadecimal) are represented by a 4x5 font.
-    LDSPR(RegisterAddress),
-
-    // 0xFX33 - BCD
-    // C Pseudo: set_BCD(Vx) *(I+0) = BCD(3); *(I+1) = BCD(2); *(I+2) = BCD(1);
-    // Stores the binary-coded decimal representation of VX, with the hundreds
-    // digit in memory at location in I, the tens digit at location I+1, and
-    // the ones digit at location I+2.
-    BCD(RegisterAddress),
-
-    // 0xFX55 - MEM
-    // C Pseudo: reg_dump(Vx, &I)
-    // Stores from V0 to VX (including VX) in memory, starting at address I.
-    // The offset from I is increased by 1 for each value written, but I itself
-    // is left unmodified.[d]
-    STORE(RegisterAddress),
-
-    // 0xFX65 - MEM
-    // C Pseudo: reg_load(Vx, &I)
-    // Fills from V0 to VX (including VX) with values from memory, starting at
-    // address I. The offset from I is increased by 1 for each value read, but
-    // I itself is left unmodified.[d]
-    READ(RegisterAddress),
-}
-
-struct UnknownOpcode;
-
-impl Opcode {
-    pub fn decode(raw_opcode: u16) -> Result<Opcode, UnknownOpcode> {
-        match raw_opcode & 0xF000 {
-            0x0000 => match raw_opcode {
-                0x00E0 => Ok(Opcode::CLR),
-                0x00EE => Ok(Opcode::RTS),
-                _ => Ok(Opcode::SYS((raw_opcode & 0x0FFF) as Address)),
-            },
-            0x1000 => Ok(Opcode::JUMP((raw_opcode & 0x0FFF) as Address)),
-            0x2000 => Ok(Opcode::CALL((raw_opcode & 0x0FFF) as Address)),
-            0x3000 => Ok(Opcode::SKE((
-                ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                (raw_opcode & 0x00FF) as Literal,
-            ))),
-            0x4000 => Ok(Opcode::SKNE((
-                ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                (raw_opcode & 0x00FF) as Literal,
-            ))),
-            0x5000 => Ok(Opcode::SKRE((
-                ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
-            ))),
-            0x6000 => Ok(Opcode::LOAD((
-                ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                (raw_opcode & 0x00FF) as Literal,
-            ))),
-            0x7000 => Ok(Opcode::ADD((
-                ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                (raw_opcode & 0x00FF) as Literal,
-            ))),
-            0x8000 => match raw_opcode & 0x000F {
-                0x0000 => Ok(Opcode::MOVE((
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
-                ))),
-                0x0001 => Ok(Opcode::OR((
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
-                ))),
-                0x0002 => Ok(Opcode::AND((
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
-                ))),
-                0x0003 => Ok(Opcode::XOR((
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
-                ))),
-                0x0004 => Ok(Opcode::ADDR((
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
-                ))),
-                0x0005 => Ok(Opcode::SUB((
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
-                ))),
-                0x0006 => Ok(Opcode::SHR((
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
-                ))),
-                0x0007 => Ok(Opcode::RSUB((
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
-                ))),
-                0x000E => Ok(Opcode::SHL((
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                    ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
-                ))),
-                _ => Err(UnknownOpcode),
-            },
-            0x9000 => Ok(Opcode::SKRNE((
-                ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
-            ))),
-            0xA000 => Ok(Opcode::LOADI((raw_opcode & 0x0FFF) as Address)),
-            0xB000 => Ok(Opcode::JUMPI((raw_opcode & 0x0FFF) as Address)),
-            0xC000 => Ok(Opcode::RAND((
-                ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                (raw_opcode & 0x00FF) as Literal,
-            ))),
-            0xD000 => Ok(Opcode::DRAW((
-                ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                ((raw_opcode & 0x00F0) >> 4) as RegisterAddress,
-                (raw_opcode & 0x000F) as Literal,
-            ))),
-            0xE000 => match raw_opcode & 0x00FF {
-                0x009E => Ok(Opcode::SKPR(
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                )),
-                0x00A1 => Ok(Opcode::SKUP(
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                )),
-                _ => Err(UnknownOpcode),
-            },
-            0xF000 => match raw_opcode & 0x00FF {
-                0x0007 => Ok(Opcode::MOVED(
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                )),
-                0x000A => Ok(Opcode::KEYD(
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                )),
-                0x0015 => Ok(Opcode::LOADD(
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                )),
-                0x0018 => Ok(Opcode::LOADS(
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                )),
-                0x001E => Ok(Opcode::ADDI(
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                )),
-                0x0029 => Ok(Opcode::LDSPR(
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                )),
-                0x0033 => Ok(Opcode::BCD(((raw_opcode & 0x0F00) >> 8) as RegisterAddress)),
-                0x0055 => Ok(Opcode::STORE(
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                )),
-                0x0065 => Ok(Opcode::READ(
-                    ((raw_opcode & 0x0F00) >> 8) as RegisterAddress,
-                )),
-                _ => Err(UnknownOpcode),
-            },
-
-            _ => Err(UnknownOpcode),
-        }
-    }
-
-    fn mnemonic(&self) -> &'static str {
-        match self {
-            Opcode::SYS(_address) => "SYS",
-            Opcode::CLR => "CLR",
-            Opcode::RTS => "RTS",
-            Opcode::JUMP(_address) => "JUMP",
-            Opcode::CALL(_address) => "CALL",
-            Opcode::SKE((_register, _literal)) => "SKE",
-            Opcode::SKNE((_register, _literal)) => "SKNE",
-            Opcode::SKRE((_register_x, _register_y)) => "SKRE",
-            Opcode::LOAD((_register, _literal)) => "LOAD",
-            Opcode::ADD((_register, _literal)) => "ADD",
-            Opcode::MOVE((_register_x, _register_y)) => "MOVE",
-            Opcode::OR((_register_x, _register_y)) => "OR",
-            Opcode::AND((_register_x, _register_y)) => "AND",
-            Opcode::XOR((_register_x, _register_y)) => "XOR",
-            Opcode::ADDR((_register_x, _register_y)) => "ADDR",
-            Opcode::SUB((_register_x, _register_y)) => "SUB",
-            Opcode::SHR((_register_x, _register_y)) => "SHR",
-            Opcode::RSUB((_register_x, _register_y)) => "RSUB",
-            Opcode::SHL((_register_x, _register_y)) => "SHL",
-            Opcode::SKRNE((_register_x, _register_y)) => "SKRNE",
-            Opcode::LOADI(_address) => "LOADI",
-            Opcode::JUMPI(_address) => "JUMPI",
-            Opcode::RAND((_register, _literal)) => "RAND",
-            Opcode::DRAW((_register_x, _register_y, _literal)) => "DRAW",
-            Opcode::SKPR(_register) => "SKPR",
-            Opcode::SKUP(_register) => "SKUP",
-            Opcode::MOVED(_register) => "MOVED",
-            Opcode::KEYD(_register) => "KEYD",
-            Opcode::LOADD(_register) => "LOADD",
-            Opcode::LOADS(_register) => "LOADS",
-            Opcode::ADDI(_register) => "ADDI",
-            Opcode::LDSPR(_register) => "LDSPR",
-            Opcode::BCD(_register) => "BCD",
-            Opcode::STORE(_register) => "STORE",
-            Opcode::READ(_register) => "READ",
-        }
-    }
-
-    fn operand(&self, i: u8) -> Option<u16> {
-        let operands = match self {
-            Opcode::SYS(address) => (Some(*address), None, None),
-            Opcode::CLR => (None, None, None),
-            Opcode::RTS => (None, None, None),
-            Opcode::JUMP(address) => (Some(*address), None, None),
-            Opcode::CALL(address) => (Some(*address), None, None),
-            Opcode::SKE((register, literal)) => (Some(*register as u16), Some(*literal), None),
-            Opcode::SKNE((register, literal)) => (Some(*register as u16), Some(*literal), None),
-            Opcode::SKRE((register_x, register_y)) => {
-                (Some(*register_x as u16), Some(*register_y), None)
-            }
-            Opcode::LOAD((register, literal)) => (Some(*register as u16), Some(*literal), None),
-            Opcode::ADD((register, literal)) => (Some(*register as u16), Some(*literal), None),
-            Opcode::MOVE((register_x, register_y)) => {
-                (Some(*register_x as u16), Some(*register_y), None)
-            }
-            Opcode::OR((register_x, register_y)) => {
-                (Some(*register_x as u16), Some(*register_y), None)
-            }
-            Opcode::AND((register_x, register_y)) => {
-                (Some(*register_x as u16), Some(*register_y), None)
-            }
-            Opcode::XOR((register_x, register_y)) => {
-                (Some(*register_x as u16), Some(*register_y), None)
-            }
-            Opcode::ADDR((register_x, register_y)) => {
-                (Some(*register_x as u16), Some(*register_y), None)
-            }
-            Opcode::SUB((register_x, register_y)) => {
-                (Some(*register_x as u16), Some(*register_y), None)
-            }
-            Opcode::SHR((register_x, register_y)) => {
-                (Some(*register_x as u16), Some(*register_y), None)
-            }
-            Opcode::RSUB((register_x, register_y)) => {
-                (Some(*register_x as u16), Some(*register_y), None)
-            }
-            Opcode::SHL((register_x, register_y)) => {
-                (Some(*register_x as u16), Some(*register_y), None)
-            }
-            Opcode::SKRNE((register_x, register_y)) => {
-                (Some(*register_x as u16), Some(*register_y), None)
-            }
-            Opcode::LOADI(address) => (Some(*address), None, None),
-            Opcode::JUMPI(address) => (Some(*address), None, None),
-            Opcode::RAND((register, literal)) => (Some(*register as u16), Some(*literal), None),
-            Opcode::DRAW((register_x, register_y, literal)) => {
-                (Some(*register_x as u16), Some(*register_y), Some(*literal))
-            }
-            Opcode::SKPR(register) => (Some(*register as u16), None, None),
-            Opcode::SKUP(register) => (Some(*register as u16), None, None),
-            Opcode::MOVED(register) => (Some(*register as u16), None, None),
-            Opcode::KEYD(register) => (Some(*register as u16), None, None),
-            Opcode::LOADD(register) => (Some(*register as u16), None, None),
-            Opcode::LOADS(register) => (Some(*register as u16), None, None),
-            Opcode::ADDI(register) => (Some(*register as u16), None, None),
-            Opcode::LDSPR(register) => (Some(*register as u16), None, None),
-            Opcode::BCD(register) => (Some(*register as u16), None, None),
-            Opcode::STORE(register) => (Some(*register as u16), None, None),
-            Opcode::READ(register) => (Some(*register as u16), None, None),
-        };
-
-        match i {
-            0 => operands.0,
-            1 => operands.1.map_or(None, |o| Some(o as u16)),
-            2 => operands.2.map_or(None, |o| Some(o as u16)),
-            _ => None,
-        }
-    }
-
-    fn describe(&self, chip8: &Chip8) -> String {
-        match self {
-            Opcode::SYS(address) => format!(
-                "Call machine code routine (RCA 1802 for COSMAC VIP) at address {address:#05X}."),
-            Opcode::CLR => format!("Clear the screen"),
-            Opcode::RTS => format!("Return from subroutine (pop the stack)"),
-            Opcode::JUMP(address) => format!("Jump to address {address:#05X}"),
-            Opcode::CALL(address) => format!("Calls subroutine at {address:#05X} (push on the stack)"),
-            Opcode::SKE((register, literal)) => format!(
-                "Skips the next instruction if V{register:X} equals {literal:#04X}"),
-            Opcode::SKNE((register, literal)) => format!(
-                "Skips the next instruction if V{register:X} does not equal {literal:#04X}"),
-            Opcode::SKRE((register_x, register_y)) => format!("Skips the next instruction if V{register_x:X} equals V{register_y:X}"),
-            Opcode::LOAD((register, literal)) => format!("Sets V{register:X} to {literal:#04X}"),
-            Opcode::ADD((register, literal)) => format!("Adds {literal:#04X} to V{register:X} (carry flag is not changed)"),
-            Opcode::MOVE((register_x, register_y)) => format!("Sets V{register_x:X} to the value of V{register_y}"),
-            Opcode::OR((register_x, register_y)) => format!("Sets V{register_x:X} to V{register_x:X} or V{register_y:X}. (bitwise OR operation"),
-            Opcode::AND((register_x, register_y)) => format!("Sets V{register_x:X} to V{register_x:X} and V{register_y:X}. (bitwise AND operation"),
-            Opcode::XOR((register_x, register_y)) => format!("Sets V{register_x:X} to V{register_x:X} xor V{register_y:X}."),
-            Opcode::ADDR((register_x, register_y)) => format!("Adds V{register_y:X} to V{register_x:X}. VF is set to 1 when there's a carry, and to 0 when there is not."),
-            Opcode::SUB((register_x, register_y)) => format!("V{register_y:X} ({}) is subtracted from V{register_x:X} ({}). VF is set to 0 when there's a borrow, and 1 when there is not.", chip8.v[*register_y as usize], chip8.v[*register_x as usize]),
-            Opcode::SHR((register_x, _register_y)) => format!("Stores the least significant bit of V{register_x:X} in VF and then shifts V{register_x:X} to the right by 1."),
-            Opcode::RSUB((register_x, register_y)) => format!("Sets V{register_x:X} to V{register_y:X} minus V{register_x:X}. VF is set to 0 when there's a borrow, and 1 when there is not."),
-            Opcode::SHL((register_x, _register_y)) => format!("Stores the most significant bit of V{register_x:X} in VF and then shifts V{register_x:X} to the left by 1"),
-            Opcode::SKRNE((register_x, register_y)) => format!("Skips the next instruction if V{register_x:X} does not equal V{register_y:X}"),
-            Opcode::LOADI(address) => format!("Sets I to the address {address:#05X}"),
-            Opcode::JUMPI(address) => format!("Jumps to the address {address:#05X} plus V0"),
-            Opcode::RAND((register, literal)) => format!("Sets V{register:X} to the result of a bitwise and operation on a random number (Typically: 0 to 255) and {literal:#04X}"),
-            Opcode::DRAW((register_x, register_y, literal)) => format!("Draws an 8x{literal} sprite at coordinate (V{register_x:X} ({}), V{register_y:X} ({})).", chip8.v[*register_x as usize], chip8.v[*register_y as usize]),
-            Opcode::SKPR(register) => format!("Skips the next instruction if the key stored in V{register:X} is pressed (usually the next instruction is a jump to skip a code block)"),
-            Opcode::SKUP(register) => format!("Skips the next instruction if the key stored in V{register:X} is not pressed"),
-            Opcode::MOVED(register) => format!("Sets V{register:X} to the value of the delay timer"),
-            Opcode::KEYD(register) => format!("A key press is awaited, and then stored in V{register:X} (blocking operation, all instruction halted until next key event)"),
-            Opcode::LOADD(register) => format!("Sets the delay timer to V{register:X}"),
-            Opcode::LOADS(register) => format!("Sets the sound timer to V{register:X}"),
-            Opcode::ADDI(register) => format!("Adds V{register:X} to I. VF is not affected."),
-            Opcode::LDSPR(register) => format!("Sets I to the location of the sprite for the character in V{register:X}. Characters 0-F (in hexadecimal) are represented by a 4x5 font."),
-            Opcode::BCD(register) => format!("Stores the binary-coded decimal representation of V{register:X}, with the hundreds digit in memory at location in I, the tens digit at location I+1, and the ones digit at location I+2."),
-            Opcode::STORE(register) => format!("Stores from V0 to V{register:X} (including V{register:X}) in memory, starting at address I"),
-            Opcode::READ(register) => format!("Fills from V0 to V{register:X} (including V{register:X}) with values from memory, starting at address I"),
+        if let Some(reason) = chip8.debugger.take_break() {
+            self.status = Some(reason.to_string());
+            ctx.request_repaint();
         }
     }
 }
 
-struct Chip8 {
-    opcode: u16,
-    memory: [u8; 4096],
-    v: [u8; 16],
-    i: u16,
-    pc: Address,
-    gfx: [u64; DISPLAY_HEIGHT],
-    delay_timer: u8,
-    sound_timer: u8,
-    stack: [Address; 16],
-    sp: u16,
-    keys: u16,
-    key_pressed: Option<u8>,
-
-    last_gfx: [u64; DISPLAY_HEIGHT],
-    last_gfx_ttl: u8,
-    deflicker: bool,
-
-    paused: bool,
-    loaded_rom_path: std::path::PathBuf,
+#[derive(Default)]
+struct Args {
+    rom: Option<std::path::PathBuf>,
+    config: MachineConfig,
+    error_policy: ErrorPolicy,
+    trace: Option<std::path::PathBuf>,
+    trace_settings: TraceSettings,
+    play: Option<std::path::PathBuf>,
 }
 
-/*impl Default for Chip8 {
-    fn default() -> Self {
-        Self::new(None)
-    }
-}*/
-
-impl Chip8 {
-    pub fn new(rom_path: std::path::PathBuf) -> Self {
-        let mut s = Self {
-            opcode: 0,
-            memory: [0; 4096],
-            v: [0; 16],
-            i: 0,
-            pc: FIRST_INSTRUCTION_ADDRESS,
-            gfx: [0; DISPLAY_HEIGHT],
-            delay_timer: 0,
-            sound_timer: 0,
-            stack: [0; 16],
-            sp: 0,
-            keys: 0,
-            key_pressed: None,
-            last_gfx: [0; DISPLAY_HEIGHT],
-            last_gfx_ttl: 0,
-            deflicker: true,
-            paused: true,
-            loaded_rom_path: rom_path,
-        };
-        s.memory[0..FONT_SET.len()].copy_from_slice(FONT_SET.as_slice());
-        let mut file_contents = std::fs::read(&s.loaded_rom_path).unwrap_or("".into());
-        file_contents.resize(4096 - 512, 0);
-        s.memory[512..].copy_from_slice(file_contents.as_slice());
-        s
-    }
-
-    fn emulate_cycle(&mut self) {
-        // fetch opcode
-        self.opcode =
-            (self.memory[self.pc as usize] as u16) << 8 | self.memory[self.pc as usize + 1] as u16;
-        self.pc += 2;
-
-        // decode opcode
-        match Opcode::decode(self.opcode) {
-            Ok(decoded_opcode) => match decoded_opcode {
-                Opcode::SYS(_address) => {
-                    std::eprintln!("Unimplemented opcode {:#06X}", self.opcode);
-                } //Calls machine code routine (RCA 1802 for COSMAC VIP) at address NNN. Not necessary for most ROMs.
-                Opcode::CLR => {
-                    self.gfx = [0; DISPLAY_HEIGHT];
-                } // 	disp_clear() 	Clears the screen.
-                Opcode::RTS => {
-                    self.pc = self.stack[self.sp as usize];
-                    self.sp -= 1;
-                } //return; 	Returns from a subroutine.
-                Opcode::JUMP(address) => {
-                    self.pc = address;
-                } //goto NNN; 	Jumps to address NNN.
-                Opcode::CALL(address) => {
-                    self.sp += 1;
-                    self.stack[self.sp as usize] = self.pc;
-                    self.pc = address;
-                } //*(0xNNN)() 	Calls subroutine at NNN.
-                Opcode::SKE((register, literal)) => {
-                    if self.v[register as usize] == literal {
-                        self.pc += 2;
-                    }
-                } //if (Vx == NN) 	Skips the next instruction if VX equals NN (usually the next instruction is a jump to skip a code block).
-                Opcode::SKNE((register, literal)) => {
-                    if self.v[register as usize] != literal {
-                        self.pc += 2;
-                    }
-                } //if (Vx != NN) 	Skips the next instruction if VX does not equal NN (usually the next instruction is a jump to skip a code block).
-                Opcode::SKRE((register_x, register_y)) => {
-                    if self.v[register_x as usize] == self.v[register_y as usize] {
-                        self.pc += 2;
-                    }
-                } //if (Vx == Vy) 	Skips the next instruction if VX equals VY (usually the next instruction is a jump to skip a code block).
-                Opcode::LOAD((register, literal)) => {
-                    self.v[register as usize] = literal;
+impl Args {
+    const USAGE: &'static str = "usage: quip-8 [--quirks vip|chip48|schip|xochip] \
+        [--on-error halt|ignore|wrap] [--rng xorshift|vip] [--seed N] \
+        [--trace FILE [--trace-format text|json] [--trace-range START-END] [--trace-limit N]] \
+        [--play MOVIE] [ROM | SOURCE.8o]";
+
+    fn parse() -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--quirks" => {
+                    let profile = args.next().ok_or("--quirks needs a profile name")?;
+                    let profile = profile.parse::<QuirkProfile>().map_err(|e| e.to_string())?;
+                    parsed.config.set_profile(profile);
                 }
-                Opcode::ADD((register, literal)) => {
-                    self.v[register as usize] =
-                        (self.v[register as usize] as u16 + literal as u16) as u8;
-                } //Vx += NN 	Adds NN to VX (carry flag is not changed).
-                Opcode::MOVE((register_x, register_y)) => {
-                    self.v[register_x as usize] = self.v[register_y as usize];
-                } //Vx = Vy 	Sets VX to the value of VY.
-                Opcode::OR((register_x, register_y)) => {
-                    self.v[register_x as usize] |= self.v[register_y as usize];
-                } // Vx |= Vy 	Sets VX to VX or VY. (bitwise OR operation)
-                Opcode::AND((register_x, register_y)) => {
-                    self.v[register_x as usize] &= self.v[register_y as usize];
-                } //Vx &= Vy 	Sets VX to VX and VY. (bitwise AND operation)
-                Opcode::XOR((register_x, register_y)) => {
-                    self.v[register_x as usize] ^= self.v[register_y as usize];
-                } // Vx ^= Vy 	Sets VX to VX xor VY.
-                Opcode::ADDR((register_x, register_y)) => {
-                    let (result, overflow) =
-                        self.v[register_x as usize].overflowing_add(self.v[register_y as usize]);
-                    self.v[register_x as usize] = result;
-                    self.v[0xF] = overflow as u8;
-                } // Vx += Vy 	Adds VY to VX. VF is set to 1 when there's a carry, and to 0 when there is not.
-                Opcode::SUB((register_x, register_y)) => {
-                    let (result, overflow) =
-                        self.v[register_x as usize].overflowing_sub(self.v[register_y as usize]);
-                    self.v[register_x as usize] = result;
-                    self.v[0xF] = overflow as u8;
-                } // Vx -= Vy 	VY is subtracted from VX. VF is set to 0 when there's a borrow, and 1 when there is not.
-                Opcode::SHR((register_x, register_y)) => {
-                    self.v[0xF] = self.v[register_y as usize] & 1;
-                    self.v[register_x as usize] = self.v[register_y as usize] >> 1;
-                } // Vx >>= 1 	Stores the least significant bit of VX in VF and then shifts VX to the right by 1.[b]
-                Opcode::RSUB((register_x, register_y)) => {
-                    self.v[register_x as usize] =
-                        self.v[register_y as usize] - self.v[register_x as usize];
-                } // Vx = Vy - Vx 	Sets VX to VY minus VX. VF is set to 0 when there's a borrow, and 1 when there is not.
-                Opcode::SHL((register_x, register_y)) => {
-                    self.v[0xF] = self.v[register_y as usize] & 0xF0;
-                    self.v[register_x as usize] = self.v[register_y as usize] << 1;
-                } // Vx <<= 1 	Stores the most significant bit of VX in VF and then shifts VX to the left by 1.[b]
-                Opcode::SKRNE((register_x, register_y)) => {
-                    if self.v[register_x as usize] != self.v[register_y as usize] {
-                        self.pc += 2;
-                    }
-                } //if (Vx != Vy) 	Skips the next instruction if VX does not equal VY. (Usually the next instruction is a jump to skip a code block);
-                Opcode::LOADI(address) => {
-                    self.i = address;
-                } //I = NNN 	Sets I to the address NNN.
-                Opcode::JUMPI(address) => {
-                    self.pc = address + self.v[0] as u16;
-                } // PC = V0 + NNN 	Jumps to the address NNN plus V0.
-                Opcode::RAND((register, literal)) => {
-                    self.v[register as usize] = random::<u8>() & literal;
-                } //Vx = rand() & NN 	Sets VX to the result of a bitwise and operation on a random number (Typically: 0 to 255) and NN.
-                Opcode::DRAW((register_x, register_y, literal)) => {
-                    let x = self.v[register_x as usize] as usize % DISPLAY_WIDTH;
-                    let y = self.v[register_y as usize] as usize % DISPLAY_HEIGHT;
-                    let height = cmp::min(literal + y as u8, DISPLAY_HEIGHT as u8) as usize - y;
-
-                    self.last_gfx = self.gfx;
-                    let mut bit_unset = 0;
-                    for i in 0..height {
-                        let current_row = self.gfx[y + i];
-                        let current_wide_row = (current_row as u128) << DISPLAY_WIDTH;
-                        let sprite_wide_row =
-                            (self.memory[self.i as usize + i] as u128) << (128 - 8 - x);
-                        self.gfx[y + i] =
-                            ((current_wide_row ^ sprite_wide_row) >> DISPLAY_WIDTH) as u64;
-                        bit_unset |= (current_row ^ self.gfx[y + i]) & current_row;
-                    }
-                    self.v[0xF] = (bit_unset != 0) as u8;
-                    if bit_unset != 0 && self.deflicker {
-                        self.last_gfx_ttl = PIXEL_ERASE_CYCLE_DELAY;
-                    } else {
-                        self.last_gfx_ttl = 0;
-                    }
-                } //draw(Vx, Vy, N) 	Draws a sprite at coordinate (VX, VY) that has a width of 8 pixels and a height of N pixels.
-                //  Each row of 8 pixels is read as bit-coded starting from memory location I; I value does not change after the execution of this instruction.
-                //  As described above, VF is set to 1 if any screen pixels are flipped from set to unset when the sprite is drawn, and to 0 if that does not happen.
-                Opcode::SKPR(register) => {
-                    if self.keys & (1 << self.v[register as usize]) != 0 {
-                        self.pc += 2;
-                    }
-                } // if (key() == Vx) 	Skips the next instruction if the key stored in VX is pressed (usually the next instruction is a jump to skip a code block).
-                Opcode::SKUP(register) => {
-                    if self.keys & (1 << self.v[register as usize]) == 0 {
-                        self.pc += 2;
-                    }
-                } //if (key() != Vx) 	Skips the next instruction if the key stored in VX is not pressed (usually the next instruction is a jump to skip a code block).
-                Opcode::MOVED(register) => {
-                    self.v[register as usize] = self.delay_timer;
-                } //Vx = get_delay() 	Sets VX to the value of the delay timer.
-                Opcode::KEYD(register) => {
-                    if let Some(key) = self.key_pressed {
-                        self.v[register as usize] = key;
-                    } else {
-                        self.pc -= 2;
-                    }
-                } //Vx = get_key() 	A key press is awaited, and then stored in VX (blocking operation, all instruction halted until next key event).
-                Opcode::LOADD(register) => {
-                    self.delay_timer = self.v[register as usize];
-                } //delay_timer(Vx) 	Sets the delay timer to VX.
-                Opcode::LOADS(register) => {
-                    self.sound_timer = self.v[register as usize];
-                } //sound_timer(Vx) 	Sets the sound timer to VX.
-                Opcode::ADDI(register) => {
-                    self.i += self.v[register as usize] as u16;
-                } //I += Vx 	Adds VX to I. VF is not affected.[c]
-                Opcode::LDSPR(register) => {
-                    self.i = FONT_START_ADDRESS + self.v[register as usize] as u16;
-                } //I = sprite_addr[Vx] 	Sets I to the location of the sprite for the character in VX. Characters 0-F (in hexadecimal) are represented by a 4x5 font.
-                Opcode::BCD(register) => {
-                    self.memory[self.i as usize] = self.v[register as usize] / 100;
-                    self.memory[(self.i + 1) as usize] = self.v[register as usize] / 10 % 10;
-                    self.memory[(self.i + 2) as usize] = (self.v[register as usize] % 100) % 10;
-                } // set_BCD(Vx) *(I+0) = BCD(3); *(I+1) = BCD(2); *(I+2) = BCD(1);
-                // Stores the binary-coded decimal representation of VX, with the hundreds digit in memory at location in I, the tens digit at location I+1, and the ones digit at location I+2.
-                Opcode::STORE(register) => {
-                    self.memory[(self.i as usize)..(self.i + register as u16 + 1) as usize]
-                        .copy_from_slice(&self.v[0..(register + 1) as usize]);
-                } //reg_dump(Vx, &I) 	Stores from V0 to VX (including VX) in memory, starting at address I. The offset from I is increased by 1 for each value written, but I itself is left unmodified.[d]
-                Opcode::READ(register) => self.v[..(register + 1) as usize].copy_from_slice(
-                    &self.memory[self.i as usize..(self.i + register as u16 + 1) as usize],
-                ), //reg_load(Vx, &I) 	Fills from V0 to VX (including VX) with values from memory, starting at address I. The offset from I is increased by 1 for each value read, but I itself is left unmodified.[d]
-            },
-            Err(UnknownOpcode) => {
-                std::eprintln!("Unknown opcode {:#06X}", self.opcode);
+                "--on-error" => {
+                    let policy = args.next().ok_or("--on-error needs a policy name")?;
+                    parsed.error_policy =
+                        policy.parse::<ErrorPolicy>().map_err(|e| e.to_string())?;
+                }
+                "--rng" => {
+                    let algorithm = args.next().ok_or("--rng needs an algorithm name")?;
+                    parsed.config.rng = algorithm
+                        .parse::<RngAlgorithm>()
+                        .map_err(|e| e.to_string())?;
+                }
+                "--seed" => {
+                    let seed = args.next().ok_or("--seed needs a number")?;
+                    parsed.config.seed =
+                        Some(seed.parse().map_err(|_| format!("invalid seed '{seed}'"))?);
+                }
+                "--trace" => {
+                    let path = args.next().ok_or("--trace needs a file name")?;
+                    parsed.trace = Some(std::path::PathBuf::from(path));
+                }
+                "--trace-format" => {
+                    let format = args.next().ok_or("--trace-format needs a format")?;
+                    parsed.trace_settings.format =
+                        format.parse::<TraceFormat>().map_err(|e| e.to_string())?;
+                }
+                "--trace-range" => {
+                    parsed.trace_settings.range =
+                        args.next().ok_or("--trace-range needs an address range")?;
+                }
+                "--trace-limit" => {
+                    let limit = args.next().ok_or("--trace-limit needs a count")?;
+                    parsed.trace_settings.limit = limit
+                        .parse()
+                        .map_err(|_| format!("invalid trace limit '{limit}'"))?;
+                }
+                "--play" => {
+                    let path = args.next().ok_or("--play needs a file name")?;
+                    parsed.play = Some(std::path::PathBuf::from(path));
+                }
+                "-h" | "--help" => {
+                    println!("{}", Self::USAGE);
+                    std::process::exit(0);
+                }
+                _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+                _ => parsed.rom = Some(std::path::PathBuf::from(arg)),
             }
         }
-
-        // execute opcode
-
-        // update timers
-        if self.delay_timer > 0 {
-            self.delay_timer -= 1;
-        }
-        if self.sound_timer > 0 {
-            self.sound_timer -= 1;
-        }
-        if self.last_gfx_ttl > 0 {
-            self.last_gfx_ttl -= 1;
+        if parsed.play.is_some() && parsed.rom.is_none() {
+            return Err("--play needs the ROM the movie was recorded with".to_owned());
         }
+        Ok(parsed)
     }
 }
 
 fn main() {
-    let path = std::env::args().nth(1).map(std::path::PathBuf::from);
+    let args = Args::parse().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        eprintln!("{}", Args::USAGE);
+        std::process::exit(2);
+    });
 
     let options = eframe::NativeOptions::default();
     eframe::run_native(
         "QUIP-8",
         options,
-        Box::new(|cc| Box::new(Quip8App::new(cc, path))),
+        Box::new(|cc| Box::new(Quip8App::new(cc, args))),
     );
 }
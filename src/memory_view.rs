@@ -0,0 +1,216 @@
+use eframe::egui;
+use egui::{Color32, Key, RichText, Sense};
+use quip8_core::{
+    Address, Chip8, BIG_FONT_SET, BIG_FONT_START_ADDRESS, FONT_SET, FONT_START_ADDRESS,
+};
+
+use crate::{ANALAGOUS2_COLOR, COMPLEMENTARY_COLOR, PRIMARY_COLOR};
+
+const BYTES_PER_ROW: usize = 16;
+/// Most edits kept for undo.
+const UNDO_LIMIT: usize = 1024;
+
+/// A byte overwritten from the memory window, for undo.
+struct Edit {
+    address: Address,
+    old: u8,
+}
+
+/// The hex/ASCII memory window.
+#[derive(Default)]
+pub struct MemoryView {
+    pub open: bool,
+    /// Keep the row holding I in view as it changes.
+    follow_i: bool,
+    goto: String,
+    /// Row to bring into view on the next frame.
+    scroll_to: Option<Address>,
+    last_i: Option<Address>,
+    /// The byte being edited and the text typed so far.
+    editing: Option<(Address, String)>,
+    undo: Vec<Edit>,
+}
+
+impl MemoryView {
+    /// Forgets edits made to a machine that has since been replaced.
+    pub fn clear_history(&mut self) {
+        self.undo.clear();
+        self.editing = None;
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, chip8: &mut Chip8) {
+        let mut open = self.open;
+        egui::Window::new("Memory")
+            .open(&mut open)
+            .default_width(560.0)
+            .show(ctx, |ui| self.contents(ui, chip8));
+        self.open = open;
+    }
+
+    fn contents(&mut self, ui: &mut egui::Ui, chip8: &mut Chip8) {
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.goto)
+                    .hint_text("address")
+                    .desired_width(60.0),
+            );
+            let submitted = response.lost_focus() && ui.input().key_pressed(Key::Enter);
+            if ui.button("Go to").clicked() || submitted {
+                let text = self.goto.trim();
+                match Address::from_str_radix(text.strip_prefix("0x").unwrap_or(text), 16) {
+                    Ok(address) if (address as usize) < chip8.memory.len() => {
+                        self.scroll_to = Some(address);
+                        self.follow_i = false;
+                    }
+                    _ => self.goto.clear(),
+                }
+            }
+            ui.checkbox(&mut self.follow_i, "Follow I");
+            ui.separator();
+            if ui
+                .add_enabled(
+                    chip8.paused && !self.undo.is_empty(),
+                    egui::Button::new(format!("Undo ({})", self.undo.len())),
+                )
+                .clicked()
+            {
+                let edit = self.undo.pop().unwrap();
+                chip8.memory[edit.address as usize] = edit.old;
+            }
+        });
+        if !chip8.paused {
+            ui.weak("Pause to edit memory");
+            self.editing = None;
+        }
+        ui.separator();
+
+        if self.follow_i && self.last_i != Some(chip8.i) {
+            self.scroll_to = Some(chip8.i);
+        }
+        self.last_i = Some(chip8.i);
+
+        let row_height =
+            ui.text_style_height(&egui::TextStyle::Monospace) + ui.spacing().item_spacing.y;
+        let rows = chip8.memory.len() / BYTES_PER_ROW;
+        let mut scroll_area = egui::ScrollArea::vertical().auto_shrink([false; 2]);
+        if let Some(address) = self.scroll_to.take() {
+            let row = address as usize / BYTES_PER_ROW;
+            // Leave a few rows of context above the target.
+            scroll_area =
+                scroll_area.vertical_scroll_offset(row.saturating_sub(4) as f32 * row_height);
+        }
+        scroll_area.show_rows(ui, row_height, rows, |ui, row_range| {
+            for row in row_range {
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 4.0;
+                    let start = row * BYTES_PER_ROW;
+                    ui.label(RichText::new(format!("{start:04X}")).monospace().weak());
+                    for address in start..start + BYTES_PER_ROW {
+                        self.byte_cell(ui, chip8, address as Address);
+                    }
+                    let ascii: String = chip8.memory[start..start + BYTES_PER_ROW]
+                        .iter()
+                        .map(|&b| {
+                            if b.is_ascii_graphic() || b == b' ' {
+                                b as char
+                            } else {
+                                '.'
+                            }
+                        })
+                        .collect();
+                    ui.label(RichText::new(ascii).monospace().weak());
+                });
+            }
+        });
+    }
+
+    fn byte_cell(&mut self, ui: &mut egui::Ui, chip8: &mut Chip8, address: Address) {
+        let value = chip8.memory[address as usize];
+        if let Some((_, text)) = self.editing.as_mut().filter(|(a, _)| *a == address) {
+            let response = ui.add(
+                egui::TextEdit::singleline(text)
+                    .font(egui::TextStyle::Monospace)
+                    .desired_width(16.0),
+            );
+            response.request_focus();
+            text.truncate(2);
+            let (escape, enter) = {
+                let input = ui.input();
+                (
+                    input.key_pressed(Key::Escape),
+                    input.key_pressed(Key::Enter),
+                )
+            };
+            let typed = (enter || text.len() == 2).then(|| u8::from_str_radix(text, 16));
+            if escape || enter && text.is_empty() {
+                self.editing = None;
+            } else if let Some(typed) = typed {
+                self.editing = None;
+                if let Ok(new) = typed {
+                    self.write(chip8, address, new);
+                }
+                // Carry on with the next byte, like a hex editor.
+                let next = address as usize + typed.is_ok() as usize;
+                if next < chip8.memory.len() {
+                    self.editing = Some((next as Address, String::new()));
+                }
+            }
+            return;
+        }
+
+        let mut text = RichText::new(format!("{value:02X}")).monospace();
+        let stack_target = chip8.return_address();
+        let in_font =
+            |start: Address, len: usize| (start..start + len as Address).contains(&address);
+        if address == chip8.pc || address == chip8.pc.wrapping_add(1) {
+            text = text
+                .color(Color32::BLACK)
+                .background_color(ANALAGOUS2_COLOR);
+        } else if address == chip8.i {
+            text = text
+                .color(Color32::BLACK)
+                .background_color(COMPLEMENTARY_COLOR);
+        } else if Some(address) == stack_target {
+            text = text.color(Color32::BLACK).background_color(PRIMARY_COLOR);
+        } else if in_font(FONT_START_ADDRESS, FONT_SET.len())
+            || in_font(BIG_FONT_START_ADDRESS, BIG_FONT_SET.len())
+        {
+            text = text.weak();
+        }
+        let response = ui
+            .add(egui::Label::new(text).sense(Sense::click()))
+            .on_hover_text(cell_description(chip8, address));
+        if response.clicked() && chip8.paused {
+            self.editing = Some((address, String::new()));
+        }
+    }
+
+    fn write(&mut self, chip8: &mut Chip8, address: Address, value: u8) {
+        let old = std::mem::replace(&mut chip8.memory[address as usize], value);
+        if old == value {
+            return;
+        }
+        if self.undo.len() == UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.undo.push(Edit { address, old });
+    }
+}
+
+fn cell_description(chip8: &Chip8, address: Address) -> String {
+    let value = chip8.memory[address as usize];
+    let mut description = format!("{address:#05X}: {value:#04X} ({value})");
+    if address == chip8.pc {
+        description += "\npc";
+    }
+    if address == chip8.i {
+        description += "\nI";
+    }
+    if chip8.return_address() == Some(address) {
+        description += "\nreturn address";
+    }
+    if chip8.paused {
+        description += "\nClick to edit";
+    }
+    description
+}
@@ -0,0 +1,73 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Most recently opened ROMs, newest first. Kept in `recent_roms` in the
+/// config directory, one path per line.
+#[derive(Default)]
+pub struct RecentRoms {
+    paths: Vec<PathBuf>,
+}
+
+impl RecentRoms {
+    const LIMIT: usize = 10;
+
+    fn file() -> Option<PathBuf> {
+        crate::config_dir().map(|dir| dir.join("recent_roms"))
+    }
+
+    /// Reads the saved list. A missing or unreadable file is an empty list.
+    pub fn load() -> Self {
+        let text = Self::file()
+            .and_then(|file| std::fs::read_to_string(file).ok())
+            .unwrap_or_default();
+        Self {
+            paths: text
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(PathBuf::from)
+                .take(Self::LIMIT)
+                .collect(),
+        }
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Moves `path` to the front of the list and saves it.
+    pub fn add(&mut self, path: &Path) -> io::Result<()> {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+        self.paths.retain(|p| *p != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(Self::LIMIT);
+        self.save()
+    }
+
+    pub fn remove(&mut self, path: &Path) -> io::Result<()> {
+        self.paths.retain(|p| p != path);
+        self.save()
+    }
+
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.paths.clear();
+        self.save()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(file) = Self::file() else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut text = String::new();
+        for path in &self.paths {
+            // Paths that can't round-trip through a line of UTF-8 aren't kept.
+            if let Some(path) = path.to_str().filter(|p| !p.contains('\n')) {
+                text.push_str(path);
+                text.push('\n');
+            }
+        }
+        std::fs::write(file, text)
+    }
+}
@@ -0,0 +1,141 @@
+use eframe::egui;
+use egui::{Key, RichText, Sense};
+use quip8_core::Chip8;
+
+use crate::ANALAGOUS2_COLOR;
+
+/// A machine value shown in the debugger that can be edited while paused.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Pc,
+    I,
+    V(usize),
+    Delay,
+    Sound,
+    /// An entry of `Chip8::stack`.
+    Stack(usize),
+}
+
+impl Field {
+    fn get(self, chip8: &Chip8) -> u16 {
+        match self {
+            Field::Pc => chip8.pc,
+            Field::I => chip8.i,
+            Field::V(x) => chip8.v[x] as u16,
+            Field::Delay => chip8.delay_timer as u16,
+            Field::Sound => chip8.sound_timer as u16,
+            Field::Stack(index) => chip8.stack[index],
+        }
+    }
+
+    /// Largest value the field can hold. Addresses stay inside memory.
+    fn max(self, chip8: &Chip8) -> u16 {
+        match self {
+            Field::Pc | Field::I | Field::Stack(_) => (chip8.memory.len() - 1) as u16,
+            Field::V(_) | Field::Delay | Field::Sound => 0xFF,
+        }
+    }
+
+    fn set(self, chip8: &mut Chip8, value: u16) {
+        match self {
+            Field::Pc => chip8.pc = value,
+            Field::I => chip8.i = value,
+            Field::V(x) => chip8.v[x] = value as u8,
+            Field::Delay => chip8.delay_timer = value as u8,
+            Field::Sound => chip8.sound_timer = value as u8,
+            Field::Stack(index) => chip8.stack[index] = value,
+        }
+    }
+
+    /// Digits needed for the largest value, so addresses take 3 hex digits
+    /// with 4 KiB of memory and 4 with 64 KiB.
+    fn digits(self, chip8: &Chip8, decimal: bool) -> usize {
+        let max = self.max(chip8);
+        if decimal {
+            max.to_string().len()
+        } else {
+            format!("{max:X}").len()
+        }
+    }
+}
+
+/// Shows register values and lets them be edited in place while paused.
+#[derive(Default)]
+pub struct RegisterEditor {
+    /// Show and type values in decimal rather than hex.
+    pub decimal: bool,
+    /// The field being edited and the text typed so far.
+    editing: Option<(Field, String)>,
+}
+
+impl RegisterEditor {
+    fn format(&self, field: Field, chip8: &Chip8) -> String {
+        let value = field.get(chip8);
+        let width = field.digits(chip8, self.decimal);
+        if self.decimal {
+            format!("{value:>width$}")
+        } else {
+            format!("{value:0width$X}")
+        }
+    }
+
+    /// Parses typed text in the current base; `0x` always means hex.
+    fn parse(&self, text: &str) -> Option<u16> {
+        let text = text.trim();
+        if let Some(hex) = text.strip_prefix("0x") {
+            u16::from_str_radix(hex, 16).ok()
+        } else if self.decimal {
+            text.parse().ok()
+        } else {
+            u16::from_str_radix(text, 16).ok()
+        }
+    }
+
+    /// Draws `field`'s value. While paused, clicking it turns it into a text
+    /// box; Enter or clicking away applies, Escape cancels.
+    pub fn value(&mut self, ui: &mut egui::Ui, chip8: &mut Chip8, field: Field) {
+        if !chip8.paused {
+            self.editing = None;
+        }
+        if let Some((_, text)) = self.editing.as_mut().filter(|(f, _)| *f == field) {
+            let response = ui.add(
+                egui::TextEdit::singleline(text)
+                    .font(egui::TextStyle::Monospace)
+                    .desired_width(32.0),
+            );
+            response.request_focus();
+            if ui.input().key_pressed(Key::Escape) {
+                self.editing = None;
+            } else if response.lost_focus() {
+                let (_, text) = self.editing.take().unwrap();
+                let typed = self.parse(&text);
+                if let Some(new) = typed.filter(|&v| v <= field.max(chip8)) {
+                    field.set(chip8, new);
+                }
+            }
+            return;
+        }
+        let label = ui.add(
+            egui::Label::new(
+                RichText::new(self.format(field, chip8))
+                    .monospace()
+                    .color(ANALAGOUS2_COLOR),
+            )
+            .sense(Sense::click()),
+        );
+        if chip8.paused && label.on_hover_text("Click to edit").clicked() {
+            self.editing = Some((field, self.format(field, chip8).trim().to_owned()));
+        }
+    }
+
+    /// Like [`value`](Self::value) for a stack entry, with the return
+    /// address's label after it.
+    pub fn stack_entry(&mut self, ui: &mut egui::Ui, chip8: &mut Chip8, index: usize, label: &str) {
+        ui.horizontal(|ui| {
+            self.value(ui, chip8, Field::Stack(index));
+            if !label.is_empty() {
+                ui.label(RichText::new(label).monospace().color(ANALAGOUS2_COLOR));
+            }
+        });
+    }
+}